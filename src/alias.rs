@@ -0,0 +1,79 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Walker’s alias method, for drawing from the same weights many times in constant time per draw.
+
+use rand::Rng;
+
+pub struct AliasTable {
+    total: u128,
+    // Column `i` yields `i` if a uniform draw from `0..total` is below `cutoff[i]`, and `alias[i]`
+    // otherwise.
+    cutoff: Vec<u128>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    /// Builds the table using Vose’s algorithm. All arithmetic is done on integers, so the table
+    /// reproduces the weights exactly.
+    ///
+    /// The weights must not all be zero.
+    pub fn new<I>(weights: I) -> AliasTable where I: IntoIterator<Item=u64> {
+        let weights: Vec<u128> = weights.into_iter().map(u128::from).collect();
+        let n = weights.len() as u128;
+        let total = weights.iter().sum::<u128>();
+        assert!(total > 0);
+        // Scale everything up by `n`, so that an average column holds exactly `total`.
+        let mut scaled: Vec<u128> = weights.iter().map(|&w| w * n).collect();
+        let mut cutoff = vec![total; weights.len()];
+        let mut alias: Vec<usize> = (0..weights.len()).collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..weights.len()).partition(|&i| scaled[i] < total);
+        while let (Some(s), Some(l)) = (small.pop(), large.pop()) {
+            cutoff[s] = scaled[s];
+            alias[s] = l;
+            scaled[l] -= total - scaled[s];
+            if scaled[l] < total {
+                small.push(l);
+            } else {
+                large.push(l);
+            }
+        }
+        AliasTable { total, cutoff, alias }
+    }
+
+    /// Draws an index.
+    pub fn sample<R>(&self, rng: &mut R) -> usize where R: Rng {
+        let i = rng.gen_range(0..self.cutoff.len());
+        if rng.gen_range(0..self.total) < self.cutoff[i] {
+            i
+        } else {
+            self.alias[i]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AliasTable;
+
+    #[test]
+    fn columns_add_up() {
+        let weights = [3u64, 0, 1, 7, 1];
+        let table = AliasTable::new(weights.iter().cloned());
+        // Summing each column’s contributions should give back the original weights, scaled up.
+        let mut mass = [0u128; 5];
+        for i in 0..5 {
+            mass[i] += table.cutoff[i];
+            mass[table.alias[i]] += table.total - table.cutoff[i];
+        }
+        for i in 0..5 {
+            assert_eq!(mass[i], weights[i] as u128 * 5);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::RefCell;
+use SchroedingerBox;
+
+/// A mutable memory location holding a value that may still be in superposition, in the style
+/// of `Cell`.
+///
+/// The value is copied out by `get`, which observes it the first time, and can be replaced at any
+/// time with a definite value by `set` or with a fresh superposition by `superpose`, all through
+/// a shared reference.
+///
+/// # Example
+///
+/// ```rust
+/// #[macro_use] extern crate schroedinger_box;
+/// # use schroedinger_box::SchroedingerCell;
+/// struct Tile {
+///     weather: SchroedingerCell<char>,
+/// }
+///
+/// # fn main() {
+/// let tile = Tile { weather: SchroedingerCell::new(superposition!['☀', '☁']) };
+/// let today = tile.weather.get();
+/// assert_eq!(tile.weather.get(), today);
+/// // A new day, new weather.
+/// tile.weather.superpose(superposition![3 => '☀', 1 => '☂']);
+/// tile.weather.set('❄');
+/// assert_eq!(tile.weather.get(), '❄');
+/// # }
+/// ```
+pub struct SchroedingerCell<Cat> {
+    inner: RefCell<SchroedingerBox<Cat>>,
+}
+
+impl<Cat> SchroedingerCell<Cat> where Cat: Copy {
+    /// Creates a new cell holding the superposition in `b`.
+    pub fn new(b: SchroedingerBox<Cat>) -> SchroedingerCell<Cat> {
+        SchroedingerCell {
+            inner: RefCell::new(b),
+        }
+    }
+
+    /// Returns a copy of the value, observing it if it is still in superposition.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the superposition can’t be observed.
+    pub fn get(&self) -> Cat {
+        **self.inner.borrow()
+    }
+
+    /// Replaces the value with a definite one.
+    pub fn set(&self, value: Cat) {
+        self.superpose(SchroedingerBox::new(vec![value]));
+    }
+
+    /// Replaces the value with a fresh superposition, which collapses on the next `get`.
+    pub fn superpose(&self, b: SchroedingerBox<Cat>) {
+        *self.inner.borrow_mut() = b;
+    }
+
+    /// Returns whether the value has collapsed, without observing it.
+    pub fn is_collapsed(&self) -> bool {
+        self.inner.borrow().is_collapsed()
+    }
+
+    /// Consumes the cell, returning the box inside.
+    pub fn into_inner(self) -> SchroedingerBox<Cat> {
+        self.inner.into_inner()
+    }
+}
+
+impl<Cat> From<SchroedingerBox<Cat>> for SchroedingerCell<Cat> where Cat: Copy {
+    fn from(b: SchroedingerBox<Cat>) -> SchroedingerCell<Cat> {
+        SchroedingerCell::new(b)
+    }
+}
+
+impl<Cat> Default for SchroedingerCell<Cat> where Cat: Copy + Default {
+    fn default() -> SchroedingerCell<Cat> {
+        SchroedingerCell::new(SchroedingerBox::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::SchroedingerCell;
+
+    #[test]
+    fn value_semantics() {
+        let cell = SchroedingerCell::new(SchroedingerBox::new(vec![1, 2, 3]));
+        assert!(!cell.is_collapsed());
+        let first = cell.get();
+        assert!(cell.is_collapsed());
+        assert_eq!(cell.get(), first);
+        cell.set(7);
+        assert_eq!(cell.get(), 7);
+        cell.superpose(SchroedingerBox::from_probabilities(vec![(0, 8), (1, 9)]));
+        assert!(!cell.is_collapsed());
+        assert_eq!(cell.get(), 9);
+        assert_eq!(cell.into_inner().into_inner(), 9);
+        assert_eq!(SchroedingerCell::<u8>::default().get(), 0);
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fault injection for chaos testing.
+//!
+//! Each call site wraps its operation in a `FaultInjector`, which holds a superposition of
+//! outcomes: letting the call through, failing it with a given error, or delaying it. Every call
+//! observes a fresh superposition, so each one is decided independently.
+//!
+//! Fault injection is disabled by default and is switched on and off for the whole process with
+//! `enable` and `disable`. While disabled, every call goes straight through.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use std::time::Duration;
+//! # use schroedinger_box::chaos::{self, FaultInjector};
+//! # fn fetch() -> Result<String, String> { Ok("data".to_owned()) }
+//! let flaky_network = FaultInjector::new()
+//!     .pass(90)
+//!     .error(5, "connection reset".to_owned())
+//!     .latency(5, Duration::from_millis(1));
+//!
+//! chaos::enable();
+//! match flaky_network.call(fetch) {
+//!     Ok(data) => assert_eq!(data, "data"),
+//!     Err(e) => assert_eq!(e, "connection reset"),
+//! }
+//! chaos::disable();
+//! assert!(flaky_network.call(fetch).is_ok());
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use choose;
+use rng::local_rng;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enables fault injection for the whole process.
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Disables fault injection for the whole process.
+pub fn disable() {
+    ENABLED.store(false, Ordering::SeqCst);
+}
+
+/// Returns whether fault injection is enabled.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// A possible outcome of a call wrapped in a `FaultInjector`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Fault<E> {
+    /// The call goes through untouched.
+    Pass,
+    /// The call fails with the error, without the operation being run.
+    Error(E),
+    /// The call goes through after the delay.
+    Latency(Duration),
+}
+
+/// A weighted superposition of faults for one call site.
+#[derive(Clone, Debug)]
+pub struct FaultInjector<E> {
+    faults: Vec<(u64, Fault<E>)>,
+}
+
+impl<E> FaultInjector<E> {
+    /// Creates an injector with no faults, which lets every call through.
+    pub fn new() -> FaultInjector<E> {
+        FaultInjector {
+            faults: vec![],
+        }
+    }
+
+    /// Adds a weighted fault.
+    pub fn fault(mut self, weight: u64, fault: Fault<E>) -> FaultInjector<E> {
+        self.faults.push((weight, fault));
+        self
+    }
+
+    /// Adds a weighted chance of letting the call through.
+    pub fn pass(self, weight: u64) -> FaultInjector<E> {
+        self.fault(weight, Fault::Pass)
+    }
+
+    /// Adds a weighted chance of failing the call with `error`.
+    pub fn error(self, weight: u64, error: E) -> FaultInjector<E> {
+        self.fault(weight, Fault::Error(error))
+    }
+
+    /// Adds a weighted chance of delaying the call by `delay`.
+    pub fn latency(self, weight: u64, delay: Duration) -> FaultInjector<E> {
+        self.fault(weight, Fault::Latency(delay))
+    }
+
+    /// Decides the fault for a single call, without running anything.
+    ///
+    /// Returns `Fault::Pass` if fault injection is disabled or there are no faults to choose
+    /// from.
+    pub fn observe(&self) -> Fault<E> where E: Clone {
+        if !is_enabled() || self.faults.iter().all(|&(w, _)| w == 0) {
+            return Fault::Pass
+        }
+        self.faults[choose(&self.faults, &mut local_rng())].1.clone()
+    }
+
+    /// Runs `op`, subject to a freshly decided fault.
+    pub fn call<T, F>(&self, op: F) -> Result<T, E> where F: FnOnce() -> Result<T, E>, E: Clone {
+        match self.observe() {
+            Fault::Pass => op(),
+            Fault::Error(e) => Err(e),
+            Fault::Latency(d) => {
+                thread::sleep(d);
+                op()
+            },
+        }
+    }
+}
+
+impl<E> Default for FaultInjector<E> {
+    fn default() -> FaultInjector<E> {
+        FaultInjector::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::{disable, enable, is_enabled, Fault, FaultInjector};
+
+    // Everything is in one test, since enabling and disabling is process-wide.
+    #[test]
+    fn inject_faults() {
+        let always_fail = FaultInjector::new().pass(0).error(1, "boom");
+        let slow = FaultInjector::<()>::new().latency(1, Duration::from_millis(20));
+        assert!(!is_enabled());
+        assert_eq!(always_fail.call(|| Ok(1)), Ok(1));
+
+        enable();
+        assert_eq!(always_fail.observe(), Fault::Error("boom"));
+        assert_eq!(always_fail.call(|| Ok(1)), Err("boom"));
+        assert_eq!(FaultInjector::new().call(|| Ok::<_, ()>(2)), Ok(2));
+        let start = Instant::now();
+        assert_eq!(slow.call(|| Ok(3)), Ok(3));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        disable();
+        assert_eq!(always_fail.call(|| Ok(1)), Ok(1));
+    }
+}
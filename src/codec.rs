@@ -0,0 +1,183 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error::Error;
+use std::fmt;
+use {SchroedingerBox, SuperpositionError};
+
+/// The ways in which `SchroedingerBox::from_bytes` can fail.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+    /// The bytes ran out in the middle of a box.
+    Truncated,
+    /// A number is too big for a `u64`.
+    Overflow,
+    /// The decoder for the states gave up.
+    InvalidState,
+    /// The states don’t make a box.
+    Superposition(SuperpositionError),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Truncated => f.write_str("encoded box is truncated"),
+            DecodeError::Overflow => f.write_str("encoded box has a number bigger than u64::MAX"),
+            DecodeError::InvalidState => f.write_str("encoded box has an invalid state"),
+            DecodeError::Superposition(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Encodes the box compactly, without observing it, appending the bytes to `out`.
+    ///
+    /// The number of states and each weight are written as variable-length integers (LEB128),
+    /// and each state is written by `encode`. As with serialization, only the states that are
+    /// still possible are encoded, so an observed box decodes as observed. Writing many boxes
+    /// into one buffer needs no other allocation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let b = SchroedingerBox::from_probabilities(vec![(1, 7u8), (300, 9)]);
+    /// let mut frame = vec![];
+    /// b.encode_into(&mut frame, |&x, out| out.push(x));
+    /// assert_eq!(frame, [2, 1, 7, 0xac, 0x02, 9]);
+    ///
+    /// let mut bytes = &frame[..];
+    /// let decoded = SchroedingerBox::from_bytes(&mut bytes, |bytes| {
+    ///     let (&x, rest) = bytes.split_first()?;
+    ///     *bytes = rest;
+    ///     Some(x)
+    /// }).unwrap();
+    /// assert_eq!(decoded.probabilities(), b.probabilities());
+    /// assert!(bytes.is_empty());
+    /// ```
+    pub fn encode_into<F>(&self, out: &mut Vec<u8>, mut encode: F)
+            where F: FnMut(&Cat, &mut Vec<u8>) {
+        let states = self.states();
+        write_varint(out, states.len() as u64);
+        for &(w, ref c) in states {
+            write_varint(out, w);
+            encode(c, out);
+        }
+    }
+
+    /// Like `encode_into`, but returns the bytes in a new `Vec`.
+    pub fn to_bytes<F>(&self, encode: F) -> Vec<u8> where F: FnMut(&Cat, &mut Vec<u8>) {
+        let mut out = vec![];
+        self.encode_into(&mut out, encode);
+        out
+    }
+
+    /// Decodes a box written by `encode_into` from the start of `bytes`, advancing `bytes` past
+    /// it.
+    ///
+    /// `decode` reads one state from the start of the bytes it is given and advances them past
+    /// it, or returns `None` if it can’t. On failure, `bytes` is left somewhere in the middle of
+    /// the box.
+    pub fn from_bytes<F>(bytes: &mut &[u8], mut decode: F)
+            -> Result<SchroedingerBox<Cat>, DecodeError>
+            where F: FnMut(&mut &[u8]) -> Option<Cat> {
+        let len = read_varint(bytes)?;
+        if len == 0 {
+            return Err(DecodeError::Superposition(SuperpositionError::Empty))
+        }
+        // Every state takes at least a byte for its weight, so this can’t be too big.
+        let mut states = Vec::with_capacity((len as usize).min(bytes.len()));
+        for _ in 0..len {
+            let w = read_varint(bytes)?;
+            states.push((w, decode(bytes).ok_or(DecodeError::InvalidState)?));
+        }
+        Ok(SchroedingerBox::from_probabilities(states))
+    }
+}
+
+/// Writes `n` in LEB128: seven bits at a time, least significant first, with the top bit of each
+/// byte set if more follow.
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Reads a number written by `write_varint`, advancing `bytes` past it.
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut n = 0u64;
+    let mut shift = 0;
+    loop {
+        let (&b, rest) = bytes.split_first().ok_or(DecodeError::Truncated)?;
+        *bytes = rest;
+        let bits = u64::from(b & 0x7f);
+        if shift == 63 && bits > 1 || shift > 63 {
+            return Err(DecodeError::Overflow)
+        }
+        n |= bits << shift;
+        if b & 0x80 == 0 {
+            return Ok(n)
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {SchroedingerBox, SuperpositionError};
+    use super::{read_varint, write_varint, DecodeError};
+
+    fn byte(bytes: &mut &[u8]) -> Option<u8> {
+        let (&x, rest) = bytes.split_first()?;
+        *bytes = rest;
+        Some(x)
+    }
+
+    #[test]
+    fn varints() {
+        for &n in &[0, 1, 127, 128, 300, u64::MAX / 3, u64::MAX] {
+            let mut out = vec![];
+            write_varint(&mut out, n);
+            assert_eq!(read_varint(&mut &out[..]), Ok(n));
+        }
+        let mut too_big = vec![0xff; 9];
+        too_big.push(0x02);
+        assert_eq!(read_varint(&mut &too_big[..]), Err(DecodeError::Overflow));
+        assert_eq!(read_varint(&mut &[0x80][..]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn many_boxes_in_one_buffer() {
+        let superposed = SchroedingerBox::from_probabilities(vec![(u64::MAX, 1), (0, 2), (5, 3)]);
+        let observed = SchroedingerBox::new(vec![4, 5, 6]);
+        let state = *observed;
+        let mut frame = vec![];
+        superposed.encode_into(&mut frame, |&x, out| out.push(x));
+        observed.encode_into(&mut frame, |&x, out| out.push(x));
+        let mut bytes = &frame[..];
+        let first = SchroedingerBox::from_bytes(&mut bytes, byte).unwrap();
+        assert_eq!(first.states(), superposed.states());
+        let second = SchroedingerBox::from_bytes(&mut bytes, byte).unwrap();
+        assert!(second.is_collapsed());
+        assert_eq!(*second, state);
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let decode = |bytes: &[u8]| SchroedingerBox::from_bytes(&mut &bytes[..], byte).err();
+        assert_eq!(decode(&[0]), Some(DecodeError::Superposition(SuperpositionError::Empty)));
+        assert_eq!(decode(&[2, 1, 7]), Some(DecodeError::Truncated));
+        assert_eq!(decode(&[1, 1]), Some(DecodeError::InvalidState));
+        assert_eq!(decode(&[]), Some(DecodeError::Truncated));
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use SchroedingerBox;
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Feeds the box’s distribution into `hasher`, without observing it.
+    ///
+    /// Unlike `Hash::hash`, which observes the box and hashes its state, this hashes every state
+    /// that is still possible along with its probability. The order of the states doesn’t matter,
+    /// equal states count as one, and states with a weight of zero are ignored (unless the box has
+    /// only one state, which it always gives whatever its weight), so boxes that collapse the same
+    /// way with the same odds hash the same.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::collections::hash_map::DefaultHasher;
+    /// # use std::hash::Hasher;
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let hash = |b: &SchroedingerBox<char>| {
+    ///     let mut hasher = DefaultHasher::new();
+    ///     b.hash_superposition(&mut hasher);
+    ///     hasher.finish()
+    /// };
+    /// let a = SchroedingerBox::from_probabilities(vec![(1, 'x'), (2, 'y')]);
+    /// let b = SchroedingerBox::from_probabilities(vec![(4, 'y'), (1, 'x'), (1, 'x')]);
+    /// assert_eq!(hash(&a), hash(&b));
+    /// assert!(!a.is_collapsed() && !b.is_collapsed());
+    /// ```
+    pub fn hash_superposition<H>(&self, hasher: &mut H) where Cat: Eq + Hash, H: Hasher {
+        let (distribution, total) = distribution(self.states());
+        // Every state is hashed on its own and the results are added up, so that the order
+        // doesn’t matter.
+        let mut combined = 0u64;
+        for &(c, w) in &distribution {
+            let common = gcd(w, total);
+            let mut h = DefaultHasher::new();
+            c.hash(&mut h);
+            (w / common, total / common).hash(&mut h);
+            combined = combined.wrapping_add(h.finish());
+        }
+        distribution.len().hash(hasher);
+        combined.hash(hasher);
+    }
+
+    /// Returns whether the two boxes have the same distribution, without observing either of
+    /// them.
+    ///
+    /// Unlike `==`, which observes both boxes and compares their states, this compares the states
+    /// that are still possible and their probabilities, in the same way that
+    /// `hash_superposition` hashes them: the order of the states doesn’t matter, equal states
+    /// count as one, and states with a weight of zero are ignored, although a box with only one
+    /// state always gives it, whatever its weight.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let a = SchroedingerBox::from_probabilities(vec![(1, "sword"), (2, "shield")]);
+    /// let b = SchroedingerBox::from_probabilities(vec![(4, "shield"), (2, "sword"), (0, "bow")]);
+    /// assert!(a.superposition_eq(&b));
+    /// assert!(!a.is_collapsed() && !b.is_collapsed());
+    /// ```
+    pub fn superposition_eq(&self, other: &SchroedingerBox<Cat>) -> bool where Cat: PartialEq {
+        let (a, total_a) = distribution(self.states());
+        let (b, total_b) = distribution(other.states());
+        let reduce = |w, total| {
+            let common = gcd(w, total);
+            (w / common, total / common)
+        };
+        a.len() == b.len() && a.iter().all(|&(c, w)| {
+            b.iter().any(|&(d, v)| c == d && reduce(w, total_a) == reduce(v, total_b))
+        })
+    }
+}
+
+/// Merges equal states, adding up their weights and dropping any with a total weight of zero,
+/// and returns them with their total weight.
+///
+/// A single state is certain whatever its weight, so it is given a weight of one.
+fn distribution<Cat>(states: &[(u64, Cat)]) -> (Vec<(&Cat, u128)>, u128) where Cat: PartialEq {
+    if let [(_, ref only)] = *states {
+        return (vec![(only, 1)], 1)
+    }
+    let mut merged: Vec<(&Cat, u128)> = vec![];
+    let mut total = 0;
+    for &(w, ref c) in states.iter().filter(|&&(w, _)| w > 0) {
+        match merged.iter_mut().find(|s| s.0 == c) {
+            Some(s) => s.1 += u128::from(w),
+            None => merged.push((c, u128::from(w))),
+        }
+        total += u128::from(w);
+    }
+    (merged, total)
+}
+
+/// Like `::gcd`, but for weights that have been added up.
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+    use SchroedingerBox;
+
+    fn hash(b: &SchroedingerBox<u32>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        b.hash_superposition(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn hashes_the_distribution() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 1), (3, 2), (0, 3)]);
+        let b = SchroedingerBox::from_probabilities(vec![(6, 2), (2, 1)]);
+        let c = SchroedingerBox::from_probabilities(vec![(3, 1), (1, 2)]);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(hash(&a), hash(&c));
+        assert!(!a.is_collapsed());
+        let observed = *a;
+        assert_eq!(hash(&a), hash(&SchroedingerBox::new(vec![observed])));
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 7)]);
+        assert_eq!(hash(&zero), hash(&SchroedingerBox::new(vec![7])));
+    }
+
+    #[test]
+    fn compares_the_distribution() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 1), (3, 2), (0, 3)]);
+        let b = SchroedingerBox::from_probabilities(vec![(6, 2), (1, 1), (1, 1)]);
+        assert!(a.superposition_eq(&b) && b.superposition_eq(&a));
+        assert!(!a.superposition_eq(&SchroedingerBox::from_probabilities(vec![(3, 1), (1, 2)])));
+        assert!(!a.superposition_eq(&SchroedingerBox::from_probabilities(vec![(1, 1), (3, 3)])));
+        assert!(!a.superposition_eq(&SchroedingerBox::new(vec![1])));
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 1)]);
+        assert!(zero.superposition_eq(&SchroedingerBox::new(vec![1])));
+        assert!(SchroedingerBox::new(vec![1]).superposition_eq(&zero));
+        let huge = SchroedingerBox::from_probabilities(vec![(u64::MAX, 1), (u64::MAX, 1), (2, 2)]);
+        let small = SchroedingerBox::from_probabilities(vec![(u64::MAX, 1), (1, 2)]);
+        assert!(huge.superposition_eq(&small));
+        assert!(!a.is_collapsed() && !huge.is_collapsed());
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use {total_weight, SchroedingerBox, SuperpositionError};
+
+/// A set of named configuration values, each superposed over some weighted alternatives.
+///
+/// Nothing is decided until `resolve` is called, which collapses every value at once and reports
+/// which alternatives were chosen.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerConfig;
+/// let config = SchroedingerConfig::new()
+///     .value("pool_size", vec![(9, 16), (1, 32)])
+///     .value("timeout_ms", vec![(1, 500)])
+///     .resolve();
+/// for resolution in config.report() {
+///     println!("{}", resolution);
+/// }
+/// assert_eq!(config.get("timeout_ms"), Some(&500));
+/// ```
+pub struct SchroedingerConfig<V> {
+    values: Vec<(String, Vec<(u64, V)>)>,
+}
+
+impl<V> SchroedingerConfig<V> {
+    /// Creates an empty configuration.
+    pub fn new() -> SchroedingerConfig<V> {
+        SchroedingerConfig {
+            values: vec![],
+        }
+    }
+
+    /// Adds a value with the given weighted alternatives, replacing any earlier value of the
+    /// same name.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `alternatives.len() == 0`.
+    pub fn value(mut self, name: &str, alternatives: Vec<(u64, V)>) -> SchroedingerConfig<V> {
+        assert!(!alternatives.is_empty());
+        self.values.retain(|(n, _)| n != name);
+        self.values.push((name.to_owned(), alternatives));
+        self
+    }
+
+    /// Collapses every value, in the order they were added.
+    ///
+    /// # Panic
+    ///
+    /// Panics if any value can’t be collapsed; `try_resolve` returns an error instead.
+    pub fn resolve(self) -> ResolvedConfig<V> {
+        self.try_resolve().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `resolve`, but returns an error instead of panicking if the weights of any value’s
+    /// alternatives are all zero, or add up to more than a `u64` can hold.
+    pub fn try_resolve(self) -> Result<ResolvedConfig<V>, SuperpositionError> {
+        let mut values = vec![];
+        let mut report = vec![];
+        for (name, alternatives) in self.values {
+            // A single alternative is always chosen, whatever its weight.
+            let total = match *alternatives {
+                [(w, _)] => w,
+                _ => total_weight(&alternatives)?,
+            };
+            let chosen = SchroedingerBox::from_probabilities(
+                alternatives.iter().enumerate().map(|(i, &(w, _))| (w, i)).collect()
+            ).into_inner();
+            let weight = alternatives[chosen].0;
+            report.push(Resolution {
+                name: name.clone(),
+                chosen,
+                alternatives: alternatives.len(),
+                weight,
+                total_weight: total,
+            });
+            values.push((name, alternatives.into_iter().nth(chosen).unwrap().1));
+        }
+        Ok(ResolvedConfig { values, report })
+    }
+}
+
+impl<V> Default for SchroedingerConfig<V> {
+    fn default() -> SchroedingerConfig<V> {
+        SchroedingerConfig::new()
+    }
+}
+
+/// A configuration whose values have all been decided.
+pub struct ResolvedConfig<V> {
+    values: Vec<(String, V)>,
+    report: Vec<Resolution>,
+}
+
+impl<V> ResolvedConfig<V> {
+    /// Returns the chosen value with the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<&V> {
+        self.values.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /// Returns a report of which alternative was chosen for each value.
+    pub fn report(&self) -> &[Resolution] {
+        &self.report
+    }
+}
+
+/// Which alternative was chosen for a configuration value.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Resolution {
+    /// The name of the value.
+    pub name: String,
+    /// The index of the chosen alternative, in the order they were given.
+    pub chosen: usize,
+    /// How many alternatives there were to choose from.
+    pub alternatives: usize,
+    /// The weight of the chosen alternative.
+    pub weight: u64,
+    /// The sum of the weights of all the alternatives.
+    pub total_weight: u64,
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: chose alternative {} of {} (weight {}/{})",
+               self.name, self.chosen + 1, self.alternatives, self.weight, self.total_weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SuperpositionError;
+    use super::SchroedingerConfig;
+
+    #[test]
+    fn resolve_and_report() {
+        let config = SchroedingerConfig::new()
+            .value("a", vec![(0, "x"), (2, "y")])
+            .value("b", vec![(1, "z")])
+            .value("a", vec![(1, "w"), (0, "v")])
+            .resolve();
+        assert_eq!(config.get("a"), Some(&"w"));
+        assert_eq!(config.get("b"), Some(&"z"));
+        assert_eq!(config.get("c"), None);
+        let report: Vec<String> = config.report().iter().map(|r| r.to_string()).collect();
+        assert_eq!(report, vec!["b: chose alternative 1 of 1 (weight 1/1)",
+                                "a: chose alternative 1 of 2 (weight 1/1)"]);
+    }
+
+    #[test]
+    fn unresolvable() {
+        let overflowing = SchroedingerConfig::new()
+            .value("a", vec![(u64::MAX, 'x'), (1, 'y')])
+            .try_resolve();
+        assert_eq!(overflowing.err(), Some(SuperpositionError::WeightOverflow));
+        let zero = SchroedingerConfig::new().value("a", vec![(0, 'x'), (0, 'y')]).try_resolve();
+        assert_eq!(zero.err(), Some(SuperpositionError::ZeroTotalWeight));
+        let only = SchroedingerConfig::new().value("a", vec![(0, 'x')]).resolve();
+        assert_eq!(only.get("a"), Some(&'x'));
+        assert_eq!(only.report()[0].total_weight, 0);
+    }
+}
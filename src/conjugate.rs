@@ -0,0 +1,213 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Pairs of conjugate observables, which can’t both be known precisely at once.
+//!
+//! A `Conjugate` describes two numeric projections of a state, like the position and momentum of
+//! a particle. Measuring a box in the basis of one of them collapses it, and then spreads the
+//! other one out again into a superposition: the more precise the measurement, the wider the
+//! spread, as given by the uncertainty relation.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use schroedinger_box::SchroedingerBox;
+//! # use schroedinger_box::conjugate::{Basis, Conjugate};
+//! #[derive(Clone)]
+//! struct Particle { position: f64, momentum: f64 }
+//!
+//! let pair = Conjugate::new(|p: &Particle| p.position, |p, x| p.position = x,
+//!                           |p: &Particle| p.momentum, |p, x| p.momentum = x);
+//! let mut particle = SchroedingerBox::new(vec![Particle { position: 0.0, momentum: 1.0 }]);
+//! let x = particle.measure_in_basis(&pair, Basis::First, 0.01).unwrap();
+//! assert_eq!(x, 0.0);
+//! // The momentum is now spread out widely around where it was.
+//! let p = particle.momentum;
+//! assert!((p - 1.0).abs() <= 50.0);
+//! ```
+
+use {SchroedingerBox, SuperpositionError};
+
+/// Which of a pair of conjugate observables to measure.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Basis {
+    /// The first observable given to `Conjugate::new`.
+    First,
+    /// The second observable given to `Conjugate::new`.
+    Second,
+}
+
+/// A pair of conjugate observables of states of type `S`.
+pub struct Conjugate<S> {
+    first: Projection<S>,
+    second: Projection<S>,
+    relation: Box<dyn Fn(f64) -> f64>,
+    resolution: usize,
+}
+
+type Getter<S> = Box<dyn Fn(&S) -> f64>;
+type Setter<S> = Box<dyn Fn(&mut S, f64)>;
+
+/// A way of reading and writing one observable of a state.
+struct Projection<S> {
+    get: Getter<S>,
+    set: Setter<S>,
+}
+
+impl<S> Conjugate<S> {
+    /// Creates a pair of conjugate observables from a getter and a setter for each of them.
+    ///
+    /// By default, the uncertainty relation is Heisenberg’s with ħ = 1: measuring one observable
+    /// with precision `σ` spreads the other out by `1 / (2σ)`.
+    pub fn new<G1, S1, G2, S2>(get_first: G1, set_first: S1, get_second: G2, set_second: S2)
+            -> Conjugate<S>
+            where G1: Fn(&S) -> f64 + 'static, S1: Fn(&mut S, f64) + 'static,
+                  G2: Fn(&S) -> f64 + 'static, S2: Fn(&mut S, f64) + 'static {
+        Conjugate {
+            first: Projection {
+                get: Box::new(get_first),
+                set: Box::new(set_first),
+            },
+            second: Projection {
+                get: Box::new(get_second),
+                set: Box::new(set_second),
+            },
+            relation: Box::new(|precision| 0.5 / precision),
+            resolution: 4,
+        }
+    }
+
+    /// Sets the uncertainty relation, which gives the spread of one observable after measuring
+    /// the other with a given precision.
+    pub fn relation<F>(mut self, relation: F) -> Conjugate<S> where F: Fn(f64) -> f64 + 'static {
+        self.relation = Box::new(relation);
+        self
+    }
+
+    /// Sets how many values on either side of the old value a spread-out observable can take.
+    ///
+    /// The default is 4, giving 9 values in all. The values are spaced evenly across the spread,
+    /// with binomial weights approximating a normal distribution.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `resolution > 30`, since the weights would overflow.
+    pub fn resolution(mut self, resolution: usize) -> Conjugate<S> {
+        assert!(resolution <= 30);
+        self.resolution = resolution;
+        self
+    }
+
+    fn projections(&self, basis: Basis) -> (&Projection<S>, &Projection<S>) {
+        match basis {
+            Basis::First => (&self.first, &self.second),
+            Basis::Second => (&self.second, &self.first),
+        }
+    }
+}
+
+impl<S> SchroedingerBox<S> where S: Clone {
+    /// Observes the box, measuring one of a pair of conjugate observables with the given
+    /// precision, and returns the measured value.
+    ///
+    /// The other observable is then spread out into a superposition around its observed value,
+    /// which changes the box’s weights: this fails if they are frozen, without observing the box.
+    /// The box collapses anew the next time it is observed, calling any `on_collapse` callbacks
+    /// registered in the meantime.
+    pub fn measure_in_basis(&mut self, pair: &Conjugate<S>, basis: Basis, precision: f64)
+            -> Result<f64, SuperpositionError> {
+        self.check_thawed()?;
+        let (measured, other) = pair.projections(basis);
+        let state = self.try_get()?.clone();
+        let value = (measured.get)(&state);
+        let spread = (pair.relation)(precision);
+        let n = pair.resolution;
+        let centre = (other.get)(&state);
+        let mut weight = 1u64;
+        let mut states = Vec::with_capacity(2 * n + 1);
+        for k in 0..2 * n + 1 {
+            let mut s = state.clone();
+            if n > 0 {
+                (other.set)(&mut s, centre + spread * (k as f64 - n as f64) / n as f64);
+            }
+            states.push((weight, s));
+            // The next binomial coefficient, C(2n, k + 1).
+            weight = weight * (2 * n - k) as u64 / (k + 1) as u64;
+        }
+        if n == 0 || spread == 0.0 {
+            // Nothing is spread out, so the box stays collapsed.
+            states.truncate(1);
+            (other.set)(&mut states[0].1, centre);
+            *self.states_mut() = states;
+        } else {
+            self.respread(states);
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::{Basis, Conjugate};
+
+    fn pair() -> Conjugate<(f64, f64)> {
+        Conjugate::new(|s: &(f64, f64)| s.0, |s, x| s.0 = x, |s: &(f64, f64)| s.1, |s, x| s.1 = x)
+    }
+
+    #[test]
+    fn spreads_the_other_observable() {
+        let mut b = SchroedingerBox::new(vec![(1.0, 2.0), (3.0, 4.0)]);
+        let x = b.measure_in_basis(&pair().resolution(2), Basis::First, 0.25).unwrap();
+        assert!(x == 1.0 || x == 3.0);
+        let centre = x + 1.0;
+        assert_eq!(b.states(), &[(1, (x, centre - 2.0)), (4, (x, centre - 1.0)), (6, (x, centre)),
+                                 (4, (x, centre + 1.0)), (1, (x, centre + 2.0))][..]);
+        // Measuring the same thing again gives the same answer (spreading the other one out
+        // further)...
+        assert_eq!(b.measure_in_basis(&pair(), Basis::First, 0.25).unwrap(), x);
+        // ...but measuring the other one spreads the first one out in turn.
+        let p = b.measure_in_basis(&pair().relation(|_| 0.0), Basis::Second, 0.25).unwrap();
+        assert!((p - centre).abs() <= 4.0);
+        assert_eq!(b.states(), &[(1, (x, p))][..]);
+    }
+
+    #[test]
+    fn collapses_anew() {
+        use std::sync::mpsc::channel;
+
+        let mut b = SchroedingerBox::new(vec![(1.0, 2.0), (3.0, 4.0)]);
+        let (tx, rx) = channel();
+        b.on_collapse(move |s| tx.send(*s).unwrap());
+        b.measure_in_basis(&pair(), Basis::First, 0.25).unwrap();
+        assert!(rx.try_recv().is_ok());
+        assert!(!b.is_collapsed());
+        assert_eq!(b.collapsed_at(), None);
+
+        let (tx, rx) = channel();
+        b.on_collapse(move |s| tx.send(*s).unwrap());
+        assert!(rx.try_recv().is_err());
+        b.measure_in_basis(&pair(), Basis::Second, 0.25).unwrap();
+        assert!(rx.try_recv().is_ok());
+        assert_eq!(b.collapsed_at(), None);
+        let _ = *b;
+        assert!(b.collapsed_at().is_some());
+        assert_eq!(b.observation_count(), 3);
+    }
+
+    #[test]
+    fn frozen_boxes_are_left_alone() {
+        use {Observable, SuperpositionError};
+
+        let mut b = SchroedingerBox::new(vec![(0.0, 0.0), (1.0, 1.0)]);
+        b.probability_of(&(0.0, 0.0));
+        assert_eq!(b.measure_in_basis(&pair(), Basis::Second, 1.0),
+                   Err(SuperpositionError::WeightsFrozen));
+        assert_eq!(b.peek(), None);
+    }
+}
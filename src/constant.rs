@@ -0,0 +1,92 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use pick;
+use seed::mix;
+
+/// Collapses a superposition at compile time, expanding to the chosen state.
+///
+/// The first argument is a seed, and the second a list of states, each of which may be given a
+/// weight with `weight => state` (the default weight is 1). Everything must be a constant, and the
+/// states must be `Copy`. The same seed and states always give the same result, so the
+/// superposition is decided once per build; a box that can’t be observed is a compile error.
+///
+/// The states are weighted just as they are in a box at run time, but the seed is hashed in a way
+/// of its own: `collapse!(seed, ..)` won’t generally choose the same state as a box made with
+/// `SchroedingerBox::with_seed` and the same seed.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate schroedinger_box;
+/// # fn main() {
+/// const GREETING: &str = collapse!(2016, [9 => "Hello!", 1 => "Meow!"]);
+/// assert!(GREETING == "Hello!" || GREETING == "Meow!");
+/// assert_eq!(collapse!(2016, [9 => "Hello!", 1 => "Meow!"]), GREETING);
+/// let lucky: u8 = collapse!(7, [1, 2, 3, 4, 5, 6]);
+/// assert!(1 <= lucky && lucky <= 6);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! collapse {
+    ($seed:expr, [$($w:expr => $v:expr),+ $(,)*]) => {
+        const { $crate::__collapse($seed, &[$(($w, $v)),+]) }
+    };
+    ($seed:expr, [$($v:expr),+ $(,)*]) => {
+        const { $crate::__collapse($seed, &[$((1, $v)),+]) }
+    };
+}
+
+/// Chooses one of the states with the given seed, as `collapse!` does.
+///
+/// # Panic
+///
+/// Panics if no state can be chosen, which is a compile error when called from `collapse!`.
+#[doc(hidden)]
+pub const fn collapse<Cat>(seed: u64, states: &[(u64, Cat)]) -> Cat where Cat: Copy {
+    let mut total = 0u64;
+    let mut i = 0;
+    while i < states.len() {
+        total = match total.checked_add(states[i].0) {
+            Some(total) => total,
+            None => panic!("the total weight of the states is too big to fit in a `u64`"),
+        };
+        i += 1;
+    }
+    if states.len() == 1 {
+        return states[0].1
+    }
+    if total == 0 {
+        panic!("all the states have a weight of zero");
+    }
+    // Scales the seed’s hash down to the total weight. Unlike `gen_range` nothing is rejected,
+    // so some values of `r` are very slightly more likely than others.
+    let r = ((mix(seed) as u128 * total as u128) >> 64) as u64;
+    states[pick(states, r)].1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::collapse;
+
+    #[test]
+    fn decided_at_compile_time() {
+        const CHOSEN: u32 = collapse!(12345, [10, 20, 30]);
+        assert!([10, 20, 30].contains(&CHOSEN));
+        assert_eq!(collapse!(12345, [10, 20, 30]), CHOSEN);
+        assert_eq!(collapse!(0, [0 => 'a', 0 => 'b', 5 => 'c',]), 'c');
+        assert_eq!(collapse!(0, [0 => "only"]), "only");
+    }
+
+    #[test]
+    fn weighted_like_a_box() {
+        let states = [(1, 'a'), (3, 'b')];
+        let b = (0..10_000).filter(|&seed| collapse(seed, &states) == 'b').count();
+        assert!(7_000 < b && b < 8_000);
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A registry of every box that is alive, for finding out how much is still undetermined.
+//!
+//! This module only exists with the `debug` feature. Without it, boxes don’t keep track of
+//! anything and cost nothing extra.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use schroedinger_box::{debug, SchroedingerBox};
+//! let weather = SchroedingerBox::new(vec!["rain", "sun"]);
+//! let boxes = debug::dump();
+//! let open = boxes.iter().filter(|b| b.type_name == "&str" && !b.collapsed).count();
+//! assert!(open >= 1);
+//! # drop(weather);
+//! ```
+
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::Instant;
+
+/// What the registry knows about a live box.
+#[derive(Clone, Debug)]
+pub struct BoxInfo {
+    /// A number identifying the box, which is never reused. Later boxes have bigger numbers.
+    pub id: u64,
+    /// The type of the box’s states.
+    pub type_name: &'static str,
+    /// How many states the box could still collapse into, as of the last time it was touched.
+    pub states: usize,
+    /// Whether the box has been observed, or holds only one state and can’t panic.
+    pub collapsed: bool,
+    /// When the box was created.
+    pub created: Instant,
+}
+
+/// Returns the boxes that are alive right now, on any thread, in the order they were created.
+pub fn dump() -> Vec<BoxInfo> {
+    registry().iter().map(|(&id, status)| BoxInfo {
+        id,
+        type_name: status.type_name,
+        states: status.states.load(Relaxed),
+        collapsed: status.collapsed.load(Relaxed),
+        created: status.created,
+    }).collect()
+}
+
+static REGISTRY: Mutex<BTreeMap<u64, Arc<Status>>> = Mutex::new(BTreeMap::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn registry() -> MutexGuard<'static, BTreeMap<u64, Arc<Status>>> {
+    // The registry is never left half-updated, so a panic elsewhere doesn’t matter.
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+struct Status {
+    type_name: &'static str,
+    states: AtomicUsize,
+    collapsed: AtomicBool,
+    created: Instant,
+}
+
+/// A box’s entry in the registry, which is removed when it is dropped.
+pub(crate) struct Tracker {
+    id: u64,
+    status: Arc<Status>,
+}
+
+impl Tracker {
+    pub(crate) fn new<Cat>() -> Tracker {
+        let id = NEXT_ID.fetch_add(1, Relaxed);
+        let status = Arc::new(Status {
+            type_name: type_name::<Cat>(),
+            states: AtomicUsize::new(0),
+            collapsed: AtomicBool::new(false),
+            created: Instant::now(),
+        });
+        registry().insert(id, status.clone());
+        Tracker {
+            id,
+            status,
+        }
+    }
+
+    pub(crate) fn update(&self, states: usize, collapsed: bool) {
+        self.status.states.store(states, Relaxed);
+        self.status.collapsed.store(collapsed, Relaxed);
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        registry().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::{dump, BoxInfo};
+
+    #[derive(Clone, PartialEq, Debug)]
+    struct Marker(u8);
+
+    fn mine() -> Vec<BoxInfo> {
+        dump().into_iter().filter(|b| b.type_name.ends_with("Marker")).collect()
+    }
+
+    #[test]
+    fn tracks_live_boxes() {
+        let mut a = SchroedingerBox::new(vec![Marker(0), Marker(1), Marker(2)]);
+        let b = SchroedingerBox::new(vec![Marker(3)]);
+        let boxes = mine();
+        assert_eq!(boxes.len(), 2);
+        assert!(boxes[0].id < boxes[1].id && boxes[0].created <= boxes[1].created);
+        assert_eq!((boxes[0].states, boxes[0].collapsed), (3, false));
+        assert_eq!((boxes[1].states, boxes[1].collapsed), (1, true));
+
+        a.retain(|m| m.0 != 0).unwrap();
+        let _ = *a;
+        assert_eq!((mine()[0].states, mine()[0].collapsed), (1, true));
+        drop(b);
+        assert_eq!(mine().len(), 1);
+        let c = a.clone();
+        drop(a);
+        assert_eq!(mine().len(), 1);
+        drop(c);
+        assert!(mine().is_empty());
+    }
+}
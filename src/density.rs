@@ -0,0 +1,402 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Mixed states, enabled by the `num-complex` feature.
+//!
+//! A `SchroedingerBox` is a classical mixture: it is in one of its states, and only the odds of
+//! which are known. A `Qubit` or `QuantumRegister` is a pure state: it is in a superposition of
+//! its states, with a phase for each. A `DensityMatrix` can be either, or anything in between,
+//! such as not knowing which of several superpositions was prepared.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use schroedinger_box::SchroedingerBox;
+//! # use schroedinger_box::density::DensityMatrix;
+//! # use schroedinger_box::quantum::QuantumRegister;
+//! let mut plus = QuantumRegister::<1>::new();
+//! plus.hadamard(0);
+//! let superposed = DensityMatrix::from(plus);
+//! let mixed = DensityMatrix::from(SchroedingerBox::new(vec![vec![false], vec![true]]));
+//! // Both give 0 or 1 with even odds, but only one of them is sure of its state.
+//! assert!((superposed.purity() - 1.0).abs() < 1e-12);
+//! assert!((mixed.purity() - 0.5).abs() < 1e-12);
+//! ```
+
+use num_complex::Complex64;
+use quantum::QuantumRegister;
+use {SchroedingerBox, SuperpositionError};
+
+/// A mixed state: a probability distribution over superpositions of some states.
+///
+/// The matrix has a row and a column for each state. Its diagonal holds the probability of
+/// measuring each state, and the rest says how much the states can still interfere.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DensityMatrix<Cat> {
+    states: Vec<Cat>,
+    rho: Vec<Vec<Complex64>>,
+}
+
+impl<Cat> DensityMatrix<Cat> {
+    /// Creates a pure state: a superposition of `states`, each with the given amplitude.
+    ///
+    /// The amplitudes are scaled so that the probabilities add up to one.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the amplitudes are all zero, or any isn’t finite.
+    pub fn pure(states: Vec<(Complex64, Cat)>) -> DensityMatrix<Cat> {
+        let (amplitudes, states) = states.into_iter().unzip();
+        DensityMatrix::from_ensemble(states, &[(1.0, amplitudes)])
+    }
+
+    /// Creates a mixed state over `states` from an ensemble of pure states: each element of
+    /// `ensemble` has the odds of that pure state having been prepared, and the amplitude of each
+    /// of `states` in it.
+    ///
+    /// The odds, and the amplitudes of each pure state, are scaled so that they add up to one.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `states.len() == 0`, if any pure state doesn’t have one amplitude for each of
+    /// `states`, if the odds are all zero, or if any pure state has no finite, non-zero
+    /// amplitude or any odds are negative or not finite.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate num_complex;
+    /// # extern crate schroedinger_box;
+    /// # use num_complex::Complex64;
+    /// # use schroedinger_box::density::DensityMatrix;
+    /// # fn main() {
+    /// let (one, i) = (Complex64::new(1.0, 0.0), Complex64::i());
+    /// // Someone prepared |0⟩ + |1⟩ or |0⟩ + i|1⟩, but didn’t say which.
+    /// let rho = DensityMatrix::from_ensemble(vec![0, 1], &[(0.5, vec![one, one]),
+    ///                                                      (0.5, vec![one, i])]);
+    /// assert!((rho.purity() - 0.75).abs() < 1e-12);
+    /// # }
+    /// ```
+    pub fn from_ensemble(states: Vec<Cat>, ensemble: &[(f64, Vec<Complex64>)])
+            -> DensityMatrix<Cat> {
+        let n = states.len();
+        assert!(n != 0, "{}", SuperpositionError::Empty);
+        let total: f64 = ensemble.iter().map(|&(p, _)| p).sum();
+        assert!(ensemble.iter().all(|&(p, _)| p >= 0.0) && total > 0.0 && total.is_finite(),
+                "the odds of an ensemble must be finite, non-negative and not all zero");
+        let mut rho = vec![vec![Complex64::new(0.0, 0.0); n]; n];
+        for &(p, ref amplitudes) in ensemble {
+            assert_eq!(amplitudes.len(), n, "a pure state needs an amplitude for each state");
+            let norm: f64 = amplitudes.iter().map(|a| a.norm_sqr()).sum();
+            assert!(norm > 0.0 && norm.is_finite(),
+                    "a pure state needs a finite, non-zero amplitude");
+            let p = p / total / norm;
+            for (row, a) in rho.iter_mut().zip(amplitudes) {
+                for (x, b) in row.iter_mut().zip(amplitudes) {
+                    *x += a * b.conj() * p;
+                }
+            }
+        }
+        DensityMatrix {
+            states,
+            rho,
+        }
+    }
+
+    /// Returns the states, in the order of the rows and columns of the matrix.
+    pub fn states(&self) -> &[Cat] {
+        &self.states
+    }
+
+    /// Returns the matrix, as a list of rows.
+    pub fn matrix(&self) -> &[Vec<Complex64>] {
+        &self.rho
+    }
+
+    /// Returns the probability of measuring each state, without measuring it.
+    pub fn probabilities(&self) -> Vec<(f64, &Cat)> {
+        self.rho.iter().enumerate().map(|(i, row)| row[i].re).zip(&self.states).collect()
+    }
+
+    /// Returns the purity, `tr(ρ²)`: one for a pure state, and down to `1 / n` for an even
+    /// mixture of `n` states.
+    pub fn purity(&self) -> f64 {
+        self.rho.iter().flat_map(|row| row.iter().map(|x| x.norm_sqr())).sum()
+    }
+
+    /// Returns the von Neumann entropy, `-tr(ρ ln ρ)`, in nats.
+    ///
+    /// This is zero for a pure state, whatever its superposition, and the Shannon entropy of the
+    /// odds for a classical mixture.
+    pub fn von_neumann_entropy(&self) -> f64 {
+        // The eigenvalues of a Hermitian matrix `A + iB` are those of the real symmetric matrix
+        // `[[A, -B], [B, A]]`, each appearing twice.
+        let n = self.rho.len();
+        let mut m = vec![vec![0.0; 2 * n]; 2 * n];
+        for (i, row) in self.rho.iter().enumerate() {
+            for (j, x) in row.iter().enumerate() {
+                m[i][j] = x.re;
+                m[i + n][j + n] = x.re;
+                m[i][j + n] = -x.im;
+                m[i + n][j] = x.im;
+            }
+        }
+        let entropy: f64 = symmetric_eigenvalues(m).into_iter()
+            .filter(|&p| p > 1e-15)
+            .map(|p| -p * p.ln())
+            .sum();
+        entropy / 2.0
+    }
+
+    /// Measures the state, consuming it and returning the state it was measured in.
+    pub fn measure(self) -> Cat {
+        let weights = self.rho.iter().enumerate().map(|(i, row)| row[i].re.max(0.0));
+        SchroedingerBox::from_f64_weights(weights.zip(self.states).collect()).into_inner()
+    }
+}
+
+impl<A, B> DensityMatrix<(A, B)> {
+    /// Takes the partial trace over the second half of each state, leaving the reduced state of
+    /// the first half.
+    ///
+    /// This is what is left to be seen of `A` by someone who can’t see `B` at all. Tracing out
+    /// half of an entangled pair leaves a mixed state, even if the pair was pure.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// # use schroedinger_box::density::DensityMatrix;
+    /// let (coin, die) = (SchroedingerBox::new(vec!['H', 'T']), SchroedingerBox::new(vec![1, 2]));
+    /// let joint = DensityMatrix::from(coin.zip(die));
+    /// let coin = joint.trace_out_second();
+    /// assert_eq!(coin.probabilities(), vec![(0.5, &'H'), (0.5, &'T')]);
+    /// ```
+    pub fn trace_out_second(self) -> DensityMatrix<A> where A: PartialEq, B: PartialEq {
+        let (first, second): (_, Vec<_>) = self.states.into_iter().unzip();
+        trace_out(first, &second, &self.rho)
+    }
+
+    /// Takes the partial trace over the first half of each state, leaving the reduced state of
+    /// the second half, as `trace_out_second` does.
+    pub fn trace_out_first(self) -> DensityMatrix<B> where A: PartialEq, B: PartialEq {
+        let (first, second): (Vec<_>, _) = self.states.into_iter().unzip();
+        trace_out(second, &first, &self.rho)
+    }
+}
+
+impl<Cat> From<SchroedingerBox<Cat>> for DensityMatrix<Cat> {
+    /// Makes a classical mixture with the box’s odds, without observing it.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    fn from(b: SchroedingerBox<Cat>) -> DensityMatrix<Cat> {
+        let states = b.into_states();
+        let n = states.len();
+        // A single state is certain, whatever its weight.
+        let total = match n {
+            1 => states[0].0 as f64,
+            _ => ::total_weight(&states).unwrap_or_else(|e| panic!("{}", e)) as f64,
+        };
+        let mut rho = vec![vec![Complex64::new(0.0, 0.0); n]; n];
+        let states = states.into_iter().enumerate().map(|(i, (w, c))| {
+            rho[i][i] = Complex64::new(if n == 1 { 1.0 } else { w as f64 / total }, 0.0);
+            c
+        }).collect();
+        DensityMatrix {
+            states,
+            rho,
+        }
+    }
+}
+
+impl<const N: usize> From<QuantumRegister<N>> for DensityMatrix<Vec<bool>> {
+    /// Makes the pure state of a register, whose states are the values of its qubits.
+    fn from(reg: QuantumRegister<N>) -> DensityMatrix<Vec<bool>> {
+        let states = (0..1usize << N).map(|index| (0..N).map(|i| index >> i & 1 == 1).collect());
+        DensityMatrix::from_ensemble(states.collect(), &[(1.0, reg.amplitudes().to_vec())])
+    }
+}
+
+impl<const N: usize> QuantumRegister<N> {
+    /// Takes the partial trace over `qubits`, leaving the reduced state of the rest of the
+    /// register, without measuring anything.
+    ///
+    /// The states of the result are the values of the qubits that are left, in order.
+    ///
+    /// # Panic
+    ///
+    /// Panics if any of `qubits` is out of range.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::quantum::QuantumRegister;
+    /// let mut bell = QuantumRegister::<2>::new();
+    /// bell.hadamard(0);
+    /// bell.cnot(0, 1);
+    /// // On its own, either half of a Bell pair is as mixed as a qubit can be.
+    /// let half = bell.partial_trace(&[1]);
+    /// assert!((half.purity() - 0.5).abs() < 1e-12);
+    /// ```
+    pub fn partial_trace(&self, qubits: &[usize]) -> DensityMatrix<Vec<bool>> {
+        let mut traced = 0;
+        for &i in qubits {
+            assert!(i < N, "qubit {} is out of range for a register of {}", i, N);
+            traced |= 1 << i;
+        }
+        let kept: Vec<usize> = (0..N).filter(|&i| traced & 1 << i == 0).collect();
+        let reduced = |index: usize| -> usize {
+            kept.iter().enumerate().map(|(k, &i)| (index >> i & 1) << k).sum()
+        };
+        let n = 1 << kept.len();
+        let mut rho = vec![vec![Complex64::new(0.0, 0.0); n]; n];
+        let amplitudes = self.amplitudes();
+        for (i, a) in amplitudes.iter().enumerate() {
+            for (j, b) in amplitudes.iter().enumerate().filter(|&(j, _)| i & traced == j & traced) {
+                rho[reduced(i)][reduced(j)] += a * b.conj();
+            }
+        }
+        let states = (0..n).map(|index| (0..kept.len()).map(|k| index >> k & 1 == 1).collect());
+        DensityMatrix {
+            states: states.collect(),
+            rho,
+        }
+    }
+}
+
+/// Sums `rho` over the states that agree on `traced`, leaving a matrix over `kept`.
+///
+/// The states of the result are the distinct values of `kept`, in the order they first appear.
+fn trace_out<A, B>(kept: Vec<A>, traced: &[B], rho: &[Vec<Complex64>]) -> DensityMatrix<A>
+        where A: PartialEq, B: PartialEq {
+    let mut states: Vec<A> = Vec::new();
+    let mut index = Vec::with_capacity(kept.len());
+    for a in kept {
+        match states.iter().position(|s| *s == a) {
+            Some(i) => index.push(i),
+            None => {
+                index.push(states.len());
+                states.push(a);
+            },
+        }
+    }
+    let n = states.len();
+    let mut reduced = vec![vec![Complex64::new(0.0, 0.0); n]; n];
+    for (i, row) in rho.iter().enumerate() {
+        for (j, x) in row.iter().enumerate().filter(|&(j, _)| traced[i] == traced[j]) {
+            reduced[index[i]][index[j]] += x;
+        }
+    }
+    DensityMatrix {
+        states,
+        rho: reduced,
+    }
+}
+
+/// Finds the eigenvalues of a real symmetric matrix, by Jacobi’s method.
+fn symmetric_eigenvalues(mut a: Vec<Vec<f64>>) -> Vec<f64> {
+    let n = a.len();
+    for _ in 0..100 {
+        let off: f64 = (0..n).flat_map(|p| (0..n).filter(move |&q| q != p).map(move |q| (p, q)))
+            .map(|(p, q)| a[p][q] * a[p][q])
+            .sum();
+        if off < 1e-30 {
+            break
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue
+                }
+                // Rotate rows and columns `p` and `q` to make `a[p][q]` zero.
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in &mut a {
+                    let (x, y) = (row[p], row[q]);
+                    row[p] = c * x - s * y;
+                    row[q] = s * x + c * y;
+                }
+                let (above, below) = a.split_at_mut(q);
+                for (x, y) in above[p].iter_mut().zip(&mut below[0]) {
+                    let (u, v) = (*x, *y);
+                    *x = c * u - s * v;
+                    *y = s * u + c * v;
+                }
+            }
+        }
+    }
+    (0..n).map(|i| a[i][i]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex64;
+    use quantum::QuantumRegister;
+    use SchroedingerBox;
+    use super::DensityMatrix;
+
+    #[test]
+    fn pure_and_mixed() {
+        let (one, i) = (Complex64::new(1.0, 0.0), Complex64::i());
+        let pure = DensityMatrix::pure(vec![(one, 'a'), (i, 'b'), (-one, 'c')]);
+        assert!((pure.purity() - 1.0).abs() < 1e-12);
+        assert!(pure.von_neumann_entropy().abs() < 1e-9);
+        assert!(pure.probabilities().iter().all(|&(p, _)| (p - 1.0 / 3.0).abs() < 1e-12));
+        assert!((pure.matrix()[0][1] - -i / 3.0).norm() < 1e-12);
+        let mixed = DensityMatrix::from(SchroedingerBox::from_probabilities(vec![(1, 'a'),
+                                                                                  (3, 'b')]));
+        assert_eq!(mixed.probabilities(), vec![(0.25, &'a'), (0.75, &'b')]);
+        assert!((mixed.purity() - 0.625).abs() < 1e-12);
+        let shannon = -(0.25f64 * 0.25f64.ln() + 0.75 * 0.75f64.ln());
+        assert!((mixed.von_neumann_entropy() - shannon).abs() < 1e-9);
+        let observed = SchroedingerBox::new(vec!['x', 'y']);
+        let x = *observed;
+        assert_eq!(DensityMatrix::from(observed).measure(), x);
+    }
+
+    #[test]
+    fn registers() {
+        let mut reg = QuantumRegister::<2>::new();
+        reg.pauli_x(1);
+        reg.hadamard(0);
+        let rho = DensityMatrix::from(reg);
+        assert_eq!(rho.states()[2], vec![false, true]);
+        assert!((rho.purity() - 1.0).abs() < 1e-12);
+        assert!(rho.von_neumann_entropy().abs() < 1e-9);
+        assert!(rho.measure()[1]);
+    }
+
+    #[test]
+    fn partial_traces() {
+        let mut bell = QuantumRegister::<2>::new();
+        bell.hadamard(0);
+        bell.cnot(0, 1);
+        let one = Complex64::new(1.0, 0.0);
+        let pair = DensityMatrix::pure(vec![(one, ('a', 0)), (one, ('b', 1))]);
+        let first = pair.clone().trace_out_second();
+        assert_eq!(first.states(), &['a', 'b'][..]);
+        assert!((first.purity() - 0.5).abs() < 1e-12);
+        assert_eq!(pair.trace_out_first().states(), &[0, 1][..]);
+        for half in [bell.partial_trace(&[0]), bell.partial_trace(&[1])] {
+            assert!((half.purity() - 0.5).abs() < 1e-12);
+            assert!((half.von_neumann_entropy() - 2f64.ln()).abs() < 1e-9);
+            assert!(half.matrix()[0][1].norm() < 1e-12);
+        }
+        let mut product = QuantumRegister::<3>::new();
+        product.hadamard(0);
+        product.pauli_x(2);
+        let rest = product.partial_trace(&[1]);
+        assert_eq!(rest.states()[2], vec![false, true]);
+        assert!((rest.purity() - 1.0).abs() < 1e-12);
+        assert_eq!(bell.partial_trace(&[]).states(), DensityMatrix::from(bell).states());
+    }
+}
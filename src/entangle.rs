@@ -0,0 +1,305 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use {SchroedingerBox, SuperpositionError};
+
+/// A box that is entangled with others, so that observing any of them decides all of them.
+///
+/// The boxes share a joint superposition, which collapses on the first observation of any of
+/// them. Dereferencing an `Entangled` observes it, just like a `SchroedingerBox`.
+pub struct Entangled<Cat> {
+    states: Vec<Cat>,
+    // Each joint outcome holds the index of every member’s state.
+    joint: Rc<SchroedingerBox<Vec<usize>>>,
+    member: usize,
+}
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Entangles two boxes, so that observing either of them decides both.
+    ///
+    /// `pairs` lists the combinations of states that can occur together, as indices into each
+    /// box’s states (in the order `peek_states` would give them). The weight of a pair is the
+    /// product of the two states’ weights, so the boxes keep their odds as far as the pairs
+    /// allow. Fails if no pair can be chosen or the weights overflow.
+    ///
+    /// # Panic
+    ///
+    /// Panics if an index is out of range.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let loot = SchroedingerBox::from_probabilities(vec![(1, "sword"), (9, "potion")]);
+    /// let stock = SchroedingerBox::new(vec!["no swords", "no potions"]);
+    /// let (loot, stock) = loot.entangle(stock, &[(0, 0), (1, 1)]).unwrap();
+    /// // Whatever the player finds, the shopkeeper has just run out of.
+    /// assert_eq!(*stock == "no swords", *loot == "sword");
+    /// ```
+    pub fn entangle<B>(self, other: SchroedingerBox<B>, pairs: &[(usize, usize)])
+            -> Result<(Entangled<Cat>, Entangled<B>), SuperpositionError> {
+        let (a, b) = (self.into_states(), other.into_states());
+        let weights = [weights(&a), weights(&b)];
+        let joint = joint(&weights, pairs.iter().map(|&(i, j)| vec![i, j]))?;
+        Ok((Entangled::new(a, &joint, 0), Entangled::new(b, &joint, 1)))
+    }
+
+    /// Splits the box into `n` entangled copies of its superposition, which all collapse into the
+    /// same state when any of them is observed; `fork` makes independent copies instead.
+    ///
+    /// The copies keep the box’s odds. Fails if the box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let copies = SchroedingerBox::new(vec!["heads", "tails"]).fork_entangled(3).unwrap();
+    /// let side = *copies[1];
+    /// assert!(copies.iter().all(|c| **c == side));
+    /// ```
+    pub fn fork_entangled(self, n: usize) -> Result<Vec<Entangled<Cat>>, SuperpositionError>
+            where Cat: Clone {
+        let states = self.into_states();
+        if n == 0 {
+            return Ok(vec![])
+        }
+        // Only the first copy’s weights count, or the odds would be raised to the `n`th power.
+        let mut ws = vec![vec![1; states.len()]; n];
+        ws[0] = weights(&states);
+        let joint = joint(&ws, (0..states.len()).map(|i| vec![i; n]))?;
+        Ok((0..n).map(|member| Entangled::new(states.clone(), &joint, member)).collect())
+    }
+}
+
+/// A group of any number of entangled boxes, so that observing any of them decides all of them.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{EntangledGroup, SchroedingerBox};
+/// let parties = (0..3).map(|_| SchroedingerBox::new(vec![0u8, 1])).collect();
+/// let group = EntangledGroup::new(parties).unwrap();
+/// // All three agree, whichever of them is looked at first.
+/// let bit = *group.members()[2];
+/// assert!(group.members().iter().all(|m| **m == bit));
+/// ```
+pub struct EntangledGroup<Cat> {
+    members: Vec<Entangled<Cat>>,
+}
+
+impl<Cat> EntangledGroup<Cat> {
+    /// Entangles the boxes so that they all collapse into the state at the same index: either
+    /// they all take their first state, or all their second, and so on.
+    ///
+    /// The weight of each outcome is the product of the weights of the states involved. Fails if
+    /// there are no boxes, no outcome can be chosen or the weights overflow.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the boxes don’t all have the same number of states still possible.
+    pub fn new(boxes: Vec<SchroedingerBox<Cat>>)
+            -> Result<EntangledGroup<Cat>, SuperpositionError> {
+        let states: Vec<_> = boxes.into_iter().map(SchroedingerBox::into_states).collect();
+        let n = states.first().map_or(0, |s| s.len());
+        assert!(states.iter().all(|s| s.len() == n),
+                "entangled boxes must have the same number of states");
+        let members = states.len();
+        let outcomes = (0..n).map(|i| vec![i; members]);
+        EntangledGroup::from_states(states, outcomes)
+    }
+
+    /// Entangles the boxes so that they collapse into one of the given outcomes.
+    ///
+    /// Each outcome lists an index into every box’s states (in the order `peek_states` would give
+    /// them), and its weight is the product of those states’ weights. Fails if no outcome can be
+    /// chosen or the weights overflow.
+    ///
+    /// # Panic
+    ///
+    /// Panics if an outcome doesn’t have an index for every box, or an index is out of range.
+    pub fn with_outcomes(boxes: Vec<SchroedingerBox<Cat>>, outcomes: &[Vec<usize>])
+            -> Result<EntangledGroup<Cat>, SuperpositionError> {
+        let states: Vec<_> = boxes.into_iter().map(SchroedingerBox::into_states).collect();
+        assert!(outcomes.iter().all(|o| o.len() == states.len()),
+                "every outcome must have an index for every box");
+        EntangledGroup::from_states(states, outcomes.iter().cloned())
+    }
+
+    fn from_states<I>(states: Vec<Vec<(u64, Cat)>>, outcomes: I)
+            -> Result<EntangledGroup<Cat>, SuperpositionError> where I: Iterator<Item=Vec<usize>> {
+        let joint = joint(&states.iter().map(|s| weights(s)).collect::<Vec<_>>(), outcomes)?;
+        Ok(EntangledGroup {
+            members: states.into_iter().enumerate().map(|(i, s)| Entangled::new(s, &joint, i))
+                .collect(),
+        })
+    }
+
+    /// Returns the entangled boxes, in the order they were given.
+    pub fn members(&self) -> &[Entangled<Cat>] {
+        &self.members
+    }
+
+    /// Returns the entangled boxes mutably, in the order they were given.
+    pub fn members_mut(&mut self) -> &mut [Entangled<Cat>] {
+        &mut self.members
+    }
+
+    /// Splits the group up into its boxes, which stay entangled with each other.
+    pub fn into_members(self) -> Vec<Entangled<Cat>> {
+        self.members
+    }
+
+    /// Returns whether the boxes have been decided, without observing them.
+    pub fn is_collapsed(&self) -> bool {
+        self.members.first().is_none_or(Entangled::is_collapsed)
+    }
+}
+
+/// Returns the weights of the given states.
+fn weights<Cat>(states: &[(u64, Cat)]) -> Vec<u64> {
+    states.iter().map(|&(w, _)| w).collect()
+}
+
+/// Builds the joint superposition of some boxes with the given weights, over the given outcomes.
+fn joint<I>(weights: &[Vec<u64>], outcomes: I)
+        -> Result<Rc<SchroedingerBox<Vec<usize>>>, SuperpositionError>
+        where I: Iterator<Item=Vec<usize>> {
+    let mut joint = vec![];
+    for outcome in outcomes {
+        let mut weight = 1u64;
+        for (w, &i) in weights.iter().zip(&outcome) {
+            weight = weight.checked_mul(w[i]).ok_or(SuperpositionError::WeightOverflow)?;
+        }
+        joint.push((weight, outcome));
+    }
+    #[cfg_attr(not(feature = "log"), allow(unused_mut))]
+    let mut joint = SchroedingerBox::try_from_probabilities(joint)?;
+    #[cfg(feature = "log")]
+    joint.on_collapse(|outcome| debug!("{} entangled boxes collapsed together into states {:?}",
+                                       outcome.len(), outcome));
+    Ok(Rc::new(joint))
+}
+
+impl<Cat> Entangled<Cat> {
+    fn new(states: Vec<(u64, Cat)>, joint: &Rc<SchroedingerBox<Vec<usize>>>, member: usize)
+            -> Entangled<Cat> {
+        Entangled {
+            states: states.into_iter().map(|(_, c)| c).collect(),
+            joint: joint.clone(),
+            member,
+        }
+    }
+
+    /// Returns whether the boxes have been decided, without observing them.
+    pub fn is_collapsed(&self) -> bool {
+        self.joint.is_collapsed()
+    }
+
+    /// Moves the value out, deciding all the entangled boxes if needed.
+    pub fn into_inner(mut self) -> Cat {
+        let i = self.joint[self.member];
+        self.states.swap_remove(i)
+    }
+}
+
+impl<Cat> Deref for Entangled<Cat> {
+    type Target = Cat;
+
+    fn deref(&self) -> &Cat {
+        &self.states[self.joint[self.member]]
+    }
+}
+
+impl<Cat> DerefMut for Entangled<Cat> {
+    fn deref_mut(&mut self) -> &mut Cat {
+        let i = self.joint[self.member];
+        &mut self.states[i]
+    }
+}
+
+impl<Cat> fmt::Debug for Entangled<Cat>
+        where Cat: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {SchroedingerBox, SuperpositionError};
+    use super::EntangledGroup;
+
+    #[test]
+    fn collapse_together() {
+        for _ in 0..20 {
+            let a = SchroedingerBox::new(vec![0, 1, 2]);
+            let b = SchroedingerBox::new(vec!['a', 'b', 'c']);
+            let (a, mut b) = a.entangle(b, &[(0, 2), (1, 1), (2, 0)]).unwrap();
+            assert!(!a.is_collapsed() && !b.is_collapsed());
+            *b = match *b { 'a' => 'x', 'b' => 'y', _ => 'z' };
+            assert!(a.is_collapsed());
+            assert_eq!(b.into_inner(), ['z', 'y', 'x'][a.into_inner()]);
+        }
+    }
+
+    #[test]
+    fn weights_multiply() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 'a'), (3, 'b')]);
+        let b = SchroedingerBox::from_probabilities(vec![(2, 'x'), (0, 'y')]);
+        let (a, b) = a.entangle(b, &[(0, 0), (1, 1)]).unwrap();
+        assert_eq!((*a, *b), ('a', 'x'));
+        let a = SchroedingerBox::from_probabilities(vec![(u64::MAX, 'a'), (0, 'b')]);
+        let b = SchroedingerBox::from_probabilities(vec![(2, 'x'), (0, 'y')]);
+        assert_eq!(a.entangle(b, &[(0, 0)]).err(), Some(SuperpositionError::WeightOverflow));
+        let observed = SchroedingerBox::new(vec![5, 6]);
+        let state = *observed;
+        let (a, b) = observed.entangle(SchroedingerBox::new(vec![7, 8]), &[(0, 1)]).unwrap();
+        assert_eq!((*a, *b), (state, 8));
+    }
+
+    #[test]
+    fn groups() {
+        let boxes = vec![SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b')]),
+                         SchroedingerBox::new(vec!['c', 'd']),
+                         SchroedingerBox::new(vec!['e', 'f'])];
+        let group = EntangledGroup::new(boxes).unwrap();
+        assert!(!group.is_collapsed());
+        assert_eq!(*group.members()[1], 'c');
+        assert!(group.is_collapsed());
+        let members: Vec<char> = group.into_members().into_iter().map(|m| m.into_inner()).collect();
+        assert_eq!(members, vec!['a', 'c', 'e']);
+
+        let bits = (0..3).map(|_| SchroedingerBox::new(vec![0, 1])).collect();
+        let odd = [vec![1, 0, 0], vec![0, 1, 0], vec![0, 0, 1], vec![1, 1, 1]];
+        let mut group = EntangledGroup::with_outcomes(bits, &odd).unwrap();
+        *group.members_mut()[0] += 2;
+        assert_eq!(group.members().iter().map(|m| **m).sum::<i32>() % 2, 1);
+        assert_eq!(EntangledGroup::<u8>::new(vec![]).err(), Some(SuperpositionError::Empty));
+    }
+
+    #[test]
+    fn entangled_forks() {
+        for _ in 0..20 {
+            let copies = SchroedingerBox::new((0..10).collect::<Vec<u32>>()).fork_entangled(4)
+                .unwrap();
+            assert!(!copies[3].is_collapsed());
+            let x = *copies[3];
+            assert!(copies.iter().all(|c| c.is_collapsed() && **c == x));
+        }
+        // The weights aren’t multiplied together, so they can’t overflow.
+        let heavy = SchroedingerBox::from_probabilities(vec![(u64::MAX / 2, 'a'), (0, 'b')]);
+        let copies = heavy.fork_entangled(2).unwrap();
+        assert_eq!((*copies[0], *copies[1]), ('a', 'a'));
+        assert!(SchroedingerBox::new(vec![1]).fork_entangled(0).unwrap().is_empty());
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 'a'), (0, 'b')]);
+        assert_eq!(zero.fork_entangled(2).err(), Some(SuperpositionError::ZeroTotalWeight));
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error::Error;
+use std::fmt;
+
+/// The ways in which a set of weighted states can fail to describe a superposition.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SuperpositionError {
+    /// There are no states at all.
+    Empty,
+    /// There are several states, but every one of them has a weight of zero, so none of them
+    /// can be chosen.
+    ZeroTotalWeight,
+    /// The weights add up to more than a `u64` can hold.
+    WeightOverflow,
+    /// A floating-point weight is negative or not a number.
+    InvalidWeight,
+    /// There are more states than a `u64` can count (which can only happen on platforms where
+    /// `usize` is wider than 64 bits).
+    TooManyStates,
+    /// The weights can’t be changed, because the odds have already been revealed. See
+    /// `SchroedingerBox::is_frozen`.
+    WeightsFrozen,
+    /// The states can’t be added to, because the box has already been observed.
+    AlreadyObserved,
+}
+
+impl fmt::Display for SuperpositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SuperpositionError::Empty => f.write_str("superposition has no states"),
+            SuperpositionError::ZeroTotalWeight => {
+                f.write_str("superposition has no states with a non-zero weight")
+            },
+            SuperpositionError::WeightOverflow => {
+                f.write_str("superposition weights add up to more than u64::MAX")
+            },
+            SuperpositionError::InvalidWeight => {
+                f.write_str("superposition has a weight that is negative or not a number")
+            },
+            SuperpositionError::TooManyStates => f.write_str("superposition has too many states"),
+            SuperpositionError::WeightsFrozen => {
+                f.write_str("superposition weights are frozen after revealing the odds")
+            },
+            SuperpositionError::AlreadyObserved => {
+                f.write_str("superposition has already been observed")
+            },
+        }
+    }
+}
+
+impl Error for SuperpositionError {}
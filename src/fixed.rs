@@ -0,0 +1,121 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use {total_weight, SchroedingerBox, SuperpositionError};
+
+/// A fixed-point probability, in parts per million.
+///
+/// Arithmetic saturates at zero and at one (a million parts), so a `Ppm` is always a valid
+/// probability. No floating-point arithmetic is involved anywhere.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Ppm(u32);
+
+impl Ppm {
+    /// A probability of zero.
+    pub const ZERO: Ppm = Ppm(0);
+    /// A probability of one.
+    pub const ONE: Ppm = Ppm(1_000_000);
+
+    /// Creates a probability of `parts` in a million, saturating at one.
+    pub const fn new(parts: u32) -> Ppm {
+        if parts > Ppm::ONE.0 { Ppm::ONE } else { Ppm(parts) }
+    }
+
+    /// Returns the number of parts per million.
+    pub const fn parts(self) -> u32 {
+        self.0
+    }
+
+    /// Adds two probabilities, saturating at one.
+    pub fn saturating_add(self, other: Ppm) -> Ppm {
+        Ppm::new(self.0 + other.0)
+    }
+
+    /// Subtracts one probability from another, saturating at zero.
+    pub fn saturating_sub(self, other: Ppm) -> Ppm {
+        Ppm(self.0.saturating_sub(other.0))
+    }
+}
+
+impl fmt::Display for Ppm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:06}", self.0 / Ppm::ONE.0, self.0 % Ppm::ONE.0)
+    }
+}
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Creates a new `SchroedingerBox` from a set of states, each with a fixed-point probability.
+    ///
+    /// As with `from_probabilities`, the probabilities are relative to each other and needn’t add
+    /// up to one.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `states.len() == 0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Ppm, SchroedingerBox};
+    /// let b = SchroedingerBox::from_ppm(vec![(Ppm::new(333_333), 'a'), (Ppm::new(666_667), 'b')]);
+    /// assert_eq!(b.ppm(), Ok(vec![Ppm::new(333_333), Ppm::new(666_667)]));
+    /// ```
+    pub fn from_ppm(states: Vec<(Ppm, Cat)>) -> SchroedingerBox<Cat> {
+        SchroedingerBox::from_probabilities(
+            states.into_iter().map(|(p, c)| (u64::from(p.0), c)).collect())
+    }
+
+    /// Returns the probability of each state that is still possible, without observing the box.
+    ///
+    /// The probabilities are renormalised so that they always add up to exactly one, with
+    /// rounding errors given to the states that lost the most to rounding. Once the box has been
+    /// observed, the only probability is that of the observed state.
+    pub fn ppm(&self) -> Result<Vec<Ppm>, SuperpositionError> {
+        let states = self.odds();
+        if states.len() == 1 {
+            return Ok(vec![Ppm::ONE])
+        }
+        let total = u128::from(total_weight(states)?);
+        let one = u128::from(Ppm::ONE.0);
+        let exact: Vec<u128> = states.iter().map(|&(w, _)| u128::from(w) * one).collect();
+        let mut parts: Vec<u32> = exact.iter().map(|&e| (e / total) as u32).collect();
+        let short = Ppm::ONE.0 - parts.iter().sum::<u32>();
+        let mut by_remainder: Vec<usize> = (0..parts.len()).collect();
+        by_remainder.sort_by_key(|&i| (total - exact[i] % total, i));
+        for &i in by_remainder.iter().take(short as usize) {
+            parts[i] += 1;
+        }
+        Ok(parts.into_iter().map(Ppm).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::Ppm;
+
+    #[test]
+    fn saturating() {
+        assert_eq!(Ppm::new(2_000_000), Ppm::ONE);
+        assert_eq!(Ppm::new(600_000).saturating_add(Ppm::new(600_000)), Ppm::ONE);
+        assert_eq!(Ppm::new(1).saturating_sub(Ppm::new(2)), Ppm::ZERO);
+        assert_eq!(Ppm::new(33_371).to_string(), "0.033371");
+    }
+
+    #[test]
+    fn renormalisation_adds_up() {
+        let b = SchroedingerBox::new(vec![1, 2, 3]);
+        let ppm = b.ppm().unwrap();
+        assert_eq!(ppm, vec![Ppm::new(333_334), Ppm::new(333_333), Ppm::new(333_333)]);
+        let b = SchroedingerBox::from_probabilities(vec![(u64::MAX - 1, 1), (1, 2)]);
+        assert_eq!(b.ppm().unwrap(), vec![Ppm::ONE, Ppm::ZERO]);
+        let _ = *b;
+        assert_eq!(b.ppm().unwrap(), vec![Ppm::ONE]);
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use {pick, total_weight, SchroedingerBox};
+
+/// A feature flag whose variant is decided the first time it is checked, and then sticks.
+///
+/// Besides the flag-wide variant, each key (a user ID, say) can be checked separately; every key
+/// gets its own independently decided variant, which also sticks. Alternatively, `variant_for`
+/// buckets keys by their hash alone, so the same key always gets the same variant without any
+/// state being kept at all.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerFlag;
+/// let new_ui = SchroedingerFlag::rollout(10);
+/// if new_ui.is_enabled() {
+///     // ...
+/// }
+/// assert_eq!(new_ui.is_enabled(), new_ui.is_enabled());
+///
+/// let button = SchroedingerFlag::new(vec![(1, "red"), (1, "green"), (2, "blue")]);
+/// assert_eq!(button.variant_for_key(&42), button.variant_for_key(&42));
+/// ```
+pub struct SchroedingerFlag<V = bool> {
+    variants: Vec<(u64, V)>,
+    global: SchroedingerBox<usize>,
+    keyed: RefCell<HashMap<u64, usize>>,
+}
+
+impl SchroedingerFlag<bool> {
+    /// Creates an on/off flag that is enabled with a probability of `percent` percent.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `percent > 100`.
+    pub fn rollout(percent: u64) -> SchroedingerFlag<bool> {
+        assert!(percent <= 100);
+        SchroedingerFlag::new(vec![(percent, true), (100 - percent, false)])
+    }
+
+    /// Checks whether the flag is enabled, deciding it if needed.
+    pub fn is_enabled(&self) -> bool {
+        *self.variant()
+    }
+
+    /// Checks whether the flag is enabled for `key`, deciding it for that key if needed.
+    pub fn is_enabled_for_key<K>(&self, key: &K) -> bool where K: Hash {
+        *self.variant_for_key(key)
+    }
+}
+
+impl<V> SchroedingerFlag<V> {
+    /// Creates a new flag from a set of weighted variants.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `variants.len() == 0`.
+    pub fn new(variants: Vec<(u64, V)>) -> SchroedingerFlag<V> {
+        assert!(!variants.is_empty());
+        SchroedingerFlag {
+            global: SchroedingerBox::from_probabilities(
+                variants.iter().enumerate().map(|(i, &(w, _))| (w, i)).collect()),
+            variants,
+            keyed: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the flag-wide variant, deciding it if needed.
+    pub fn variant(&self) -> &V {
+        &self.variants[*self.global].1
+    }
+
+    /// Returns the variant for `key`, deciding it for that key if needed.
+    ///
+    /// Keys are told apart by their hash, so two keys with the same hash share a variant.
+    pub fn variant_for_key<K>(&self, key: &K) -> &V where K: Hash {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let idx = *self.keyed.borrow_mut().entry(hasher.finish()).or_insert_with(|| {
+            SchroedingerBox::from_probabilities(
+                self.variants.iter().enumerate().map(|(i, &(w, _))| (w, i)).collect()
+            ).into_inner()
+        });
+        &self.variants[idx].1
+    }
+
+    /// Returns a variant for `key` derived only from the weights and the key’s hash, without
+    /// using any randomness or deciding the flag.
+    ///
+    /// Keys are spread over the variants in proportion to their weights. The same key is always
+    /// put in the same bucket as long as the weights don’t change, although hashes (and so
+    /// buckets) are not guaranteed to be the same across different versions of Rust.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the variants can’t be chosen between, as observing a box with the same weights
+    /// would.
+    pub fn variant_for<K>(&self, key: &K) -> &V where K: Hash {
+        if let [(_, ref only)] = *self.variants {
+            return only
+        }
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let total = total_weight(&self.variants).unwrap_or_else(|e| panic!("{}", e));
+        // Scale the hash down to the total weight using its high bits.
+        let r = ((u128::from(hasher.finish()) * u128::from(total)) >> 64) as u64;
+        &self.variants[pick(&self.variants, r)].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SchroedingerFlag;
+
+    #[test]
+    fn rollout_extremes() {
+        assert!(SchroedingerFlag::rollout(100).is_enabled());
+        assert!(!SchroedingerFlag::rollout(0).is_enabled());
+        assert!(!SchroedingerFlag::rollout(0).is_enabled_for_key(&"alice"));
+    }
+
+    #[test]
+    fn keyed_variants_stick() {
+        let flag = SchroedingerFlag::new(vec![(1, 'a'), (1, 'b'), (1, 'c')]);
+        let mine = *flag.variant_for_key(&"me");
+        for _ in 0..100 {
+            assert_eq!(*flag.variant_for_key(&"me"), mine);
+        }
+        assert_eq!(flag.variant(), flag.variant());
+    }
+
+    #[test]
+    fn hashed_buckets() {
+        let flag = SchroedingerFlag::new(vec![(1, 'a'), (0, 'b'), (3, 'c')]);
+        let mut counts = [0; 3];
+        for user in 0..4000 {
+            let v = *flag.variant_for(&user);
+            assert_eq!(*flag.variant_for(&user), v);
+            counts[(v as u8 - b'a') as usize] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        // Roughly a quarter of the users should be in bucket 'a'.
+        assert!(counts[0] > 800 && counts[0] < 1200);
+        assert_eq!(*SchroedingerFlag::new(vec![(5, 'x')]).variant_for(&"anyone"), 'x');
+        assert_eq!(*SchroedingerFlag::new(vec![(0, 'x')]).variant_for(&1), 'x');
+    }
+
+    #[test]
+    #[should_panic(expected = "superposition weights add up to more than u64::MAX")]
+    fn overflowing_buckets() {
+        SchroedingerFlag::new(vec![(u64::MAX, 'a'), (1, 'b')]).variant_for(&1);
+    }
+}
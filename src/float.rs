@@ -0,0 +1,95 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use {gcd, SchroedingerBox, SuperpositionError};
+
+/// The total that floating-point weights are scaled up to, leaving plenty of room for rounding.
+const SCALE: f64 = (1u64 << 62) as f64;
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Creates a new `SchroedingerBox` from a set of states, each with a floating-point weight.
+    ///
+    /// As with `from_probabilities`, the weights are relative to each other and needn’t add up to
+    /// one. They are converted to integer weights with about 62 bits of precision (more than an
+    /// `f64` has for any one weight), and a state with a positive weight is never rounded down to
+    /// a weight of zero.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `states.len() == 0`, or under the same conditions as `try_from_f64_weights`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let b = SchroedingerBox::from_f64_weights(vec![(0.03371, "rare"), (0.96629, "common")]);
+    /// let p = b.probabilities();
+    /// assert!((p[0].0 - 0.03371).abs() < 1e-15);
+    /// ```
+    pub fn from_f64_weights(states: Vec<(f64, Cat)>) -> SchroedingerBox<Cat> {
+        SchroedingerBox::try_from_f64_weights(states).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `from_f64_weights`, but returns an error instead of panicking, under the same
+    /// conditions as `try_from_probabilities`, or if any weight is negative or NaN, or if the
+    /// weights add up to infinity.
+    pub fn try_from_f64_weights(states: Vec<(f64, Cat)>)
+            -> Result<SchroedingerBox<Cat>, SuperpositionError> {
+        let weights: Vec<_> = states.iter().map(|&(w, _)| w).collect();
+        let weighted = scale(&weights)?.into_iter().zip(states).map(|(w, (_, c))| (w, c)).collect();
+        SchroedingerBox::try_from_probabilities(weighted)
+    }
+}
+
+/// Converts floating-point weights into integer weights in the same proportions, as described by
+/// `from_f64_weights`.
+pub(crate) fn scale(weights: &[f64]) -> Result<Vec<u64>, SuperpositionError> {
+    if weights.iter().any(|&w| w.is_nan() || w < 0.0) {
+        return Err(SuperpositionError::InvalidWeight)
+    }
+    let total = weights.iter().sum::<f64>();
+    if total.is_infinite() {
+        return Err(SuperpositionError::WeightOverflow)
+    }
+    let mut scaled: Vec<_> = weights.iter()
+        .map(|&w| if w > 0.0 { ((w / total * SCALE).round() as u64).max(1) } else { 0 })
+        .collect();
+    let common = scaled.iter().fold(0, |g, &w| gcd(g, w)).max(1);
+    for w in &mut scaled {
+        *w /= common;
+    }
+    Ok(scaled)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64;
+    use {SchroedingerBox, SuperpositionError};
+
+    #[test]
+    fn float_weights() {
+        let b = SchroedingerBox::from_f64_weights(vec![(0.5, 1), (0.25, 2), (0.25, 3)]);
+        assert_eq!(b.states(), &[(2, 1), (1, 2), (1, 3)][..]);
+        let tiny = SchroedingerBox::from_f64_weights(vec![(1e-300, 'a'), (1.0, 'b'), (0.0, 'c')]);
+        assert_eq!(tiny.states(), &[(1, 'a'), (1 << 62, 'b'), (0, 'c')][..]);
+        let small = SchroedingerBox::from_f64_weights(vec![(1e-310, 'a'), (3e-310, 'b')]);
+        assert_eq!(small.states(), &[(1, 'a'), (3, 'b')][..]);
+    }
+
+    #[test]
+    fn invalid_float_weights() {
+        let invalid = |states| SchroedingerBox::try_from_f64_weights(states).err();
+        assert_eq!(invalid(vec![(-1.0, 1), (2.0, 2)]), Some(SuperpositionError::InvalidWeight));
+        assert_eq!(invalid(vec![(f64::NAN, 1)]), Some(SuperpositionError::InvalidWeight));
+        assert_eq!(invalid(vec![(f64::MAX, 1), (f64::MAX, 2)]),
+                   Some(SuperpositionError::WeightOverflow));
+        assert_eq!(invalid(vec![(0.0, 1), (0.0, 2)]), Some(SuperpositionError::ZeroTotalWeight));
+        assert_eq!(invalid(vec![]), Some(SuperpositionError::Empty));
+        assert_eq!(SchroedingerBox::from_f64_weights(vec![(0.0, 1)]).into_inner(), 1);
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rigging observations in tests, enabled by the `test-util` feature.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use {Panic, SchroedingerBox};
+
+// Checked before taking the lock, so that collapses stay cheap while nothing is rigged.
+static RIGGED: AtomicBool = AtomicBool::new(false);
+static QUEUE: Mutex<VecDeque<usize>> = Mutex::new(VecDeque::new());
+// Held by every `ForcedOutcomes`, so that only one can exist at a time.
+static OWNER: Mutex<()> = Mutex::new(());
+
+/// A guard that rigs the next few observations in the whole process, for as long as it lives.
+///
+/// Each observation that has to choose between several states takes the next index from the
+/// guard instead, until they run out. Observations in every thread count, so tests using this
+/// are best kept in a test binary of their own. Only one guard can exist at a time; creating
+/// another waits for the first to be dropped.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{ForcedOutcomes, SchroedingerBox};
+/// let _rigged = ForcedOutcomes::new(vec![1, 0]);
+/// assert_eq!(*SchroedingerBox::new(vec!["heads", "tails"]), "tails");
+/// assert_eq!(*SchroedingerBox::new(vec!["heads", "tails"]), "heads");
+/// ```
+pub struct ForcedOutcomes {
+    _owner: MutexGuard<'static, ()>,
+}
+
+impl ForcedOutcomes {
+    /// Rigs the next `indices.len()` observations to choose the states at the given indices.
+    ///
+    /// Observing a box that doesn’t have a state at the next index panics.
+    pub fn new(indices: Vec<usize>) -> ForcedOutcomes {
+        let owner = OWNER.lock().unwrap_or_else(|e| e.into_inner());
+        let mut queue = lock_queue();
+        *queue = indices.into();
+        RIGGED.store(!queue.is_empty(), Ordering::SeqCst);
+        ForcedOutcomes {
+            _owner: owner,
+        }
+    }
+
+    /// Returns how many rigged observations are left.
+    pub fn remaining(&self) -> usize {
+        lock_queue().len()
+    }
+}
+
+impl Drop for ForcedOutcomes {
+    fn drop(&mut self) {
+        lock_queue().clear();
+        RIGGED.store(false, Ordering::SeqCst);
+    }
+}
+
+fn lock_queue() -> MutexGuard<'static, VecDeque<usize>> {
+    // A panicking observation mustn’t stop later tests from rigging anything.
+    QUEUE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Takes the next rigged index, if there is one.
+pub fn next_forced() -> Option<usize> {
+    if !RIGGED.load(Ordering::SeqCst) {
+        return None
+    }
+    let mut queue = lock_queue();
+    let next = queue.pop_front();
+    RIGGED.store(!queue.is_empty(), Ordering::SeqCst);
+    next
+}
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Decides, without observing it, that the box holds the state at `index`.
+    ///
+    /// This also defuses boxes made by `maybe_panic`, which is the only way they can be given
+    /// their value in tests that use `PanicMode::Random`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if there is no state at `index`, or if the box has already been observed to hold a
+    /// different state (or to panic).
+    pub fn force_state(&self, index: usize) {
+        self.decohere();
+        // Once the states that weren’t chosen have been discarded, only the observed state’s old
+        // index is left to check against.
+        if self._trimmed_from.is_none() {
+            assert!(index < self._states.len(), "no state at index {} in a box with {} states",
+                    index, self._states.len());
+        }
+        // Taking a pending panic out of the box defuses it.
+        if let Some(Panic::Exploded(m)) = self._panic.take() {
+            self._panic.set(Some(Panic::Exploded(m)));
+            panic!("box has already panicked");
+        }
+        let observed = self._trimmed_from.or_else(|| self._chosen.get().cloned())
+            .or(if self._states.len() == 1 { Some(0) } else { None });
+        match observed {
+            None => {
+                let _ = self._chosen.set(index);
+            },
+            Some(i) => {
+                assert!(index == i, "box has already been observed to hold a different state");
+            },
+        }
+        self.track();
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finite-state machines whose transitions may lead to superposed next states.
+
+use SchroedingerBox;
+
+/// A transition table, as accepted by `Machine::new`.
+pub type Table<S, E> = Box<dyn Fn(&S, &E) -> Option<SchroedingerBox<S>>>;
+
+/// A hook, as accepted by `Machine::on_transition`.
+pub type Hook<S, E> = Box<dyn FnMut(&S, &E, &S)>;
+
+/// A finite-state machine with states `S` driven by events `E`.
+///
+/// The transition table is a function from the current state and an event to a
+/// `SchroedingerBox` of possible next states. The box is only observed when the machine actually
+/// advances, so which branch is taken stays undetermined until then.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerBox;
+/// # use schroedinger_box::fsm::Machine;
+/// #[derive(Clone, Copy, PartialEq, Debug)]
+/// enum Link { Up, Down }
+///
+/// let mut link = Machine::new(Link::Up, |state: &Link, _: &()| match *state {
+///     Link::Up => Some(SchroedingerBox::from_probabilities(vec![(9, Link::Up), (1, Link::Down)])),
+///     Link::Down => Some(SchroedingerBox::new(vec![Link::Up])),
+/// });
+/// link.on_transition(|from, _, to| println!("{:?} -> {:?}", from, to));
+/// link.advance(());
+/// ```
+pub struct Machine<S, E> {
+    state: S,
+    table: Table<S, E>,
+    hooks: Vec<Hook<S, E>>,
+}
+
+impl<S, E> Machine<S, E> {
+    /// Creates a new machine in the given state, using `table` to look up transitions.
+    ///
+    /// `table` should return `None` if the event is not accepted in the given state.
+    pub fn new<F>(initial: S, table: F) -> Machine<S, E>
+            where F: Fn(&S, &E) -> Option<SchroedingerBox<S>> + 'static {
+        Machine {
+            state: initial,
+            table: Box::new(table),
+            hooks: vec![],
+        }
+    }
+
+    /// Registers a hook that is called with the previous state, the event and the new state every
+    /// time the machine advances.
+    ///
+    /// Hooks are called in the order they were registered.
+    pub fn on_transition<F>(&mut self, hook: F) where F: FnMut(&S, &E, &S) + 'static {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Returns the current state of the machine.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Feeds an event to the machine, collapsing the resulting superposition of next states.
+    ///
+    /// Returns the new state, or `None` (leaving the machine untouched) if the event is not
+    /// accepted in the current state.
+    pub fn advance(&mut self, event: E) -> Option<&S> {
+        let next = (self.table)(&self.state, &event)?.into_inner();
+        let prev = ::std::mem::replace(&mut self.state, next);
+        for hook in &mut self.hooks {
+            hook(&prev, &event, &self.state);
+        }
+        Some(&self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use SchroedingerBox;
+    use super::Machine;
+
+    fn door(state: &bool, open: &bool) -> Option<SchroedingerBox<bool>> {
+        if state == open {
+            None
+        } else {
+            Some(SchroedingerBox::new(vec![*open]))
+        }
+    }
+
+    #[test]
+    fn rejected_events_leave_state() {
+        let mut m = Machine::new(false, door);
+        assert_eq!(m.advance(false), None);
+        assert_eq!(m.advance(true), Some(&true));
+        assert!(*m.state());
+    }
+
+    #[test]
+    fn hooks_see_transitions() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut m = Machine::new(false, door);
+        let l = log.clone();
+        m.on_transition(move |&from, &ev, &to| l.borrow_mut().push((from, ev, to)));
+        m.advance(true);
+        m.advance(true);
+        m.advance(false);
+        assert_eq!(*log.borrow(), vec![(false, true, true), (true, false, false)]);
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Futures for observing a `SyncSchroedingerBox`, enabled by the `async` feature.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::MutexGuard;
+use std::task::{Context, Poll, Waker};
+use SyncSchroedingerBox;
+
+/// A future that observes a box, as returned by `SyncSchroedingerBox::observe`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Observe<'a, Cat: 'a> {
+    b: &'a SyncSchroedingerBox<Cat>,
+}
+
+/// A future that waits for a box to be observed by someone else, as returned by
+/// `SyncSchroedingerBox::observed`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Observed<'a, Cat: 'a> {
+    b: &'a SyncSchroedingerBox<Cat>,
+}
+
+impl<Cat> SyncSchroedingerBox<Cat> {
+    /// Returns a future that observes the box when it is first polled, and resolves to the
+    /// observed state.
+    ///
+    /// Every task waiting on `observed` is woken once the box has been observed.
+    ///
+    /// # Panic
+    ///
+    /// The future panics if the box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,edition2018
+    /// # use std::future::Future;
+    /// # use std::pin::pin;
+    /// # use std::task::{Context, Poll, Waker};
+    /// # use schroedinger_box::SyncSchroedingerBox;
+    /// # fn block_on<F: Future>(f: F) -> F::Output {
+    /// #     let mut f = pin!(f);
+    /// #     loop {
+    /// #         if let Poll::Ready(x) = f.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+    /// #             return x;
+    /// #         }
+    /// #     }
+    /// # }
+    /// let cat = SyncSchroedingerBox::new(vec!["alive", "dead"]);
+    /// block_on(async {
+    ///     let state = *cat.observe().await;
+    ///     assert_eq!(*cat.observed().await, state);
+    /// });
+    /// ```
+    pub fn observe(&self) -> Observe<'_, Cat> {
+        Observe {
+            b: self,
+        }
+    }
+
+    /// Returns a future that resolves to the observed state once the box has been observed,
+    /// without observing it.
+    pub fn observed(&self) -> Observed<'_, Cat> {
+        Observed {
+            b: self,
+        }
+    }
+}
+
+impl<'a, Cat> Future for Observe<'a, Cat> {
+    type Output = &'a Cat;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<&'a Cat> {
+        Poll::Ready(&**self.b)
+    }
+}
+
+impl<'a, Cat> Future for Observed<'a, Cat> {
+    type Output = &'a Cat;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<&'a Cat> {
+        if self.b.is_collapsed() {
+            return Poll::Ready(&**self.b)
+        }
+        {
+            let mut wakers = lock(self.b);
+            if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        }
+        // Check again, in case the box was observed before the waker was registered.
+        if self.b.is_collapsed() {
+            Poll::Ready(&**self.b)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+fn lock<Cat>(b: &SyncSchroedingerBox<Cat>) -> MutexGuard<'_, Vec<Waker>> {
+    // A list of wakers can’t be left half-updated, so a panic elsewhere doesn’t matter.
+    b.wakers.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Wakes every task waiting for the box to be observed.
+pub(crate) fn wake_all<Cat>(b: &SyncSchroedingerBox<Cat>) {
+    for waker in lock(b).drain(..) {
+        waker.wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll, Wake, Waker};
+    use SyncSchroedingerBox;
+
+    struct Counter(AtomicUsize);
+
+    impl Wake for Counter {
+        fn wake(self: Arc<Counter>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn waiters_are_woken() {
+        let b = SyncSchroedingerBox::new((0..100).collect::<Vec<u32>>());
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+        let (mut first, mut second) = (pin!(b.observed()), pin!(b.observed()));
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+        let state = match pin!(b.observe()).poll(&mut cx) {
+            Poll::Ready(&s) => s,
+            Poll::Pending => panic!("observing shouldn’t wait"),
+        };
+        // The same waker was only registered once.
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(first.poll(&mut cx), Poll::Ready(&state));
+        assert_eq!(second.poll(&mut cx), Poll::Ready(&state));
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::time::Duration;
+use rand::Rng;
+use rng::local_rng;
+use SchroedingerBox;
+
+/// A superposition of delays for retry loops, drawing a fresh delay every time one is needed.
+///
+/// Unlike opening a `SchroedingerBox`, drawing a delay doesn’t collapse anything, so the same
+/// `Jitter` can be used for every retry.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use schroedinger_box::Jitter;
+/// let jitter = Jitter::uniform(Duration::from_millis(50), Duration::from_millis(150));
+/// for attempt in 0..3 {
+///     let delay = jitter.backoff(attempt);
+///     assert!(delay <= Duration::from_millis(150) * 2u32.pow(attempt));
+///     // std::thread::sleep(delay);
+/// }
+/// ```
+pub struct Jitter {
+    delays: Delays,
+}
+
+enum Delays {
+    Weighted(SchroedingerBox<Duration>),
+    Uniform(Duration, Duration),
+}
+
+impl Jitter {
+    /// Creates a jitter that draws from a set of weighted delays.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `delays.len() == 0`.
+    pub fn new(delays: Vec<(u64, Duration)>) -> Jitter {
+        Jitter {
+            delays: Delays::Weighted(SchroedingerBox::from_probabilities(delays)),
+        }
+    }
+
+    /// Creates a jitter that draws uniformly from the delays between `min` and `max`, inclusive.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `min > max`.
+    pub fn uniform(min: Duration, max: Duration) -> Jitter {
+        assert!(min <= max);
+        Jitter {
+            delays: Delays::Uniform(min, max),
+        }
+    }
+
+    /// Draws a fresh delay.
+    pub fn next_delay(&self) -> Duration {
+        let mut rng = local_rng();
+        match self.delays {
+            Delays::Weighted(ref b) => b.draw(1, &mut rng).pop().unwrap(),
+            Delays::Uniform(min, max) => rng.gen_range(min..=max),
+        }
+    }
+
+    /// Draws a fresh delay for exponential backoff, doubling it for every previous attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.next_delay().saturating_mul(2u32.saturating_pow(attempt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::Jitter;
+
+    #[test]
+    fn fresh_draws() {
+        let jitter = Jitter::new(vec![(1, Duration::from_secs(1)), (1, Duration::from_secs(2))]);
+        let delays: Vec<_> = (0..200).map(|_| jitter.next_delay()).collect();
+        assert!(delays.contains(&Duration::from_secs(1)));
+        assert!(delays.contains(&Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn uniform_bounds() {
+        let (min, max) = (Duration::from_millis(10), Duration::from_millis(20));
+        let jitter = Jitter::uniform(min, max);
+        for _ in 0..100 {
+            let d = jitter.next_delay();
+            assert!(min <= d && d <= max);
+        }
+        assert_eq!(Jitter::uniform(min, min).backoff(3), min * 8);
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use {gcd, total_weight, SchroedingerBox};
+
+/// Formats a box in Dirac’s ket notation, as returned by `SchroedingerBox::ket`.
+///
+/// Each state that is still possible is written as `|state⟩`, with its amplitude in front: the
+/// square root of its probability, as an exact fraction. The alternate form (`{:#}`) writes the
+/// probabilities themselves instead. States with a weight of zero are left out, and a box that
+/// can’t be observed is written as `0`.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerBox;
+/// let b = SchroedingerBox::from_probabilities(vec![(1, 1), (5, 2)]);
+/// assert_eq!(b.ket().to_string(), "√(1/6)|1⟩ + √(5/6)|2⟩");
+/// assert_eq!(format!("{:#}", b.ket()), "1/6|1⟩ + 5/6|2⟩");
+/// let _ = *b;
+/// assert!(["|1⟩", "|2⟩"].contains(&&*b.ket().to_string()));
+/// ```
+pub struct Ket<'a, Cat: 'a> {
+    b: &'a SchroedingerBox<Cat>,
+}
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Returns something that formats the box in ket notation, without observing it.
+    ///
+    /// This reveals the odds, so it freezes the weights.
+    pub fn ket(&self) -> Ket<'_, Cat> {
+        self.odds();
+        Ket {
+            b: self,
+        }
+    }
+}
+
+impl<'a, Cat> fmt::Display for Ket<'a, Cat> where Cat: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let states = self.b.odds();
+        if let [(_, ref c)] = *states {
+            return write!(f, "|{}⟩", c)
+        }
+        let total = match total_weight(states) {
+            Ok(total) => total,
+            Err(_) => return f.write_str("0"),
+        };
+        let mut first = true;
+        for &(w, ref c) in states.iter().filter(|&&(w, _)| w > 0) {
+            if !first {
+                f.write_str(" + ")?;
+            }
+            first = false;
+            let common = gcd(w, total);
+            let (numer, denom) = (w / common, total / common);
+            match (denom, f.alternate()) {
+                (1, _) => {},
+                (_, true) => write!(f, "{}/{}", numer, denom)?,
+                (_, false) => write!(f, "√({}/{})", numer, denom)?,
+            }
+            write!(f, "|{}⟩", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+
+    #[test]
+    fn kets() {
+        let b = SchroedingerBox::from_probabilities(vec![(2, 'a'), (0, 'b'), (2, 'c'), (4, 'd')]);
+        assert_eq!(b.ket().to_string(), "√(1/4)|a⟩ + √(1/4)|c⟩ + √(1/2)|d⟩");
+        assert!(b.is_frozen());
+        let certain = SchroedingerBox::from_probabilities(vec![(0, 'a'), (3, 'b')]);
+        assert_eq!(format!("{:#}", certain.ket()), "|b⟩");
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 'a'), (0, 'b')]);
+        assert_eq!(zero.ket().to_string(), "0");
+    }
+}
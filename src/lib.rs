@@ -5,16 +5,123 @@
 // <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+#![forbid(unsafe_code)]
+
 extern crate rand;
+#[cfg(feature = "num-rational")]
+extern crate num_rational;
+#[cfg(feature = "num-complex")]
+extern crate num_complex;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate bincode;
 
-use std::cell::UnsafeCell;
-use std::mem::{replace, transmute};
-use rand::{thread_rng, Rng};
+use std::cell::{Cell, OnceCell};
+use std::mem::take;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use rand::{Rng, SeedableRng};
+use rand::distributions::Distribution;
+use rand::rngs::StdRng;
 use std::fmt;
 use std::default::Default;
 use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 use std::ops::{Deref, DerefMut};
 use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+use alias::AliasTable;
+use rng::local_rng;
+
+pub mod chaos;
+pub mod conjugate;
+#[cfg(feature = "num-complex")]
+pub mod density;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod fsm;
+pub mod loot;
+pub mod markov;
+pub mod ngram;
+#[cfg(feature = "num-complex")]
+pub mod quantum;
+pub mod seed;
+pub mod testing;
+pub mod wfc;
+
+mod alias;
+mod cell;
+mod codec;
+mod compare;
+mod config;
+mod constant;
+mod entangle;
+mod error;
+mod fixed;
+#[cfg(feature = "async")]
+mod future;
+#[cfg(feature = "test-util")]
+mod force;
+mod flag;
+mod float;
+mod jitter;
+mod ket;
+mod map;
+mod observable;
+mod option;
+mod permutation;
+#[cfg(feature = "num-rational")]
+mod ratio;
+mod result;
+mod rng;
+#[cfg(feature = "serde")]
+mod serialize;
+mod simulation;
+mod snapshot;
+mod stats;
+mod strategy;
+mod subset;
+mod sync;
+mod vec;
+mod view;
+mod weak;
+mod weights;
+mod zeno;
+
+pub use cell::SchroedingerCell;
+pub use codec::DecodeError;
+pub use config::{ResolvedConfig, Resolution, SchroedingerConfig};
+#[doc(hidden)]
+pub use constant::collapse as __collapse;
+pub use entangle::{Entangled, EntangledGroup};
+pub use error::SuperpositionError;
+pub use fixed::Ppm;
+#[cfg(feature = "async")]
+pub use future::{Observe, Observed};
+#[cfg(feature = "test-util")]
+pub use force::ForcedOutcomes;
+pub use flag::SchroedingerFlag;
+pub use jitter::Jitter;
+pub use ket::Ket;
+pub use map::SchroedingerHashMap;
+pub use observable::{FixedOutcome, Observable};
+pub use option::SchroedingerOption;
+pub use permutation::SchroedingerPermutation;
+pub use result::SchroedingerResult;
+pub use rng::with_rng_override;
+pub use simulation::{monte_carlo, Outcomes, Trial};
+pub use snapshot::Snapshot;
+pub use strategy::SchroedingerStrategy;
+pub use subset::SchroedingerSubset;
+pub use sync::SyncSchroedingerBox;
+pub use vec::SchroedingerVec;
+pub use view::FrozenView;
+pub use weights::{set_weight_arithmetic, WeightArithmetic};
+pub use zeno::ZenoBox;
 
 /// A box that contains many values, but collapses into one when opened (read from) for the first
 /// time.
@@ -29,10 +136,79 @@ use std::cmp::Ordering;
 /// // ...it collapses into one of the possible states with equal probability.
 /// assert_eq!(state, *cat_is_alive);
 /// ```
+///
+/// Once a box has been observed, its value stays in one place: references obtained from `deref`
+/// and `deref_mut` all point to the same place, and that place stays the same when the box itself
+/// is moved, cloned, hashed or observed again. Methods that change the states or their weights
+/// (`retain` or `update`, say) may move it, though they can’t be called while any reference into
+/// the box is alive.
+///
+/// A box with only one state always holds that state, whatever its weight. A box with several
+/// states that all have a weight of zero can’t be observed: the usual methods panic (with a
+/// `SuperpositionError` as the message), while the `try_` methods return the error and leave the
+/// box as it was.
 // This should be called `SchrödingerBox`, but until type aliases can have static methods called on
 // them (rust-lang/rust#11047) I’ll take pity on those barbarians who can’t type umlauts easily.
+//
+// The states are never touched through a shared reference; observing the box only records which
+// of them was chosen. That way a reference to the observed state stays valid (and in the same
+// place) for as long as the box is borrowed, without any unsafe code.
 pub struct SchroedingerBox<Cat> {
-    _inner: UnsafeCell<Vec<(u64, Cat)>>,
+    _states: Vec<(u64, Cat)>,
+    // The index of the observed state, if it has been observed. A box with only one state is
+    // always collapsed, whether or not this has been set.
+    _chosen: OnceCell<usize>,
+    _panic: Cell<Option<Panic>>,
+    // Whether the odds have been revealed, so that the weights mustn’t change any more.
+    _frozen: Cell<bool>,
+    // The seed that the box collapses with, whatever RNG it is observed with.
+    _seed: Option<u64>,
+    // When the box collapses on its own, if it hasn’t been observed by then.
+    _deadline: Cell<Option<Instant>>,
+    // How many times the box has been observed, and when it collapsed.
+    _observations: Cell<u64>,
+    _collapsed_at: OnceCell<Instant>,
+    // What to call when the box collapses.
+    _on_collapse: Cell<Vec<Callback<Cat>>>,
+    // The index that the observed state had before the other states were discarded, so that
+    // `force_state` can still tell which one it was.
+    #[cfg(feature = "test-util")]
+    _trimmed_from: Option<usize>,
+    #[cfg(feature = "debug")]
+    _debug: debug::Tracker,
+}
+
+/// A callback registered with `SchroedingerBox::on_collapse`.
+type Callback<Cat> = Box<dyn FnOnce(&Cat) + Send>;
+
+/// The fate of a box made by `SchroedingerBox::maybe_panic`.
+#[derive(Clone)]
+enum Panic {
+    /// Not decided yet: the weight of blowing up on observation, and the message to panic with.
+    Pending(u64, String),
+    /// The box has blown up, and will blow up again on every observation.
+    Exploded(String),
+}
+
+/// Controls whether boxes created with `SchroedingerBox::maybe_panic` panic when observed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PanicMode {
+    /// Panic with the probability given to `maybe_panic`. This is the default.
+    Random,
+    /// Always panic, unless the panic was given a weight of zero.
+    Always,
+    /// Never panic, unless the value was given a weight of zero (or the box has already
+    /// panicked).
+    Never,
+}
+
+thread_local!(static PANIC_MODE: Cell<PanicMode> = const { Cell::new(PanicMode::Random) });
+
+/// Sets the `PanicMode` for the current thread, returning the previous mode.
+///
+/// This is meant for tests that need to exercise (or avoid) a particular path deterministically.
+pub fn set_panic_mode(mode: PanicMode) -> PanicMode {
+    PANIC_MODE.with(|m| m.replace(mode))
 }
 
 impl<Cat> SchroedingerBox<Cat> {
@@ -43,7 +219,7 @@ impl<Cat> SchroedingerBox<Cat> {
     ///
     /// # Panic
     ///
-    /// Panics if `states.len() == 0`.
+    /// Panics if `states.len() == 0`; `try_new` returns an error instead.
     pub fn new(states: Vec<Cat>) -> SchroedingerBox<Cat> {
         SchroedingerBox::from_probabilities(states.into_iter().map(|x| (1, x)).collect())
     }
@@ -61,46 +237,1090 @@ impl<Cat> SchroedingerBox<Cat> {
     ///
     /// Panics if `states.len() == 0`.
     // Here we *could* choose the `Collapsed` state instantly, avoiding all the trouble with
-    // `UnsafeCell` and so on. But that would be boring and against the point, so we make sure that
+    // `OnceCell` and so on. But that would be boring and against the point, so we make sure that
     // the state collapses only on the first observation.
     pub fn from_probabilities(states: Vec<(u64, Cat)>) -> SchroedingerBox<Cat> {
-        assert!(states.len() > 0);
-        SchroedingerBox {
-            _inner: UnsafeCell::new(states),
+        assert!(!states.is_empty(), "{}", SuperpositionError::Empty);
+        let b = SchroedingerBox {
+            _states: states,
+            _chosen: OnceCell::new(),
+            _panic: Cell::new(None),
+            _frozen: Cell::new(false),
+            _seed: None,
+            _deadline: Cell::new(None),
+            _observations: Cell::new(0),
+            _collapsed_at: OnceCell::new(),
+            _on_collapse: Cell::new(vec![]),
+            #[cfg(feature = "test-util")]
+            _trimmed_from: None,
+            #[cfg(feature = "debug")]
+            _debug: debug::Tracker::new::<Cat>(),
+        };
+        b.track();
+        b
+    }
+
+    /// Creates a new `SchroedingerBox` from a set of states, like `new`, that always collapses the
+    /// same way for the same seed.
+    ///
+    /// The superposition still only collapses on the first observation, but the RNG it is
+    /// observed with is ignored. As with `SeedTree`, outcomes are only reproducible as long as
+    /// `rand`’s `StdRng` stays the same.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `states.len() == 0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let a = SchroedingerBox::with_seed((0..100).collect::<Vec<u32>>(), 42);
+    /// let b = SchroedingerBox::with_seed((0..100).collect::<Vec<u32>>(), 42);
+    /// assert_eq!(*a, *b);
+    /// ```
+    pub fn with_seed(states: Vec<Cat>, seed: u64) -> SchroedingerBox<Cat> {
+        SchroedingerBox::new(states).seeded(seed)
+    }
+
+    /// Makes the box collapse on its own if it hasn’t been observed within `duration`, the way a
+    /// real superposition decoheres through contact with its environment.
+    ///
+    /// Nothing happens in the background: the box notices that its time is up the next time
+    /// anything looks at it, even without observing it (`peek` or `is_collapsed`, say).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let cat = SchroedingerBox::new(vec!["alive", "dead"]).decohere_after(Duration::ZERO);
+    /// assert!(cat.is_collapsed());
+    /// ```
+    pub fn decohere_after(self, duration: Duration) -> SchroedingerBox<Cat> {
+        self._deadline.set(Instant::now().checked_add(duration));
+        self
+    }
+
+    /// Makes the box always collapse the same way for the same seed, as `with_seed` does.
+    pub fn seeded(mut self, seed: u64) -> SchroedingerBox<Cat> {
+        self._seed = Some(seed);
+        self
+    }
+
+    /// Like `new`, but returns an error instead of panicking if `states` doesn’t describe a
+    /// superposition that can be observed.
+    pub fn try_new(states: Vec<Cat>) -> Result<SchroedingerBox<Cat>, SuperpositionError> {
+        SchroedingerBox::try_from_probabilities(states.into_iter().map(|x| (1, x)).collect())
+    }
+
+    /// Like `from_probabilities`, but checks that the box can be observed, returning an error if
+    /// there are no states, or several states whose weights are all zero or add up to more than a
+    /// `u64` can hold.
+    pub fn try_from_probabilities(states: Vec<(u64, Cat)>)
+            -> Result<SchroedingerBox<Cat>, SuperpositionError> {
+        if states.len() != 1 {
+            total_weight(&states)?;
+        }
+        Ok(SchroedingerBox::from_probabilities(states))
+    }
+
+    /// Creates a new `SchroedingerBox` from a set of samples, with each distinct state weighted by
+    /// the number of times it occurs.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `samples` is empty.
+    pub fn from_samples<I>(samples: I) -> SchroedingerBox<Cat>
+            where I: IntoIterator<Item=Cat>, Cat: Clone + Eq + Hash {
+        let mut states = vec![];
+        add_samples(&mut states, &mut HashMap::new(), samples);
+        SchroedingerBox::from_probabilities(states)
+    }
+
+    /// Creates a new `SchroedingerBox` that, when first opened, either contains `value` or
+    /// panics with `message`, with the probabilities given by the weights.
+    ///
+    /// Once a box has panicked it stays that way: every later observation panics too. The
+    /// outcome can be pinned down in tests with `set_panic_mode`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::panic::{catch_unwind, AssertUnwindSafe};
+    /// # use schroedinger_box::{set_panic_mode, PanicMode, SchroedingerBox};
+    /// set_panic_mode(PanicMode::Always);
+    /// let cat = SchroedingerBox::maybe_panic(9, "purr", 1, "the cat has escaped");
+    /// assert!(catch_unwind(AssertUnwindSafe(|| *cat)).is_err());
+    /// ```
+    pub fn maybe_panic(weight_ok: u64, value: Cat, weight_panic: u64, message: &str)
+            -> SchroedingerBox<Cat> {
+        let b = SchroedingerBox {
+            _states: vec![(weight_ok, value)],
+            _chosen: OnceCell::new(),
+            _panic: Cell::new(Some(Panic::Pending(weight_panic, message.to_owned()))),
+            _frozen: Cell::new(false),
+            _seed: None,
+            _deadline: Cell::new(None),
+            _observations: Cell::new(0),
+            _collapsed_at: OnceCell::new(),
+            _on_collapse: Cell::new(vec![]),
+            #[cfg(feature = "test-util")]
+            _trimmed_from: None,
+            #[cfg(feature = "debug")]
+            _debug: debug::Tracker::new::<Cat>(),
+        };
+        b.track();
+        b
+    }
+
+    /// Collapses any superposition into a definite state using the given RNG, if it hasn’t
+    /// already, and returns a reference to it.
+    ///
+    /// Observing the box any other way uses the thread’s RNG (or the one given to
+    /// `with_rng_override`). A box that has already collapsed ignores `rng`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rand;
+    /// # extern crate schroedinger_box;
+    /// # use rand::SeedableRng;
+    /// # use rand::rngs::StdRng;
+    /// # use schroedinger_box::SchroedingerBox;
+    /// # fn main() {
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let cat = SchroedingerBox::new(vec!["alive", "dead"]);
+    /// let state = *cat.collapse_with(&mut rng);
+    /// assert_eq!(*cat, state);
+    /// # }
+    /// ```
+    pub fn collapse_with<R>(&self, rng: &mut R) -> &Cat where R: Rng {
+        self.try_collapse_with(rng).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `collapse_with`, but returns an error instead of panicking if the box can’t be
+    /// observed.
+    pub fn try_collapse_with<R>(&self, rng: &mut R) -> Result<&Cat, SuperpositionError>
+            where R: Rng {
+        let state = self.collapse(rng)?;
+        self._observations.set(self._observations.get().saturating_add(1));
+        Ok(state)
+    }
+
+    /// Like `try_collapse_with`, but doesn’t count as an observation.
+    fn collapse<R>(&self, rng: &mut R) -> Result<&Cat, SuperpositionError> where R: Rng {
+        let state = match self._seed {
+            Some(seed) => self.try_collapse_unseeded(&mut StdRng::seed_from_u64(seed)),
+            None => self.try_collapse_unseeded(rng),
+        }?;
+        if self._collapsed_at.get().is_none() {
+            #[cfg(feature = "log")]
+            self.log_collapse();
+            self._collapsed_at.get_or_init(Instant::now);
+            for f in self._on_collapse.take() {
+                f(state);
+            }
+        }
+        Ok(state)
+    }
+
+    /// Like `try_collapse_with`, but ignores the box’s seed.
+    fn try_collapse_unseeded<R>(&self, rng: &mut R) -> Result<&Cat, SuperpositionError>
+            where R: Rng {
+        let i = self.decide_unseeded(rng)?;
+        if self._states.len() != 1 {
+            let _ = self._chosen.set(i);
+        }
+        self.track();
+        Ok(&self._states[i].1)
+    }
+
+    /// Returns the index of the state that the box collapses into, choosing it just as observing
+    /// the box would (with its seed, and any forced outcome), but without recording the choice
+    /// or calling the callbacks.
+    fn decide<R>(&self, rng: &mut R) -> Result<usize, SuperpositionError> where R: Rng {
+        match self._seed {
+            Some(seed) => self.decide_unseeded(&mut StdRng::seed_from_u64(seed)),
+            None => self.decide_unseeded(rng),
+        }
+    }
+
+    /// Picks one of the states that the box could collapse into, without collapsing it.
+    ///
+    /// A box with only one state always gives that one, whatever its weight.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    fn sample_ref<R>(&self, rng: &mut R) -> &Cat where R: Rng {
+        let states = self.states();
+        if let [(_, ref only)] = *states {
+            return only
         }
+        &states[choose(states, rng)].1
     }
 
-    /// This function is unsafe because it does lots of unsafe stuff that’s probably able to cause
-    /// bad things to happen.
-    unsafe fn collapse(&self) {
-        // Using `UnsafeCell` is quite messy, so I hope I’ve got this bit right.
-        let vec = &mut *self._inner.get();
-        if vec.len() == 1 {
-            return
+    /// Like `decide`, but ignores the box’s seed.
+    fn decide_unseeded<R>(&self, rng: &mut R) -> Result<usize, SuperpositionError>
+            where R: Rng {
+        self.maybe_explode(rng);
+        if let Some(&i) = self._chosen.get() {
+            return Ok(i)
         }
-        let mut idx = {
-            let len = vec.iter().map(|&(f, _)| f).fold(0, |a, b| a + b);
-            thread_rng().gen_range(0, len)
-        } + 1; // For some reason, we need to add 1 to idx
+        if self._states.len() == 1 {
+            return Ok(0)
+        }
+        #[cfg(feature = "test-util")]
+        {
+            if let Some(i) = force::next_forced() {
+                assert!(i < self._states.len(), "no state at index {} in a box with {} states", i,
+                        self._states.len());
+                return Ok(i)
+            }
+        }
+        try_choose(&self._states, rng)
+    }
+
+    /// Logs the collapse of the box at the debug level.
+    #[cfg(feature = "log")]
+    fn log_collapse(&self) {
+        let total = self._states.iter().map(|&(w, _)| u128::from(w)).sum::<u128>();
+        debug!("{} collapsed into state {} of {}, out of a total weight of {}",
+               std::any::type_name::<Self>(), self._chosen.get().map_or(0, |&i| i),
+               self._states.len(), total);
+    }
+
+    /// Panics if the box was made by `maybe_panic` and it’s decided that it should.
+    fn maybe_explode<R>(&self, rng: &mut R) where R: Rng {
+        let message = match self._panic.take() {
+            None => return,
+            Some(Panic::Exploded(message)) => message,
+            Some(Panic::Pending(weight, message)) => {
+                let ok = self._states.iter().map(|&(w, _)| u128::from(w)).sum::<u128>();
+                let explode = weight > 0 && match PANIC_MODE.with(|m| m.get()) {
+                    _ if ok == 0 => true,
+                    PanicMode::Random => rng.gen_range(0..ok + u128::from(weight)) >= ok,
+                    PanicMode::Always => true,
+                    PanicMode::Never => false,
+                };
+                if !explode {
+                    return
+                }
+                message
+            },
+        };
+        // Make sure the next observation blows up as well.
+        self._panic.set(Some(Panic::Exploded(message.clone())));
+        panic!("{}", message);
+    }
+
+    /// Returns whether observing the box might panic, because it was made by `maybe_panic` and
+    /// either hasn’t been observed yet or has already panicked.
+    fn may_panic(&self) -> bool {
+        let panic = self._panic.take();
+        let may = panic.is_some();
+        self._panic.set(panic);
+        may
+    }
+
+    /// Tells the debug registry how many states the box has left, and whether it has collapsed.
+    #[cfg(feature = "debug")]
+    fn track(&self) {
+        let states = if self._chosen.get().is_some() { 1 } else { self._states.len() };
+        self._debug.update(states, self.is_collapsed());
+    }
+
+    #[cfg(not(feature = "debug"))]
+    fn track(&self) {}
+
+    /// Collapses the box if it was made by `decohere_after` and its time is up.
+    fn decohere(&self) {
+        if let Some(deadline) = self._deadline.get() {
+            if Instant::now() >= deadline {
+                self._deadline.set(None);
+                let _ = self.collapse(&mut local_rng());
+            }
+        }
+    }
+
+    /// Returns the states that are still possible: all of them, or just the observed one.
+    fn states(&self) -> &[(u64, Cat)] {
+        self.decohere();
+        self.track();
+        match self._chosen.get() {
+            Some(&i) => &self._states[i..=i],
+            None => &self._states,
+        }
+    }
 
-        let v = replace(vec, vec![]);
-        let (_, val) =
-            v.into_iter().skip_while(|&(f, _)| {
-                idx = idx.saturating_sub(f);
-                idx != 0
-            }).next().unwrap();
-        *vec = vec![(1, val)];
+    /// Like `states`, but for answering questions about the odds, which freezes the weights.
+    fn odds(&self) -> &[(u64, Cat)] {
+        self._frozen.set(true);
+        self.states()
+    }
+
+    /// Returns an error if the weights have been frozen.
+    fn check_thawed(&self) -> Result<(), SuperpositionError> {
+        if self._frozen.get() {
+            Err(SuperpositionError::WeightsFrozen)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns whether the weights are frozen.
+    ///
+    /// Answering any question about the odds (`peek` or `probability_of`, for example) freezes the
+    /// weights, so that the odds can’t silently change after they’ve been revealed. From then on,
+    /// anything that would change the weights (`retain`, `amplify`, `update` or `re_superpose`)
+    /// fails with `SuperpositionError::WeightsFrozen` until `thaw` is called.
+    pub fn is_frozen(&self) -> bool {
+        self._frozen.get()
+    }
+
+    /// Allows the weights to be changed again after they have been frozen.
+    pub fn thaw(&mut self) {
+        self._frozen.set(false);
+    }
+
+    /// Returns whether the box has collapsed into a definite state, without observing it.
+    ///
+    /// A box that has been observed has collapsed, and so has a box with only one state, unless
+    /// it was made by `maybe_panic` and hasn’t been observed yet.
+    pub fn is_collapsed(&self) -> bool {
+        self.decohere();
+        self._chosen.get().is_some() || self._states.len() == 1 && !self.may_panic()
+    }
+
+    /// Returns how many times the box has been observed.
+    ///
+    /// Every dereference counts, as does every other way of getting at the value (`try_get` or
+    /// `collapse_with`, for example). Looking at the odds doesn’t.
+    pub fn observation_count(&self) -> u64 {
+        self._observations.get()
+    }
+
+    /// Returns when the box collapsed, or `None` if it hasn’t yet.
+    ///
+    /// A box with only one state has nothing to collapse, so this is when it was first observed.
+    pub fn collapsed_at(&self) -> Option<Instant> {
+        self.decohere();
+        self._collapsed_at.get().cloned()
+    }
+
+    /// Registers a callback to be called with the observed state at the moment the box
+    /// collapses, or straight away if it already has.
+    ///
+    /// Each callback is called exactly once, in the order they were registered. As with
+    /// `collapsed_at`, a box with only one state collapses when it is first observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::sync::mpsc::channel;
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let (tx, rx) = channel();
+    /// let mut chest = SchroedingerBox::new(vec!["gold", "dust"]);
+    /// chest.on_collapse(move |&loot| tx.send(loot).unwrap());
+    /// assert!(rx.try_recv().is_err());
+    /// let loot = *chest;
+    /// assert_eq!(rx.try_recv(), Ok(loot));
+    /// ```
+    pub fn on_collapse<F>(&mut self, f: F) where F: FnOnce(&Cat) + Send + 'static {
+        if self._collapsed_at.get().is_some() {
+            // Not dereferencing, which would count as another observation.
+            f(&self.states()[0].1);
+        } else {
+            self._on_collapse.get_mut().push(Box::new(f));
+        }
+    }
+
+    /// Returns the states that the box could still collapse into, with their weights. This is
+    /// not an observation: the superposition is left as it is.
+    ///
+    /// If the box has already been observed, this is just the observed state. Seeing the weights
+    /// reveals the odds, so this freezes them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let cat = SchroedingerBox::from_probabilities(vec![(1, "alive"), (2, "dead")]);
+    /// assert_eq!(cat.peek_states(), &[(1, "alive"), (2, "dead")]);
+    /// assert_eq!(cat.peek(), None);
+    /// let state = *cat;
+    /// assert_eq!(cat.peek_states().len(), 1);
+    /// assert_eq!(cat.peek_states()[0].1, state);
+    /// ```
+    pub fn peek_states(&self) -> &[(u64, Cat)] {
+        self.odds()
+    }
+
+    /// Returns the probability of each state that the box could still collapse into, in the same
+    /// order as `peek_states`, without observing the box.
+    ///
+    /// This freezes the weights.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let cat = SchroedingerBox::from_probabilities(vec![(1, "alive"), (3, "dead")]);
+    /// assert_eq!(cat.probabilities(), vec![(0.25, &"alive"), (0.75, &"dead")]);
+    /// ```
+    pub fn probabilities(&self) -> Vec<(f64, &Cat)> {
+        let states = self.odds();
+        if let [(_, ref c)] = *states {
+            return vec![(1.0, c)]
+        }
+        let total = total_weight(states).unwrap_or_else(|e| panic!("{}", e)) as f64;
+        states.iter().map(|&(w, ref c)| (w as f64 / total, c)).collect()
+    }
+
+    /// Gives direct access to the remaining states without observing them, discarding the states
+    /// that weren’t chosen if the box has been observed.
+    ///
+    /// Callers must never leave the list empty. On an observed box, this moves the observed value
+    /// to the start of a new list, so only methods that are allowed to move it (those that change
+    /// the states or weights, not `deref_mut`) may use it.
+    fn states_mut(&mut self) -> &mut Vec<(u64, Cat)> {
+        self.decohere();
+        if let Some(i) = self._chosen.take() {
+            let chosen = self._states.swap_remove(i);
+            self._states = vec![chosen];
+            #[cfg(feature = "test-util")]
+            {
+                self._trimmed_from = Some(i);
+            }
+        }
+        self.track();
+        &mut self._states
+    }
+
+    /// Takes the states that are still possible out of the box without observing it, blowing up
+    /// first if the box was made by `maybe_panic` and it’s decided that it should.
+    fn into_states(mut self) -> Vec<(u64, Cat)> {
+        self.maybe_explode(&mut local_rng());
+        take(self.states_mut())
+    }
+
+    /// Puts the box back into a fresh superposition of `states`, whether or not it has been
+    /// observed, so that it collapses anew on the next observation.
+    ///
+    /// The box ends up as if it had just been made by `from_probabilities`, except that it keeps
+    /// its seed and any `on_collapse` callbacks that haven’t been called yet.
+    ///
+    /// This changes the weights, so it fails without touching the box if they are frozen: once
+    /// the odds of a round have been shown, `thaw` has to be called before starting the next.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `states.len() == 0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let mut round = SchroedingerBox::new(vec!["rock", "paper", "scissors"]);
+    /// let _ = *round;
+    /// round.re_superpose(vec![(1, "rock"), (1, "paper"), (1, "scissors")]).unwrap();
+    /// assert!(!round.is_collapsed());
+    /// assert_eq!(round.probability_of(&"rock"), 1.0 / 3.0);
+    /// assert!(round.re_superpose(vec![(1, "rock")]).is_err());
+    /// round.thaw();
+    /// round.re_superpose(vec![(1, "rock")]).unwrap();
+    /// ```
+    pub fn re_superpose(&mut self, states: Vec<(u64, Cat)>) -> Result<(), SuperpositionError> {
+        assert!(!states.is_empty(), "{}", SuperpositionError::Empty);
+        self.check_thawed()?;
+        self._deadline = Cell::new(None);
+        self._observations = Cell::new(0);
+        self.respread(states);
+        Ok(())
+    }
+
+    /// Replaces the states with `states` and forgets that the box was ever observed, apart from
+    /// how many times it was.
+    fn respread(&mut self, states: Vec<(u64, Cat)>) {
+        self._states = states;
+        self._chosen = OnceCell::new();
+        self._panic = Cell::new(None);
+        self._collapsed_at = OnceCell::new();
+        #[cfg(feature = "test-util")]
+        {
+            self._trimmed_from = None;
+        }
+        self.track();
+    }
+
+    /// Adds another state to the superposition, with the given weight.
+    ///
+    /// Nothing is added if the box has already been observed, if the weights are frozen or if the
+    /// total weight would no longer fit in a `u64`; the error says why instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let mut loot = SchroedingerBox::from_probabilities(vec![(10, "copper")]);
+    /// loot.push_state(3, "silver").unwrap();
+    /// loot.push_state(1, "gold").unwrap();
+    /// let _ = *loot;
+    /// assert!(loot.push_state(1, "mithril").is_err());
+    /// ```
+    pub fn push_state(&mut self, weight: u64, state: Cat) -> Result<(), SuperpositionError> {
+        if self.collapsed_at().is_some() {
+            return Err(SuperpositionError::AlreadyObserved)
+        }
+        self.check_thawed()?;
+        let total = self._states.iter().try_fold(weight, |t, &(w, _)| t.checked_add(w));
+        if total.is_none() {
+            return Err(SuperpositionError::WeightOverflow)
+        }
+        self.states_mut().push((weight, state));
+        Ok(())
+    }
+
+    /// Keeps only the states for which `f` returns `true`, without observing the box.
+    ///
+    /// This is how a box learns something about itself short of being observed: the remaining
+    /// states keep their relative odds, so the probabilities are renormalized over them. If the
+    /// box has already been observed, only the observed state is considered. Nothing is removed if
+    /// that would leave a box that can’t be observed, or if the weights are frozen; the error says
+    /// why instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox, SuperpositionError};
+    /// let mut cat = SchroedingerBox::from_probabilities(vec![(1, "alive"), (1, "dead"),
+    ///                                                        (2, "asleep")]);
+    /// // I heard a meow, so the cat isn’t dead.
+    /// cat.retain(|&c| c != "dead").unwrap();
+    /// assert_eq!(cat.peek(), None);
+    /// assert_eq!(cat.probability_of(&"asleep"), 2.0 / 3.0);
+    /// cat.thaw();
+    /// assert_eq!(cat.retain(|_| false), Err(SuperpositionError::Empty));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F) -> Result<(), SuperpositionError>
+            where F: FnMut(&Cat) -> bool {
+        self.check_thawed()?;
+        let states = self.states_mut();
+        let keep: Vec<bool> = states.iter().map(|(_, c)| f(c)).collect();
+        let kept: Vec<u64> =
+            states.iter().zip(&keep).filter(|&(_, &k)| k).map(|(s, _)| s.0).collect();
+        if kept.len() != 1 {
+            total_weight_of(kept.into_iter())?;
+        }
+        let mut keep = keep.into_iter();
+        states.retain(|_| keep.next().unwrap());
+        Ok(())
+    }
+
+    /// Removes the states for which `f` returns `false`, without observing the box, as `retain`
+    /// does.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let mut suspect = SchroedingerBox::new(vec!["butler", "gardener", "cook"]);
+    /// // The butler has an alibi.
+    /// suspect.retain_states(|&s| s != "butler").unwrap();
+    /// assert_eq!(suspect.probability_of(&"cook"), 0.5);
+    /// ```
+    pub fn retain_states<F>(&mut self, f: F) -> Result<(), SuperpositionError>
+            where F: FnMut(&Cat) -> bool {
+        self.retain(f)
+    }
+
+    /// Applies `f` to every state that is still possible, keeping the weights, without observing
+    /// the box.
+    ///
+    /// The new box is in superposition if and only if this one is, and keeps its seed and frozen
+    /// weights. Equal results are kept as separate states. Callbacks registered with
+    /// `on_collapse` are dropped without being called.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let d6 = SchroedingerBox::new((1..7).collect::<Vec<u32>>());
+    /// let even = d6.map(|x| x % 2 == 0);
+    /// assert_eq!(even.peek(), None);
+    /// assert_eq!(even.probability_of(&true), 0.5);
+    /// ```
+    pub fn map<U, F>(mut self, mut f: F) -> SchroedingerBox<U> where F: FnMut(Cat) -> U {
+        let states = take(self.states_mut()).into_iter().map(|(w, c)| (w, f(c))).collect();
+        let mut mapped = SchroedingerBox::from_probabilities(states);
+        mapped._panic = Cell::new(self._panic.take());
+        mapped._frozen = self._frozen.clone();
+        mapped._seed = self._seed;
+        mapped._deadline = self._deadline.clone();
+        mapped._observations = self._observations.clone();
+        mapped._collapsed_at = self._collapsed_at.clone();
+        #[cfg(feature = "test-util")]
+        {
+            mapped._trimmed_from = self._trimmed_from;
+        }
+        mapped.track();
+        mapped
+    }
+
+    /// Copies the box without observing it, so that the copy and the original collapse
+    /// independently of each other.
+    ///
+    /// The copy gets every state that is still possible, with the same weights (so the copy of an
+    /// observed box is observed too), and may panic if the original may. Nothing else is copied:
+    /// in particular, the copy has no seed, as a seed would make it collapse just like the
+    /// original.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let prototype = SchroedingerBox::from_probabilities(vec![(90, "junk"), (10, "treasure")]);
+    /// let chests: Vec<_> = (0..10).map(|_| prototype.clone_superposed()).collect();
+    /// assert!(chests.iter().all(|c| c.peek().is_none()));
+    /// assert_eq!(prototype.peek(), None);
+    /// ```
+    pub fn clone_superposed(&self) -> SchroedingerBox<Cat> where Cat: Clone {
+        let copy = SchroedingerBox::from_probabilities(self.states().to_vec());
+        let panic = self._panic.take();
+        self._panic.set(panic.clone());
+        copy._panic.set(panic);
+        copy
+    }
+
+    /// Splits the box into `n` copies of its superposition, each of which collapses
+    /// independently, as in the many-worlds picture; `fork_entangled` makes copies that all
+    /// collapse the same way instead.
+    ///
+    /// Each copy is made as by `clone_superposed`, so callbacks registered with `on_collapse`
+    /// are dropped without being called.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let worlds = SchroedingerBox::new(vec!["alive", "dead"]).fork(3);
+    /// assert_eq!(worlds.len(), 3);
+    /// assert!(worlds.iter().all(|w| w.peek().is_none()));
+    /// ```
+    pub fn fork(self, n: usize) -> Vec<SchroedingerBox<Cat>> where Cat: Clone {
+        (0..n).map(|_| self.clone_superposed()).collect()
     }
 
     /// Moves the value inside a `SchroedingerBox` out, consuming the box and collapsing any
     /// superposition into a definite state if needed.
     pub fn into_inner(self) -> Cat {
-        unsafe { self.collapse(); }
-        let vec = unsafe { &mut *self._inner.get() };
-        let v = replace(&mut *vec, vec![]);
-        debug_assert_eq!(v.len(), 1);
-        v.into_iter().next().unwrap().1
+        self.try_into_inner().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `into_inner`, but returns an error instead of panicking if the box can’t be observed.
+    pub fn try_into_inner(mut self) -> Result<Cat, SuperpositionError> {
+        self.try_collapse_with(&mut local_rng())?;
+        let i = self._chosen.take().unwrap_or(0);
+        Ok(self._states.swap_remove(i).1)
     }
+
+    /// Like dereferencing the box, but returns an error instead of panicking if the box can’t be
+    /// observed.
+    pub fn try_get(&self) -> Result<&Cat, SuperpositionError> {
+        self.try_collapse_with(&mut local_rng())
+    }
+
+    /// Like mutably dereferencing the box, but returns an error instead of panicking if the box
+    /// can’t be observed.
+    pub fn try_get_mut(&mut self) -> Result<&mut Cat, SuperpositionError> {
+        self.try_collapse_with(&mut local_rng())?;
+        let i = self._chosen.get().map_or(0, |&i| i);
+        Ok(&mut self._states[i].1)
+    }
+
+    /// Opens the box `k` times over, drawing `k` distinct states without replacement and
+    /// consuming the box.
+    ///
+    /// Each draw picks one of the remaining states with probability proportional to its weight.
+    /// States with a weight of zero are never drawn, so fewer than `k` states are returned if there
+    /// aren’t enough to go around. A box that has already collapsed has only one state to give.
+    pub fn observe_multiple(self, k: usize) -> Vec<Cat> {
+        match self._seed {
+            Some(seed) => self.observe_multiple_with(k, &mut StdRng::seed_from_u64(seed)),
+            None => self.observe_multiple_with(k, &mut local_rng()),
+        }
+    }
+
+    /// Like `observe_multiple`, but using the given RNG.
+    fn observe_multiple_with<R>(mut self, k: usize, rng: &mut R) -> Vec<Cat> where R: Rng {
+        self.maybe_explode(rng);
+        let mut states = take(self.states_mut());
+        if states.len() == 1 {
+            states.truncate(k);
+            return states.into_iter().map(|(_, c)| c).collect()
+        }
+        let mut drawn = Vec::with_capacity(k.min(states.len()));
+        while drawn.len() < k && states.iter().any(|&(w, _)| w > 0) {
+            let i = choose(&states, rng);
+            drawn.push(states.swap_remove(i).1);
+        }
+        drawn
+    }
+
+    /// Draws `k` independent samples from the box’s distribution, without observing it.
+    ///
+    /// This is much faster than preparing `k` boxes and opening each of them, since the weights
+    /// are only processed once. If the box has already collapsed, every sample is the observed
+    /// state.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    pub fn draw<R>(&self, k: usize, rng: &mut R) -> Vec<Cat> where Cat: Clone, R: Rng {
+        let states = self.states();
+        if states.len() == 1 {
+            return vec![states[0].1.clone(); k]
+        }
+        if let Err(e) = total_weight(states) {
+            panic!("{}", e);
+        }
+        let table = AliasTable::new(states.iter().map(|&(w, _)| w));
+        (0..k).map(|_| states[table.sample(rng)].1.clone()).collect()
+    }
+
+    /// Draws one sample from the box’s distribution, without observing it.
+    ///
+    /// Every call draws afresh, so the box can be kept in its superposition and used as a
+    /// sampler at the same time. If the box has already collapsed, the sample is the observed
+    /// state.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let die = SchroedingerBox::new((1..7).collect::<Vec<u32>>());
+    /// let total: u32 = (0..10).map(|_| die.sample()).sum();
+    /// assert!(10 <= total && total <= 60);
+    /// assert_eq!(die.peek(), None);
+    /// ```
+    pub fn sample(&self) -> Cat where Cat: Clone {
+        Distribution::sample(self, &mut local_rng())
+    }
+
+    /// Tallies `n` independent samples from the box’s distribution, without observing it.
+    ///
+    /// Only states that were drawn at least once appear in the result. Equal states are tallied
+    /// together, even if they were given separately.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rand;
+    /// # extern crate schroedinger_box;
+    /// # use rand::thread_rng;
+    /// # use schroedinger_box::SchroedingerBox;
+    /// # fn main() {
+    /// let coin = SchroedingerBox::new(vec!["heads", "tails"]);
+    /// let tally = coin.histogram(1000, &mut thread_rng());
+    /// assert_eq!(tally.values().sum::<u64>(), 1000);
+    /// # }
+    /// ```
+    pub fn histogram<R>(&self, n: usize, rng: &mut R) -> HashMap<Cat, u64>
+            where Cat: Clone + Eq + Hash, R: Rng {
+        let states = self.states();
+        let mut counts = vec![0; states.len()];
+        if states.len() == 1 {
+            counts[0] = n as u64;
+        } else if let Err(e) = total_weight(states) {
+            panic!("{}", e);
+        } else {
+            let table = AliasTable::new(states.iter().map(|&(w, _)| w));
+            for _ in 0..n {
+                counts[table.sample(rng)] += 1;
+            }
+        }
+        let mut tally = HashMap::new();
+        for ((_, c), n) in states.iter().zip(counts) {
+            if n > 0 {
+                *tally.entry(c.clone()).or_insert(0) += n;
+            }
+        }
+        tally
+    }
+
+    /// Observes the box once and hands the observed state to `arms`, returning whatever it
+    /// returns.
+    ///
+    /// This makes collapsing and branching on the outcome a single expression, so that match
+    /// guards and arms all see the same observation; `match_observe!` wraps it in `match` syntax.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let roll = SchroedingerBox::new((1..7).collect::<Vec<u32>>());
+    /// let message = roll.observe_match(|&n| if n == 6 { "critical hit" } else { "miss" });
+    /// assert_eq!(message == "critical hit", *roll == 6);
+    /// ```
+    pub fn observe_match<R, F>(&self, arms: F) -> R where F: FnOnce(&Cat) -> R {
+        arms(&**self)
+    }
+
+    /// Calls the closure inside a `SchroedingerBox`, collapsing any superposition of closures
+    /// into a definite one if needed.
+    ///
+    /// Every call after the first goes to the same closure. Stable Rust doesn’t allow
+    /// implementing `Fn` for a box directly, so closures taking several arguments should take a
+    /// tuple instead (or be called as `(*the_box)(a, b)`).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let double = |x: i32| x * 2;
+    /// let square = |x: i32| x * x;
+    /// let op = SchroedingerBox::new(vec![&double as &dyn Fn(i32) -> i32, &square]);
+    /// let four = op.call(2);
+    /// assert_eq!(four, 4);
+    /// assert!(op.call(3) == 6 || op.call(3) == 9);
+    /// ```
+    pub fn call<A, R>(&self, arg: A) -> R where Cat: Fn(A) -> R {
+        (**self)(arg)
+    }
+
+    /// Like `call`, but for closures that need to mutate their state.
+    pub fn call_mut<A, R>(&mut self, arg: A) -> R where Cat: FnMut(A) -> R {
+        (**self)(arg)
+    }
+
+    /// Returns a real closure that calls the closure inside the box, for passing to anything
+    /// that expects an `Fn`. The superposition collapses on the first call, not before.
+    pub fn as_fn<A, R>(&self) -> impl Fn(A) -> R + '_ where Cat: Fn(A) -> R {
+        move |arg| self.call(arg)
+    }
+}
+
+/// Creates a `SchroedingerBox` from a list of states, each of which may be given a weight with
+/// `weight => state`.
+///
+/// Without weights this is `SchroedingerBox::new`, and with them it is
+/// `SchroedingerBox::from_probabilities`.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate schroedinger_box;
+/// # use schroedinger_box::Observable;
+/// # fn main() {
+/// let cat = superposition![true, false];
+/// let loot = superposition![10 => "common", 1 => "rare"];
+/// assert_eq!(cat.probability_of(&true), 0.5);
+/// assert_eq!(loot.probability_of(&"rare"), 1.0 / 11.0);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! superposition {
+    ($($w:expr => $v:expr),+ $(,)*) => {
+        $crate::SchroedingerBox::from_probabilities(vec![$(($w, $v)),+])
+    };
+    ($($v:expr),+ $(,)*) => {
+        $crate::SchroedingerBox::new(vec![$($v),+])
+    };
+}
+
+/// Observes a box and matches on the observed state, as `SchroedingerBox::observe_match` does.
+///
+/// The arms are written as in a `match` on a reference to the state, so bindings borrow from the
+/// box. The box is observed exactly once, however many guards look at the state.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate schroedinger_box;
+/// # use schroedinger_box::SchroedingerBox;
+/// # fn main() {
+/// let weather = SchroedingerBox::new(vec![Some(12), Some(25), None]);
+/// let advice = match_observe!(weather, {
+///     Some(t) if *t > 20 => "wear a hat",
+///     Some(_) => "wear a coat",
+///     None => "stay inside",
+/// });
+/// assert_eq!(advice == "stay inside", weather.is_none());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! match_observe {
+    ($b:expr, { $($arms:tt)* }) => {
+        $crate::SchroedingerBox::observe_match(&$b, |state| match state { $($arms)* })
+    };
+}
+
+/// Chooses the index of one of the given states, with each state’s probability determined by its
+/// weight.
+///
+/// # Panic
+///
+/// Panics if no state can be chosen.
+fn choose<Cat, R>(states: &[(u64, Cat)], rng: &mut R) -> usize where R: Rng {
+    try_choose(states, rng).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like `choose`, but returns an error instead of panicking if no state can be chosen.
+fn try_choose<Cat, R>(states: &[(u64, Cat)], rng: &mut R) -> Result<usize, SuperpositionError>
+        where R: Rng {
+    Ok(pick(states, rng.gen_range(0..total_weight(states)?)))
+}
+
+/// Returns the total weight of the given states, or an error if no state can be chosen.
+fn total_weight<Cat>(states: &[(u64, Cat)]) -> Result<u64, SuperpositionError> {
+    total_weight_of(states.iter().map(|&(f, _)| f))
+}
+
+/// Like `total_weight`, but for the weights alone.
+fn total_weight_of<I>(weights: I) -> Result<u64, SuperpositionError>
+        where I: ExactSizeIterator<Item=u64> {
+    if weights.len() == 0 {
+        return Err(SuperpositionError::Empty)
+    }
+    if u64::try_from(weights.len()).is_err() {
+        return Err(SuperpositionError::TooManyStates)
+    }
+    let mut total = 0u64;
+    for w in weights {
+        total = total.checked_add(w).ok_or(SuperpositionError::WeightOverflow)?;
+    }
+    match total {
+        0 => Err(SuperpositionError::ZeroTotalWeight),
+        total => Ok(total),
+    }
+}
+
+/// Returns the index of the state that `r` lands on when the weights are laid end to end, where
+/// `r` is less than the total weight.
+///
+/// This is a `const fn` so that `collapse!` can share it.
+const fn pick<Cat>(states: &[(u64, Cat)], r: u64) -> usize {
+    let mut end = 0;
+    let mut i = 0;
+    loop {
+        end += states[i].0;
+        if r < end {
+            return i
+        }
+        i += 1;
+    }
+}
+
+/// Returns the Shannon entropy of the given states’ odds, in nats.
+fn entropy<Cat>(states: &[(u64, Cat)]) -> f64 {
+    let total = states.iter().map(|&(w, _)| w as f64).sum::<f64>();
+    states.iter()
+        .map(|&(w, _)| w as f64 / total)
+        .filter(|&p| p != 0.0)
+        .map(|p| -p * p.ln())
+        .sum()
+}
+
+/// Formats states that are still in superposition for `Debug`, mapping each state to its weight.
+fn debug_superposed<Cat>(f: &mut fmt::Formatter, states: &[(u64, Cat)]) -> fmt::Result
+        where Cat: fmt::Debug {
+    f.write_str("Superposed ")?;
+    f.debug_map().entries(states.iter().map(|(w, c)| (c, w))).finish()
+}
+
+/// Returns the greatest common divisor of `a` and `b`, or the other one if either is zero.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Adds `samples` to a set of weighted states, using `index` to find each state’s entry.
+///
+/// Distinct states keep the order in which they were first seen, so seeded observations are
+/// reproducible.
+fn add_samples<Cat, I>(states: &mut Vec<(u64, Cat)>, index: &mut HashMap<Cat, usize>, samples: I)
+        where I: IntoIterator<Item=Cat>, Cat: Clone + Eq + Hash {
+    for s in samples {
+        let i = *index.entry(s.clone()).or_insert_with(|| {
+            states.push((0, s));
+            states.len() - 1
+        });
+        states[i].0 += 1;
+    }
+}
+
+/// Opens every box yielded by an iterator, collapsing any superpositions, and returns references to
+/// the observed values in the same order.
+///
+/// The RNG is fetched once for the whole pass rather than once per box.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{collapse_all, SchroedingerBox};
+/// let mut cats = vec![SchroedingerBox::new(vec![true, false]),
+///                     SchroedingerBox::new(vec![true, false])];
+/// let states: Vec<bool> = collapse_all(cats.iter_mut()).into_iter().map(|s| *s).collect();
+/// assert_eq!(states, cats.iter().map(|c| **c).collect::<Vec<_>>());
+/// ```
+pub fn collapse_all<'a, Cat, I>(boxes: I) -> Vec<&'a mut Cat>
+        where Cat: 'a, I: IntoIterator<Item=&'a mut SchroedingerBox<Cat>> {
+    let mut rng = local_rng();
+    boxes.into_iter().map(|b| {
+        b.collapse_with(&mut rng);
+        &mut **b
+    }).collect()
+}
+
+/// Applies one mutation, drawn afresh from a box of weighted mutations, to `value`.
+///
+/// The box of mutations isn’t collapsed, so every call can pick a different one; this is what
+/// evolutionary search wants from its mutation operator. Chaining calls (or using `mutate_n`)
+/// composes several independently drawn mutations. If the box has already been opened, its
+/// observed mutation is always used.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{mutate, mutate_n, SchroedingerBox};
+/// let ops: SchroedingerBox<Box<dyn Fn(i32) -> i32>> = SchroedingerBox::from_probabilities(vec![
+///     (3, Box::new(|x| x + 1)),
+///     (1, Box::new(|x| x - 1)),
+/// ]);
+/// let child = mutate(10, &ops);
+/// assert!(child == 9 || child == 11);
+/// let grandchild = mutate_n(10, &ops, 4);
+/// assert!(6 <= grandchild && grandchild <= 14 && grandchild % 2 == 0);
+/// ```
+pub fn mutate<T, F>(value: T, ops: &SchroedingerBox<F>) -> T where F: Fn(T) -> T {
+    mutate_n(value, ops, 1)
+}
+
+/// Applies `n` mutations to `value`, each drawn afresh from a box of weighted mutations.
+pub fn mutate_n<T, F>(value: T, ops: &SchroedingerBox<F>, n: usize) -> T where F: Fn(T) -> T {
+    let mut rng = local_rng();
+    (0..n).fold(value, |v, _| ops.sample_ref(&mut rng)(v))
 }
 
 impl<Cat> Deref for SchroedingerBox<Cat> {
@@ -109,10 +1329,7 @@ impl<Cat> Deref for SchroedingerBox<Cat> {
     /// Obtains a reference to the value inside a `SchroedingerBox`, collapsing any superposition
     /// into a definite state if needed.
     fn deref(&self) -> &Cat {
-        unsafe {
-            self.collapse();
-            transmute::<&Cat, &Cat>(&(*self._inner.get())[0].1)
-        }
+        self.try_get().unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
@@ -120,17 +1337,20 @@ impl<Cat> DerefMut for SchroedingerBox<Cat> {
     /// Obtains a mutable reference to the value inside a `SchroedingerBox`, collapsing any
     /// superposition into a definite state if needed.
     fn deref_mut(&mut self) -> &mut Cat {
-        unsafe {
-            self.collapse();
-            transmute::<&mut Cat, &mut Cat>(&mut (*self._inner.get())[0].1)
-        }
+        self.try_get_mut().unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
+/// Formats the box without observing it: a box in superposition shows its states and their
+/// weights, as in `Superposed {1: 3, 2: 1}`, while an observed box shows its state, as in
+/// `Collapsed(2)`.
 impl<Cat> fmt::Debug for SchroedingerBox<Cat>
         where Cat: fmt::Debug {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        (**self).fmt(f)
+        match self.states() {
+            [(_, c)] if self.is_collapsed() => f.debug_tuple("Collapsed").field(c).finish(),
+            states => debug_superposed(f, states),
+        }
     }
 }
 
@@ -141,6 +1361,8 @@ impl<Cat> fmt::Display for SchroedingerBox<Cat>
     }
 }
 
+/// Compares the observed states, observing both boxes if needed; `superposition_eq` compares the
+/// distributions instead.
 impl<Cat> PartialEq for SchroedingerBox<Cat>
         where Cat: PartialEq {
     fn eq(&self, other: &SchroedingerBox<Cat>) -> bool {
@@ -170,16 +1392,65 @@ impl<Cat> Default for SchroedingerBox<Cat>
     }
 }
 
+impl<Cat> IntoIterator for SchroedingerBox<Cat> {
+    type Item = (u64, Cat);
+    type IntoIter = std::vec::IntoIter<(u64, Cat)>;
+
+    /// Takes the box apart into the states that are still possible and their weights, without
+    /// observing it.
+    ///
+    /// If the box has already been observed, the only state is the observed one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let b = SchroedingerBox::from_probabilities(vec![(1, "a"), (2, "b")]);
+    /// let shouted: SchroedingerBox<String> =
+    ///     b.into_iter().map(|(w, s)| (w * 10, s.to_uppercase())).collect();
+    /// assert!(*shouted == "A" || *shouted == "B");
+    /// ```
+    fn into_iter(self) -> std::vec::IntoIter<(u64, Cat)> {
+        self.into_states().into_iter()
+    }
+}
+
+impl<Cat> FromIterator<Cat> for SchroedingerBox<Cat> {
+    /// Creates a new `SchroedingerBox` from the states in an iterator, as `new` does.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the iterator is empty.
+    fn from_iter<I>(states: I) -> SchroedingerBox<Cat> where I: IntoIterator<Item=Cat> {
+        SchroedingerBox::new(states.into_iter().collect())
+    }
+}
+
+impl<Cat> FromIterator<(u64, Cat)> for SchroedingerBox<Cat> {
+    /// Creates a new `SchroedingerBox` from the weighted states in an iterator, as
+    /// `from_probabilities` does.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the iterator is empty.
+    fn from_iter<I>(states: I) -> SchroedingerBox<Cat> where I: IntoIterator<Item=(u64, Cat)> {
+        SchroedingerBox::from_probabilities(states.into_iter().collect())
+    }
+}
+
 impl<Cat> Clone for SchroedingerBox<Cat>
         where Cat: Clone {
     /// Clones a `SchroedingerBox`.
     ///
-    /// This collapses any superposition into a single state.
+    /// This collapses any superposition into a single state; `clone_superposed` copies the
+    /// superposition instead.
     fn clone(&self) -> SchroedingerBox<Cat> {
         SchroedingerBox::new(vec![(**self).clone()])
     }
 }
 
+/// Hashes the observed state, observing the box if needed; `hash_superposition` hashes the
+/// distribution instead.
 impl<Cat> Hash for SchroedingerBox<Cat>
         where Cat: Hash {
     fn hash<H>(&self, hasher: &mut H) where H: Hasher {
@@ -187,11 +1458,39 @@ impl<Cat> Hash for SchroedingerBox<Cat>
     }
 }
 
+/// A box is also a distribution over its states, so that it can be sampled with `Rng::sample`
+/// and `sample_iter`, just like `sample` but with any RNG.
+///
+/// # Panic
+///
+/// Sampling panics if the box can’t be observed.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rand;
+/// # extern crate schroedinger_box;
+/// # use rand::{thread_rng, Rng};
+/// # use schroedinger_box::SchroedingerBox;
+/// # fn main() {
+/// let coin = SchroedingerBox::new(vec!["heads", "tails"]);
+/// let flips: Vec<_> = thread_rng().sample_iter(&coin).take(10).collect();
+/// assert_eq!(flips.len(), 10);
+/// # }
+/// ```
+impl<Cat> Distribution<Cat> for SchroedingerBox<Cat> where Cat: Clone {
+    fn sample<R>(&self, mut rng: &mut R) -> Cat where R: Rng + ?Sized {
+        self.sample_ref(&mut rng).clone()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::SchroedingerBox;
+    use rand::thread_rng;
+    use super::{collapse_all, mutate, mutate_n, SchroedingerBox};
 
     #[test]
+    #[allow(clippy::manual_range_patterns)]
     fn whats_in_the_box() {
         // This is basically imposible to test, but we try anyway.
         // I think I’m beginning to understand how quantum physicists feel. :(
@@ -228,4 +1527,537 @@ mod tests {
         let own = foo.into_inner();
         assert_eq!(own, val);
     }
+
+    #[test]
+    fn test_observe_multiple() {
+        let foo = SchroedingerBox::from_probabilities(vec![(1, 1), (0, 2), (5, 3), (2, 4)]);
+        let mut winners = foo.observe_multiple(3);
+        winners.sort();
+        assert_eq!(winners, vec![1, 3, 4]);
+
+        let foo = SchroedingerBox::new(vec![1, 2, 3]);
+        assert_eq!(foo.observe_multiple(10).len(), 3);
+
+        let foo = SchroedingerBox::new(vec![1, 2, 3]);
+        let val = *foo;
+        assert_eq!(foo.observe_multiple(2), vec![val]);
+    }
+
+    #[test]
+    fn test_draw() {
+        let foo = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (3, 'c')]);
+        let samples = foo.draw(1000, &mut thread_rng());
+        assert_eq!(samples.len(), 1000);
+        assert!(samples.iter().all(|&c| c != 'b'));
+        assert!(samples.contains(&'c'));
+
+        let val = *foo;
+        assert!(foo.draw(10, &mut thread_rng()).iter().all(|&c| c == val));
+    }
+
+    #[test]
+    fn test_sample() {
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (3, 'c')]);
+        for _ in 0..100 {
+            assert_ne!(b.sample(), 'b');
+        }
+        assert!(!b.is_collapsed());
+        let observed = *b;
+        assert_eq!(b.sample(), observed);
+    }
+
+    #[test]
+    fn test_distribution() {
+        use rand::Rng;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let b = SchroedingerBox::from_probabilities(vec![(2, 1u32), (0, 2), (1, 3)]);
+        let mut rng = StdRng::seed_from_u64(7);
+        let samples: Vec<u32> = (&mut rng).sample_iter(&b).take(100).collect();
+        assert!(samples.iter().all(|&x| x != 2));
+        assert!(samples.contains(&1) && samples.contains(&3));
+        assert_eq!(rng.sample(SchroedingerBox::new(vec![5])), 5);
+        assert!(!b.is_collapsed());
+    }
+
+    #[test]
+    fn test_maybe_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use super::{set_panic_mode, PanicMode};
+
+        let prev = set_panic_mode(PanicMode::Never);
+        assert_eq!(prev, PanicMode::Random);
+        let foo = SchroedingerBox::maybe_panic(1, 5, 1000, "boom");
+        assert_eq!(*foo, 5);
+        set_panic_mode(PanicMode::Always);
+        // Already observed, so it can’t blow up any more.
+        assert_eq!(*foo, 5);
+
+        let foo = SchroedingerBox::maybe_panic(1, 5, 1, "boom");
+        assert!(catch_unwind(AssertUnwindSafe(|| *foo)).is_err());
+        set_panic_mode(PanicMode::Never);
+        assert!(catch_unwind(AssertUnwindSafe(|| *foo)).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| foo.into_inner())).is_err());
+
+        set_panic_mode(PanicMode::Always);
+        assert_eq!(SchroedingerBox::maybe_panic(1, 5, 0, "boom").into_inner(), 5);
+        set_panic_mode(PanicMode::Random);
+    }
+
+    #[test]
+    fn test_call() {
+        let mut count = 0;
+        let mut foo = SchroedingerBox::new(vec![|x: u32| x + 1, |x: u32| x + 1]);
+        assert_eq!(foo.call(1), 2);
+        assert_eq!((1..4).map(foo.as_fn()).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(foo.call_mut(5), 6);
+
+        let mut counter = SchroedingerBox::new(vec![|n: u32| { count += n; count }]);
+        counter.call_mut(2);
+        assert_eq!(counter.call_mut(3), 5);
+    }
+
+    #[test]
+    fn test_collapse_all() {
+        let mut boxes = [SchroedingerBox::new(vec![1, 2, 3]),
+                         SchroedingerBox::new(vec![4]),
+                         SchroedingerBox::new(vec![5, 6])];
+        let vals: Vec<i32> = collapse_all(boxes.iter_mut()).into_iter().map(|v| *v).collect();
+        assert_eq!(vals.len(), 3);
+        assert_eq!(vals[1], 4);
+        for (b, v) in boxes.iter().zip(vals) {
+            assert_eq!(**b, v);
+        }
+    }
+
+    #[test]
+    fn test_mutate() {
+        let ops = SchroedingerBox::new(vec![|x: u32| x * 2, |x: u32| x * 3]);
+        let children: Vec<u32> = (0..100).map(|_| mutate(1, &ops)).collect();
+        assert!(children.contains(&2) && children.contains(&3));
+        assert!(children.iter().all(|&c| c == 2 || c == 3));
+        assert_eq!(mutate_n(5, &ops, 0), 5);
+        let _ = *ops;
+        let fixed = mutate(1, &ops);
+        assert_eq!(mutate_n(1, &ops, 3), fixed * fixed * fixed);
+        let only = SchroedingerBox::from_probabilities(vec![(0, |x: u32| x + 1)]);
+        assert_eq!(mutate_n(1, &only, 2), 3);
+    }
+
+    #[test]
+    fn test_from_samples() {
+        let b = SchroedingerBox::from_samples("abracadabra".chars());
+        let draws = b.draw(1000, &mut thread_rng());
+        assert!(draws.iter().all(|&c| "abrcd".contains(c)));
+        let a = draws.iter().filter(|&&c| c == 'a').count();
+        assert!(a > draws.len() / 3 && a < draws.len() * 2 / 3);
+    }
+
+    #[test]
+    fn test_observe_match() {
+        use std::cell::Cell;
+
+        let seen = Cell::new(0);
+        let b = SchroedingerBox::new(vec![String::from("cat"), String::from("dog")]);
+        let noise = match_observe!(b, {
+            s if { seen.set(seen.get() + 1); s == "dog" } => "woof",
+            s if s.len() == 3 && *s == *b => "meow",
+            _ => unreachable!(),
+        });
+        assert_eq!(seen.get(), 1);
+        assert_eq!(noise, if *b == "dog" { "woof" } else { "meow" });
+        assert_eq!(b.observe_match(|s| s.clone()), *b);
+    }
+
+    #[test]
+    fn test_is_collapsed() {
+        use super::{set_panic_mode, PanicMode};
+
+        let b = SchroedingerBox::new(vec![1, 2]);
+        assert!(!b.is_collapsed());
+        let _ = *b;
+        assert!(b.is_collapsed());
+        assert!(SchroedingerBox::new(vec![3]).is_collapsed());
+        let old = set_panic_mode(PanicMode::Never);
+        let risky = SchroedingerBox::maybe_panic(1, 4, 1, "boom");
+        assert!(!risky.is_collapsed());
+        assert_eq!(*risky, 4);
+        assert!(risky.is_collapsed());
+        set_panic_mode(old);
+    }
+
+    #[test]
+    fn test_probabilities() {
+        let foo = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (3, 'a')]);
+        assert_eq!(foo.probabilities(), vec![(0.25, &'a'), (0.0, &'b'), (0.75, &'a')]);
+        assert!(foo.is_frozen());
+        assert_eq!(*foo, 'a');
+        assert_eq!(foo.probabilities(), vec![(1.0, &'a')]);
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 'z')]);
+        assert_eq!(zero.probabilities(), vec![(1.0, &'z')]);
+    }
+
+    #[test]
+    fn test_collapse_with() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let states: Vec<u32> = (0..1000).collect();
+        let picks: Vec<u32> = (0..2).map(|_| {
+            let b = SchroedingerBox::new(states.clone());
+            *b.collapse_with(&mut StdRng::seed_from_u64(7))
+        }).collect();
+        assert_eq!(picks[0], picks[1]);
+        let b = SchroedingerBox::new(states);
+        let first = *b.collapse_with(&mut StdRng::seed_from_u64(7));
+        assert_eq!(*b.collapse_with(&mut StdRng::seed_from_u64(8)), first);
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 1), (0, 2)]);
+        assert!(zero.try_collapse_with(&mut thread_rng()).is_err());
+    }
+
+    #[test]
+    fn test_with_seed() {
+        let states: Vec<u32> = (0..1000).collect();
+        let a = SchroedingerBox::with_seed(states.clone(), 1);
+        let b = SchroedingerBox::with_seed(states.clone(), 1);
+        assert_eq!(*a.collapse_with(&mut thread_rng()), *b);
+        let weighted = SchroedingerBox::from_probabilities(vec![(1, 'a'), (1, 'b'), (1, 'c')]);
+        let drawn = weighted.seeded(5).observe_multiple(3);
+        let again = SchroedingerBox::from_probabilities(vec![(1, 'a'), (1, 'b'), (1, 'c')]);
+        assert_eq!(again.seeded(5).observe_multiple(3), drawn);
+        let seeds = (0..10).filter(|&seed| *SchroedingerBox::with_seed(states.clone(), seed) == *a);
+        assert!(seeds.count() < 10);
+    }
+
+    #[test]
+    fn test_map() {
+        use Observable;
+
+        let foo = SchroedingerBox::from_probabilities(vec![(1, 1), (2, 2), (3, 3)]);
+        let doubled = foo.map(|x| x * 2);
+        assert_eq!(doubled.states(), &[(1, 2), (2, 4), (3, 6)][..]);
+        let observed = *doubled;
+        let halved = doubled.map(|x| x / 2);
+        assert_eq!(halved.peek(), Some(&(observed / 2)));
+        let seeded = SchroedingerBox::with_seed((0..100).collect::<Vec<u32>>(), 3);
+        assert_eq!(*seeded.map(|x| x + 1), *SchroedingerBox::with_seed((1..101).collect(), 3));
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let d6 = (1..=6).collect::<SchroedingerBox<_>>();
+        assert_eq!(d6.states(), &[(1, 1), (1, 2), (1, 3), (1, 4), (1, 5), (1, 6)][..]);
+        let coin: SchroedingerBox<&str> = vec![(1, "heads"), (3, "tails")].into_iter().collect();
+        assert_eq!(coin.states(), &[(1, "heads"), (3, "tails")][..]);
+        let pairs: SchroedingerBox<(u64, char)> = vec![(1, (2, 'a'))].into_iter().collect();
+        assert_eq!(*pairs, (2, 'a'));
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let foo = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (2, 'c')]);
+        assert_eq!(foo.into_iter().collect::<Vec<_>>(), vec![(1, 'a'), (0, 'b'), (2, 'c')]);
+        let bar = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (2, 'c')]);
+        let observed = *bar;
+        let weight = if observed == 'a' { 1 } else { 2 };
+        assert_eq!(bar.into_iter().collect::<Vec<_>>(), vec![(weight, observed)]);
+    }
+
+    #[test]
+    fn test_superposition_macro() {
+        let plain = superposition![1, 2, 3,];
+        assert_eq!(plain.states(), &[(1, 1), (1, 2), (1, 3)][..]);
+        let weighted = superposition![2 => 'a', 0 => 'b'];
+        assert_eq!(weighted.states(), &[(2, 'a'), (0, 'b')][..]);
+        let nested = superposition![1 => superposition![4, 5], 3 => superposition![6]];
+        assert!(*nested.map(|b| *b) >= 4);
+    }
+
+    #[test]
+    fn test_decoherence() {
+        use std::thread::sleep;
+        use std::time::Duration;
+        use Observable;
+
+        let foo = SchroedingerBox::new(vec![1, 2, 3]).decohere_after(Duration::from_millis(20));
+        assert!(!foo.is_collapsed());
+        assert_eq!(foo.peek(), None);
+        sleep(Duration::from_millis(30));
+        assert!(foo.peek().is_some());
+        let forever = SchroedingerBox::new(vec![1, 2]).decohere_after(Duration::MAX);
+        assert!(!forever.is_collapsed());
+        let mapped = SchroedingerBox::new(vec![1, 2]).decohere_after(Duration::ZERO).map(|x| x);
+        assert_eq!(mapped.states().len(), 1);
+    }
+
+    #[test]
+    fn test_observation_metadata() {
+        use std::time::Instant;
+
+        let foo = SchroedingerBox::new(vec![1, 2, 3]);
+        assert_eq!((foo.observation_count(), foo.collapsed_at()), (0, None));
+        foo.probabilities();
+        assert_eq!(foo.observation_count(), 0);
+        let before = Instant::now();
+        let _ = *foo;
+        let at = foo.collapsed_at().unwrap();
+        assert!(at >= before);
+        let _ = foo.try_get();
+        assert_eq!((foo.observation_count(), foo.collapsed_at()), (2, Some(at)));
+    }
+
+    #[test]
+    fn test_on_collapse() {
+        use std::sync::{Arc, Mutex};
+
+        let calls = Arc::new(Mutex::new(vec![]));
+        let mut foo = SchroedingerBox::new(vec![1, 2, 3]);
+        for tag in 0..2 {
+            let calls = calls.clone();
+            foo.on_collapse(move |&x| calls.lock().unwrap().push((tag, x)));
+        }
+        foo.probabilities();
+        assert!(calls.lock().unwrap().is_empty());
+        let state = *foo;
+        let _ = *foo;
+        assert_eq!(*calls.lock().unwrap(), vec![(0, state), (1, state)]);
+        let later = calls.clone();
+        foo.on_collapse(move |&x| later.lock().unwrap().push((2, x)));
+        assert_eq!(calls.lock().unwrap()[2], (2, state));
+        assert_eq!(foo.observation_count(), 2);
+    }
+
+    #[test]
+    fn test_re_superpose() {
+        let mut b = SchroedingerBox::from_probabilities(vec![(1, 1), (1, 2)]).seeded(3);
+        let first = *b;
+        let _ = b.probabilities();
+        assert_eq!(b.re_superpose(vec![(0, 3), (1, 4)]), Err(::SuperpositionError::WeightsFrozen));
+        assert_eq!(*b, first);
+        b.thaw();
+        b.re_superpose(vec![(0, 3), (1, 4)]).unwrap();
+        assert!(!b.is_collapsed() && !b.is_frozen());
+        assert_eq!((b.observation_count(), b.collapsed_at()), (0, None));
+        assert_eq!(*b, 4);
+        b.re_superpose(vec![(1, 1), (1, 2)]).unwrap();
+        assert_eq!(*b, first);
+    }
+
+    #[test]
+    fn test_push_state() {
+        use SuperpositionError;
+
+        let mut b = SchroedingerBox::from_probabilities(vec![(0, 'a')]);
+        b.push_state(1, 'b').unwrap();
+        assert_eq!(b.push_state(u64::MAX, 'c'), Err(SuperpositionError::WeightOverflow));
+        assert_eq!(b.states(), &[(0, 'a'), (1, 'b')][..]);
+        let _ = b.probabilities();
+        assert_eq!(b.push_state(1, 'c'), Err(SuperpositionError::WeightsFrozen));
+        assert_eq!(*b, 'b');
+        b.thaw();
+        assert_eq!(b.push_state(1, 'c'), Err(SuperpositionError::AlreadyObserved));
+    }
+
+    #[test]
+    #[should_panic(expected = "superposition has no states")]
+    fn test_empty() {
+        SchroedingerBox::<u8>::new(vec![]);
+    }
+
+    #[test]
+    fn test_debug() {
+        let foo = SchroedingerBox::from_probabilities(vec![(3, 1), (1, 2)]);
+        assert_eq!(format!("{:?}", foo), "Superposed {1: 3, 2: 1}");
+        assert!(!foo.is_collapsed() && !foo.is_frozen());
+        let observed = *foo;
+        assert_eq!(format!("{:?}", foo), format!("Collapsed({})", observed));
+        assert_eq!(format!("{:?}", SchroedingerBox::new(vec!["cat"])), "Collapsed(\"cat\")");
+    }
+
+    #[test]
+    fn test_clone_superposed() {
+        let original = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (1, 'c')])
+            .seeded(5);
+        let copy = original.clone_superposed();
+        assert!(!copy.is_collapsed());
+        assert_eq!(copy.states(), original.states());
+        let observed = *original;
+        assert!(!copy.is_collapsed());
+        assert!(original.clone_superposed().is_collapsed());
+        assert_eq!(*original.clone_superposed(), observed);
+        let risky = SchroedingerBox::maybe_panic(1, 'a', 1, "boom");
+        assert!(!risky.clone_superposed().is_collapsed());
+    }
+
+    #[test]
+    fn test_fork() {
+        let worlds = SchroedingerBox::new((0..1000).collect::<Vec<u32>>()).fork(3);
+        let outcomes: Vec<u32> = worlds.iter().map(|w| **w).collect();
+        // There's a one in a million chance that all three worlds agree by accident.
+        assert!(outcomes.iter().any(|&x| x != outcomes[0]));
+        assert!(SchroedingerBox::new(vec![1]).fork(0).is_empty());
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_logging() {
+        use std::sync::Mutex;
+        use log::{set_logger, set_max_level, LevelFilter, Log, Metadata, Record};
+
+        struct Logger(Mutex<Vec<String>>);
+
+        impl Log for Logger {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &Record) {
+                let message = record.args().to_string();
+                if message.contains("Logged") {
+                    self.0.lock().unwrap().push(message);
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        #[derive(Debug)]
+        struct Logged;
+
+        static LOGGER: Logger = Logger(Mutex::new(vec![]));
+        set_logger(&LOGGER).unwrap();
+        set_max_level(LevelFilter::Debug);
+        let foo = SchroedingerBox::from_probabilities(vec![(0, Logged), (5, Logged)]);
+        let _ = *foo;
+        let _ = *foo;
+        assert_eq!(*LOGGER.0.lock().unwrap(),
+                   vec![format!("{} collapsed into state 1 of 2, out of a total weight of 5",
+                                std::any::type_name::<SchroedingerBox<Logged>>())]);
+    }
+
+    // The tests below poke at the aliasing of observed states, and are small enough to be run
+    // under Miri with `cargo +nightly miri test`.
+
+    #[test]
+    fn deref_is_stable() {
+        let foo = SchroedingerBox::new(vec![String::from("a"), String::from("b")]);
+        let first: &String = &foo;
+        let second: &String = &foo;
+        assert!(std::ptr::eq(first, second));
+        let _ = foo.clone();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn address_survives_everything() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hash;
+
+        let mut foo = SchroedingerBox::new(vec![1u32, 2, 3]);
+        let addr = &*foo as *const u32;
+        assert_eq!(&mut *foo as *const u32, addr);
+        let _ = foo.clone();
+        foo.hash(&mut DefaultHasher::new());
+        assert_eq!(format!("{:?}", foo), format!("Collapsed({:?})", *foo));
+        let moved = foo;
+        assert_eq!(&*moved as *const u32, addr);
+        let mut boxed = Box::new(moved);
+        assert_eq!(collapse_all(Some(&mut *boxed))[0] as *const u32, addr);
+    }
+
+    #[test]
+    fn deref_mut_then_deref() {
+        let mut foo = SchroedingerBox::new(vec![vec![1], vec![2, 3]]);
+        foo.push(4);
+        let len = foo.len();
+        assert!(len == 2 || len == 3);
+        assert_eq!(*foo.last().unwrap(), 4);
+        assert_eq!(foo.into_inner().len(), len);
+    }
+
+    #[test]
+    fn panic_during_collapse() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use super::{set_panic_mode, PanicMode};
+
+        let old = set_panic_mode(PanicMode::Always);
+        let foo = SchroedingerBox::maybe_panic(1, Box::new(5), 1, "boom");
+        assert!(catch_unwind(AssertUnwindSafe(|| **foo)).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| **foo)).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| foo.into_inner())).is_err());
+        set_panic_mode(old);
+    }
+
+    #[test]
+    fn test_degenerate() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use super::SuperpositionError;
+
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 1), (0, 2)]);
+        assert_eq!(zero.try_get(), Err(SuperpositionError::ZeroTotalWeight));
+        assert!(catch_unwind(AssertUnwindSafe(|| *zero)).is_err());
+        assert_eq!(zero.try_into_inner(), Err(SuperpositionError::ZeroTotalWeight));
+
+        // A single state is certain, whatever its weight.
+        let mut one = SchroedingerBox::from_probabilities(vec![(0, 1)]);
+        assert_eq!(one.try_get_mut().map(|v| *v), Ok(1));
+        assert_eq!(one.try_into_inner(), Ok(1));
+    }
+
+    #[test]
+    fn test_validation() {
+        use super::SuperpositionError;
+
+        assert_eq!(SchroedingerBox::<u8>::try_new(vec![]).err(), Some(SuperpositionError::Empty));
+        assert_eq!(SchroedingerBox::try_from_probabilities(vec![(0, 1), (0, 2)]).err(),
+                   Some(SuperpositionError::ZeroTotalWeight));
+        assert_eq!(SchroedingerBox::try_from_probabilities(vec![(u64::MAX, 1), (1, 2)]).err(),
+                   Some(SuperpositionError::WeightOverflow));
+        assert!(SchroedingerBox::try_new(vec![1, 2]).is_ok());
+
+        let mut foo = SchroedingerBox::from_probabilities(
+            vec![(1, 1), (0, 2), (0, 5), (1, 3), (1, 4)]);
+        assert_eq!(foo.retain(|&x| x == 2 || x == 5), Err(SuperpositionError::ZeroTotalWeight));
+        assert_eq!(foo.retain(|_| false), Err(SuperpositionError::Empty));
+        assert_eq!(foo.retain_states(|_| false), Err(SuperpositionError::Empty));
+        assert_eq!(foo.retain_states(|&x| x != 4), Ok(()));
+        assert!(*foo == 1 || *foo == 3);
+        let val = *foo;
+        assert_eq!(foo.retain(|&x| x != val), Err(SuperpositionError::Empty));
+        assert_eq!(foo.into_inner(), val);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let foo = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (1, 'c'), (2, 'a')]);
+        let tally = foo.histogram(1000, &mut thread_rng());
+        assert_eq!(tally.len(), 2);
+        assert_eq!(tally[&'a'] + tally[&'c'], 1000);
+        assert!(tally[&'a'] > tally[&'c']);
+
+        let val = *foo;
+        let tally = foo.histogram(10, &mut thread_rng());
+        assert_eq!(tally.into_iter().collect::<Vec<_>>(), vec![(val, 10)]);
+    }
+
+    #[test]
+    fn test_frozen_weights() {
+        use super::{Observable, SuperpositionError};
+
+        let mut foo = SchroedingerBox::from_probabilities(vec![(1, 'a'), (1, 'b'), (2, 'c')]);
+        assert!(!foo.is_frozen());
+        foo.amplify(2, |&c| c == 'a').unwrap();
+        assert_eq!(foo.probability_of(&'a'), 0.4);
+        assert!(foo.is_frozen());
+        assert_eq!(foo.amplify(2, |&c| c == 'b'), Err(SuperpositionError::WeightsFrozen));
+        assert_eq!(foo.retain(|&c| c != 'c'), Err(SuperpositionError::WeightsFrozen));
+        assert_eq!(foo.probability_of(&'a'), 0.4);
+        foo.thaw();
+        foo.retain(|&c| c != 'c').unwrap();
+        assert_eq!(foo.probability_of(&'a'), 2.0 / 3.0);
+        foo.thaw();
+        assert_eq!(foo.peek(), None);
+        assert!(foo.is_frozen());
+    }
 }
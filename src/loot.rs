@@ -0,0 +1,227 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Loot tables: nested weighted drops with guaranteed items and pity timers.
+
+use SchroedingerBox;
+
+/// A single weighted entry of a `LootTable`.
+#[derive(Clone, Debug)]
+pub enum Entry<T> {
+    /// Drops the item.
+    Item(T),
+    /// Rolls the nested table once and drops whatever it drops.
+    Table(LootTable<T>),
+    /// Drops nothing.
+    Nothing,
+}
+
+/// A loot table.
+///
+/// Every roll drops all of the table’s guaranteed items plus one weighted entry, chosen by
+/// opening a `SchroedingerBox` of the entries. Entries may themselves be tables.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::loot::LootTable;
+/// let gems = LootTable::new().item(3, "ruby").item(1, "diamond");
+/// let mut chest = LootTable::new()
+///     .guaranteed("gold")
+///     .item(10, "sword")
+///     .nothing(5)
+///     .rare_table(1, gems)
+///     .pity(20);
+/// let drops = chest.roll();
+/// assert_eq!(drops[0], "gold");
+/// ```
+#[derive(Clone, Debug)]
+pub struct LootTable<T> {
+    entries: Vec<(u64, Entry<T>)>,
+    rare: Vec<bool>,
+    guaranteed: Vec<T>,
+    pity: Option<u32>,
+    dry_streak: u32,
+}
+
+impl<T> LootTable<T> {
+    /// Creates an empty loot table, which drops nothing.
+    pub fn new() -> LootTable<T> {
+        LootTable {
+            entries: vec![],
+            rare: vec![],
+            guaranteed: vec![],
+            pity: None,
+            dry_streak: 0,
+        }
+    }
+
+    /// Adds a weighted entry.
+    pub fn entry(mut self, weight: u64, entry: Entry<T>) -> LootTable<T> {
+        self.entries.push((weight, entry));
+        self.rare.push(false);
+        self
+    }
+
+    /// Adds a weighted entry that counts as a rare drop for the pity timer.
+    pub fn rare_entry(mut self, weight: u64, entry: Entry<T>) -> LootTable<T> {
+        self.entries.push((weight, entry));
+        self.rare.push(true);
+        self
+    }
+
+    /// Adds a weighted item.
+    pub fn item(self, weight: u64, item: T) -> LootTable<T> {
+        self.entry(weight, Entry::Item(item))
+    }
+
+    /// Adds a weighted item that counts as a rare drop for the pity timer.
+    pub fn rare_item(self, weight: u64, item: T) -> LootTable<T> {
+        self.rare_entry(weight, Entry::Item(item))
+    }
+
+    /// Adds a weighted nested table.
+    pub fn table(self, weight: u64, table: LootTable<T>) -> LootTable<T> {
+        self.entry(weight, Entry::Table(table))
+    }
+
+    /// Adds a weighted nested table that counts as a rare drop for the pity timer.
+    pub fn rare_table(self, weight: u64, table: LootTable<T>) -> LootTable<T> {
+        self.rare_entry(weight, Entry::Table(table))
+    }
+
+    /// Adds a weighted chance of dropping nothing.
+    pub fn nothing(self, weight: u64) -> LootTable<T> {
+        self.entry(weight, Entry::Nothing)
+    }
+
+    /// Adds an item that is dropped on every roll.
+    pub fn guaranteed(mut self, item: T) -> LootTable<T> {
+        self.guaranteed.push(item);
+        self
+    }
+
+    /// Enables a pity timer: after `rolls` consecutive rolls without a rare drop, the next roll
+    /// only considers the rare entries.
+    pub fn pity(mut self, rolls: u32) -> LootTable<T> {
+        self.pity = Some(rolls);
+        self
+    }
+
+    /// Returns how many rolls in a row have not produced a rare drop.
+    pub fn dry_streak(&self) -> u32 {
+        self.dry_streak
+    }
+}
+
+impl<T> LootTable<T> where T: Clone {
+    /// Rolls the table once, returning the guaranteed items followed by whatever the chosen
+    /// entry dropped.
+    pub fn roll(&mut self) -> Vec<T> {
+        let mut drops = self.guaranteed.clone();
+        let available: Vec<_> = (0..self.entries.len()).collect();
+        self.draw(&available, &mut drops);
+        drops
+    }
+
+    /// Rolls the table `n` times, returning all of the drops together. Guaranteed items are only
+    /// dropped once.
+    ///
+    /// Without replacement, each entry can be chosen at most once, and rolling stops early once
+    /// every entry with a nonzero weight has been chosen.
+    pub fn roll_n(&mut self, n: usize, replacement: bool) -> Vec<T> {
+        let mut drops = self.guaranteed.clone();
+        let mut available: Vec<_> = (0..self.entries.len()).collect();
+        for _ in 0..n {
+            match self.draw(&available, &mut drops) {
+                Some(idx) if !replacement => available.retain(|&i| i != idx),
+                Some(_) => {},
+                None => break,
+            }
+        }
+        drops
+    }
+
+    /// Chooses an entry among `available`, honouring the pity timer, and adds its drops to
+    /// `drops`. Returns the index of the chosen entry, or `None` if nothing could be chosen.
+    fn draw(&mut self, available: &[usize], drops: &mut Vec<T>) -> Option<usize> {
+        let candidates = |rare_only: bool| -> Vec<(u64, usize)> {
+            available.iter()
+                .filter(|&&i| !rare_only || self.rare[i])
+                .map(|&i| (self.entries[i].0, i))
+                .collect()
+        };
+        let mut states = match self.pity {
+            Some(p) if self.dry_streak >= p => candidates(true),
+            _ => vec![],
+        };
+        if states.is_empty() {
+            states = candidates(false);
+        } else if states.iter().all(|&(w, _)| w == 0) {
+            // Rare entries that can *only* be obtained out of pity are all equally likely.
+            for s in &mut states {
+                s.0 = 1;
+            }
+        }
+        if states.iter().all(|&(w, _)| w == 0) {
+            return None
+        }
+        let idx = SchroedingerBox::from_probabilities(states).into_inner();
+
+        if self.rare[idx] {
+            self.dry_streak = 0;
+        } else {
+            self.dry_streak = self.dry_streak.saturating_add(1);
+        }
+        match self.entries[idx].1 {
+            Entry::Item(ref item) => drops.push(item.clone()),
+            Entry::Table(ref mut table) => drops.extend(table.roll()),
+            Entry::Nothing => {},
+        }
+        Some(idx)
+    }
+}
+
+impl<T> Default for LootTable<T> {
+    fn default() -> LootTable<T> {
+        LootTable::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LootTable;
+
+    #[test]
+    fn nested_and_guaranteed() {
+        let inner = LootTable::new().guaranteed(1).item(1, 2);
+        let mut outer = LootTable::new().guaranteed(0).table(1, inner);
+        assert_eq!(outer.roll(), vec![0, 1, 2]);
+        assert_eq!(LootTable::<u8>::new().roll(), vec![]);
+    }
+
+    #[test]
+    fn without_replacement() {
+        let mut table = LootTable::new().item(1, 'a').item(1000, 'b').item(1, 'c').nothing(0);
+        let mut drops = table.roll_n(5, false);
+        drops.sort();
+        assert_eq!(drops, vec!['a', 'b', 'c']);
+        assert_eq!(table.roll_n(5, true).len(), 5);
+    }
+
+    #[test]
+    fn pity_timer() {
+        let mut table = LootTable::new().item(1, "junk").rare_item(0, "legendary").pity(3);
+        for _ in 0..3 {
+            assert_eq!(table.roll(), vec!["junk"]);
+        }
+        assert_eq!(table.dry_streak(), 3);
+        assert_eq!(table.roll(), vec!["legendary"]);
+        assert_eq!(table.dry_streak(), 0);
+    }
+}
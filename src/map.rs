@@ -0,0 +1,167 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::collections::hash_map::{self, Entry};
+use std::hash::Hash;
+use std::iter::FromIterator;
+use SchroedingerBox;
+
+/// A hash map whose values are each in their own superposition.
+///
+/// Looking up a key with `get` observes only the value behind that key. Iterating can either
+/// observe every value (`iter`) or leave them all alone (`boxes`).
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{SchroedingerBox, SchroedingerHashMap};
+/// let mut chunks = SchroedingerHashMap::new();
+/// for x in 0..100 {
+///     chunks.insert(x, SchroedingerBox::new(vec!["forest", "lake", "mountain"]));
+/// }
+/// // The player only ever looks at the chunk they are standing in.
+/// assert!(["forest", "lake", "mountain"].contains(chunks.get(&42).unwrap()));
+/// assert_eq!(chunks.boxes().filter(|&(_, b)| b.is_collapsed()).count(), 1);
+/// chunks.entry(100).or_insert_with(|| SchroedingerBox::new(vec!["desert"]));
+/// assert_eq!(chunks.len(), 101);
+/// ```
+pub struct SchroedingerHashMap<K, V> {
+    map: HashMap<K, SchroedingerBox<V>>,
+}
+
+impl<K, V> SchroedingerHashMap<K, V> where K: Eq + Hash {
+    /// Creates an empty map.
+    pub fn new() -> SchroedingerHashMap<K, V> {
+        SchroedingerHashMap {
+            map: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Puts a superposition behind `key`, returning the box that was there before, if any.
+    pub fn insert(&mut self, key: K, b: SchroedingerBox<V>) -> Option<SchroedingerBox<V>> {
+        self.map.insert(key, b)
+    }
+
+    /// Removes the box behind `key`, without observing it, and returns it.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<SchroedingerBox<V>>
+            where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.map.remove(key)
+    }
+
+    /// Observes the value behind `key`, or returns `None` if there isn’t one.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the value can’t be observed.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V> where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.map.get(key).map(|b| &**b)
+    }
+
+    /// Like `get`, but gives a mutable reference.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+            where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.map.get_mut(key).map(|b| &mut **b)
+    }
+
+    /// Returns the box behind `key`, without observing it.
+    pub fn get_box<Q>(&self, key: &Q) -> Option<&SchroedingerBox<V>>
+            where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.map.get(key)
+    }
+
+    /// Returns the box behind `key` mutably, without observing it.
+    pub fn get_box_mut<Q>(&mut self, key: &Q) -> Option<&mut SchroedingerBox<V>>
+            where K: Borrow<Q>, Q: Eq + Hash + ?Sized {
+        self.map.get_mut(key)
+    }
+
+    /// Gets the entry for `key`, for inserting a superposition if there isn’t one yet, without
+    /// observing anything.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, SchroedingerBox<V>> {
+        self.map.entry(key)
+    }
+
+    /// Iterates over the entries in arbitrary order, observing every value.
+    ///
+    /// # Panic
+    ///
+    /// The iterator panics if a value can’t be observed.
+    pub fn iter(&self) -> impl Iterator<Item=(&K, &V)> {
+        self.map.iter().map(|(k, b)| (k, &**b))
+    }
+
+    /// Iterates over the entries in arbitrary order, without observing any of the values.
+    pub fn boxes(&self) -> hash_map::Iter<'_, K, SchroedingerBox<V>> {
+        self.map.iter()
+    }
+
+    /// Consumes the map, returning the boxes behind the keys.
+    pub fn into_inner(self) -> HashMap<K, SchroedingerBox<V>> {
+        self.map
+    }
+}
+
+impl<K, V> Default for SchroedingerHashMap<K, V> where K: Eq + Hash {
+    fn default() -> SchroedingerHashMap<K, V> {
+        SchroedingerHashMap::new()
+    }
+}
+
+impl<K, V> From<HashMap<K, SchroedingerBox<V>>> for SchroedingerHashMap<K, V> {
+    fn from(map: HashMap<K, SchroedingerBox<V>>) -> SchroedingerHashMap<K, V> {
+        SchroedingerHashMap {
+            map,
+        }
+    }
+}
+
+impl<K, V> FromIterator<(K, SchroedingerBox<V>)> for SchroedingerHashMap<K, V>
+        where K: Eq + Hash {
+    fn from_iter<I>(entries: I) -> SchroedingerHashMap<K, V>
+            where I: IntoIterator<Item=(K, SchroedingerBox<V>)> {
+        SchroedingerHashMap::from(entries.into_iter().collect::<HashMap<_, _>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::SchroedingerHashMap;
+
+    #[test]
+    fn values_collapse_separately() {
+        let mut map: SchroedingerHashMap<&str, u32> = vec![
+            ("a", SchroedingerBox::new(vec![1, 2])),
+            ("b", SchroedingerBox::from_probabilities(vec![(0, 3), (1, 4)])),
+        ].into_iter().collect();
+        *map.get_mut("b").unwrap() += 10;
+        assert_eq!(map.get("b"), Some(&14));
+        assert!(!map.get_box("a").unwrap().is_collapsed());
+        assert_eq!(map.get("c"), None);
+        map.entry("c").or_insert_with(|| SchroedingerBox::new(vec![5, 6]));
+        assert_eq!(map.boxes().filter(|&(_, b)| !b.is_collapsed()).count(), 2);
+        let mut values: Vec<u32> = map.iter().map(|(_, &v)| v).collect();
+        values.sort();
+        assert_eq!(values.len(), 3);
+        assert!(map.boxes().all(|(_, b)| b.is_collapsed()));
+        assert!(map.remove("a").is_some());
+        assert_eq!(map.into_inner().len(), 2);
+    }
+}
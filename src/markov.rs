@@ -0,0 +1,108 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Markov chains whose transitions are `SchroedingerBox`es.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use SchroedingerBox;
+
+/// A Markov chain over states of type `S`.
+///
+/// Each state maps to a weighted set of successor states. Every step prepares a fresh
+/// `SchroedingerBox` of successors for the current state and observes it, so each transition is
+/// drawn independently.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::markov::MarkovChain;
+/// let mut weather = MarkovChain::new("sunny");
+/// weather.add_transitions("sunny", vec![(9, "sunny"), (1, "rainy")]);
+/// weather.add_transitions("rainy", vec![(1, "sunny"), (1, "rainy")]);
+/// let forecast = weather.walk(7);
+/// assert_eq!(forecast.len(), 7);
+/// ```
+pub struct MarkovChain<S> {
+    transitions: HashMap<S, Vec<(u64, S)>>,
+    state: S,
+}
+
+impl<S> MarkovChain<S> where S: Clone + Eq + Hash {
+    /// Creates a new Markov chain with no transitions, starting in the given state.
+    pub fn new(initial: S) -> MarkovChain<S> {
+        MarkovChain {
+            transitions: HashMap::new(),
+            state: initial,
+        }
+    }
+
+    /// Sets the weighted successors of `from`, replacing any successors it previously had.
+    ///
+    /// The weights are interpreted in the same way as those given to
+    /// `SchroedingerBox::from_probabilities`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `successors.len() == 0`.
+    pub fn add_transitions(&mut self, from: S, successors: Vec<(u64, S)>) {
+        assert!(!successors.is_empty());
+        self.transitions.insert(from, successors);
+    }
+
+    /// Returns the state the chain is currently in.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Prepares a fresh, unobserved box of the successors of `from`, or `None` if `from` has no
+    /// successors.
+    pub fn transition(&self, from: &S) -> Option<SchroedingerBox<S>> {
+        self.transitions.get(from).map(|s| SchroedingerBox::from_probabilities(s.clone()))
+    }
+
+    /// Advances the chain by one step and returns the new state.
+    ///
+    /// A state without any successors is absorbing: the chain stays where it is.
+    pub fn step(&mut self) -> &S {
+        if let Some(next) = self.transition(&self.state) {
+            self.state = next.into_inner();
+        }
+        &self.state
+    }
+
+    /// Advances the chain by `n` steps, returning the states visited along the way (not including
+    /// the state the chain started in).
+    pub fn walk(&mut self, n: usize) -> Vec<S> {
+        (0..n).map(|_| self.step().clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MarkovChain;
+
+    #[test]
+    fn deterministic_cycle() {
+        let mut chain = MarkovChain::new(0);
+        chain.add_transitions(0, vec![(1, 1)]);
+        chain.add_transitions(1, vec![(1, 2)]);
+        chain.add_transitions(2, vec![(1, 0)]);
+        assert_eq!(chain.walk(5), vec![1, 2, 0, 1, 2]);
+        assert_eq!(*chain.state(), 2);
+    }
+
+    #[test]
+    fn absorbing_state() {
+        let mut chain = MarkovChain::new('a');
+        chain.add_transitions('a', vec![(1, 'b'), (1, 'c')]);
+        let first = *chain.step();
+        assert!(first == 'b' || first == 'c');
+        assert_eq!(chain.walk(3), vec![first; 3]);
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! N-gram models whose successors are `SchroedingerBox`es.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use {add_samples, SchroedingerBox};
+
+/// An n-gram model over items of type `T`, trained on example sequences.
+///
+/// Every window of `n - 1` consecutive items seen in training is a context, and each context
+/// records the items that followed it, weighted by how often they did. Generating a sequence
+/// prepares a fresh `SchroedingerBox` of successors for the current context at every step and
+/// observes it, much like a `MarkovChain` whose states are contexts.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::ngram::NGram;
+/// let mut names = NGram::new(3);
+/// for name in &["anna", "hannah", "johanna"] {
+///     names.train(&name.chars().collect::<Vec<_>>());
+/// }
+/// let generated = names.generate(&['a', 'n'], 10);
+/// assert!(generated.iter().all(|c| "ahjon".contains(*c)));
+/// ```
+pub struct NGram<T> {
+    n: usize,
+    contexts: HashMap<Vec<T>, Context<T>>,
+}
+
+/// The successors of one context.
+struct Context<T> {
+    states: Vec<(u64, T)>,
+    index: HashMap<T, usize>,
+}
+
+impl<T> NGram<T> where T: Clone + Eq + Hash {
+    /// Creates a new, untrained model in which each item depends on the `n - 1` items before it.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `n == 0`.
+    pub fn new(n: usize) -> NGram<T> {
+        assert!(n > 0);
+        NGram {
+            n,
+            contexts: HashMap::new(),
+        }
+    }
+
+    /// Returns `n`, the length of the windows the model was trained on (including the successor).
+    pub fn order(&self) -> usize {
+        self.n
+    }
+
+    /// Adds every window of `sequence` to the model.
+    pub fn train(&mut self, sequence: &[T]) {
+        for window in sequence.windows(self.n) {
+            let (successor, context) = window.split_last().unwrap();
+            let c = self.contexts.entry(context.to_vec()).or_insert_with(|| Context {
+                states: vec![],
+                index: HashMap::new(),
+            });
+            add_samples(&mut c.states, &mut c.index, Some(successor.clone()));
+        }
+    }
+
+    /// Prepares a fresh, unobserved box of the successors of `context`, or `None` if `context`
+    /// never occurred in training.
+    ///
+    /// Only the last `n - 1` items of `context` are used.
+    pub fn successors(&self, context: &[T]) -> Option<SchroedingerBox<T>> {
+        let context = &context[context.len().saturating_sub(self.n - 1)..];
+        self.contexts.get(context).map(|c| SchroedingerBox::from_probabilities(c.states.clone()))
+    }
+
+    /// Generates up to `len` items following `start`, returning them without `start`.
+    ///
+    /// Generation stops early when it reaches a context that never occurred in training.
+    pub fn generate(&self, start: &[T], len: usize) -> Vec<T> {
+        let mut sequence = start.to_vec();
+        for _ in 0..len {
+            match self.successors(&sequence) {
+                Some(next) => sequence.push(next.into_inner()),
+                None => break,
+            }
+        }
+        sequence.split_off(start.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NGram;
+
+    #[test]
+    fn deterministic_text() {
+        let mut model = NGram::new(2);
+        model.train(&[1, 2, 3, 1, 2, 3]);
+        assert_eq!(model.order(), 2);
+        assert_eq!(model.generate(&[3], 5), vec![1, 2, 3, 1, 2]);
+        assert_eq!(model.generate(&[4], 5), vec![]);
+        assert!(model.successors(&[1, 2]).is_some());
+    }
+
+    #[test]
+    fn stops_at_unseen_context() {
+        let mut model = NGram::new(3);
+        model.train(&"abc".chars().collect::<Vec<_>>());
+        model.train(&"abd".chars().collect::<Vec<_>>());
+        let generated = model.generate(&['a', 'b'], 5);
+        assert_eq!(generated.len(), 1);
+        assert!(generated[0] == 'c' || generated[0] == 'd');
+
+        let mut unigram = NGram::new(1);
+        unigram.train(&['x', 'x', 'x']);
+        assert_eq!(unigram.generate(&[], 3), vec!['x'; 3]);
+    }
+}
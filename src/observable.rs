@@ -0,0 +1,132 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use {total_weight, SchroedingerBox};
+
+/// Something that holds a value of type `T` which may not have been decided yet.
+///
+/// Code written against this trait instead of `SchroedingerBox` directly can be tested with a
+/// `FixedOutcome`, without any randomness involved.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{FixedOutcome, Observable, SchroedingerBox};
+/// fn greet<O: Observable<bool>>(cat_is_alive: &O) -> &'static str {
+///     if *cat_is_alive.observe() { "hello, cat" } else { "oh no" }
+/// }
+///
+/// assert_eq!(greet(&FixedOutcome::new(true)), "hello, cat");
+/// let real = SchroedingerBox::new(vec![true, false]);
+/// assert!(["hello, cat", "oh no"].contains(&greet(&real)));
+/// ```
+pub trait Observable<T> {
+    /// Obtains a reference to the value, deciding it if needed.
+    fn observe(&self) -> &T;
+
+    /// Obtains a reference to the value if it has already been decided, without deciding it.
+    fn peek(&self) -> Option<&T>;
+
+    /// Returns the probability that observing gives a value equal to `value`, without observing
+    /// anything.
+    fn probability_of(&self, value: &T) -> f64 where T: PartialEq;
+}
+
+impl<Cat> Observable<Cat> for SchroedingerBox<Cat> {
+    fn observe(&self) -> &Cat {
+        self
+    }
+
+    /// Obtains a reference to the value if the box has already been observed (or only has one
+    /// state and can’t panic), without observing it.
+    ///
+    /// Like `probability_of`, this freezes the weights.
+    fn peek(&self) -> Option<&Cat> {
+        match self.odds() {
+            [(_, c)] if self.is_collapsed() => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns the probability that observing gives a value equal to `value`, without observing
+    /// anything.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    fn probability_of(&self, value: &Cat) -> f64 where Cat: PartialEq {
+        let states = self.odds();
+        if states.len() == 1 {
+            return if states[0].1 == *value { 1.0 } else { 0.0 }
+        }
+        let total = total_weight(states).unwrap_or_else(|e| panic!("{}", e));
+        let matching = states.iter().filter(|&(_, c)| c == value).map(|&(w, _)| w).sum::<u64>();
+        matching as f64 / total as f64
+    }
+}
+
+/// A value that has been decided in advance, for standing in for a `SchroedingerBox` in tests.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct FixedOutcome<T> {
+    value: T,
+}
+
+impl<T> FixedOutcome<T> {
+    /// Creates an outcome that is always `value`.
+    pub fn new(value: T) -> FixedOutcome<T> {
+        FixedOutcome {
+            value,
+        }
+    }
+
+    /// Moves the value out.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Observable<T> for FixedOutcome<T> {
+    fn observe(&self) -> &T {
+        &self.value
+    }
+
+    fn peek(&self) -> Option<&T> {
+        Some(&self.value)
+    }
+
+    fn probability_of(&self, value: &T) -> f64 where T: PartialEq {
+        if self.value == *value { 1.0 } else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::{FixedOutcome, Observable};
+
+    #[test]
+    fn boxes_are_observable() {
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (2, 'b'), (1, 'a')]);
+        assert_eq!(b.peek(), None);
+        assert_eq!(b.probability_of(&'a'), 0.5);
+        assert_eq!(b.probability_of(&'c'), 0.0);
+        let val = *b.observe();
+        assert_eq!(b.peek(), Some(&val));
+        assert_eq!(b.probability_of(&val), 1.0);
+        assert_eq!(SchroedingerBox::maybe_panic(1, 'a', 0, "never").peek(), None);
+    }
+
+    #[test]
+    fn fixed_outcomes() {
+        let fixed = FixedOutcome::new(3);
+        assert_eq!(fixed.observe(), &3);
+        assert_eq!(fixed.peek(), Some(&3));
+        assert_eq!(fixed.probability_of(&4), 0.0);
+        assert_eq!(fixed.into_inner(), 3);
+    }
+}
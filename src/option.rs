@@ -0,0 +1,132 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::ops::Deref;
+use SchroedingerBox;
+
+/// A value that may or may not be there, undetermined until observed.
+///
+/// This is a `SchroedingerBox<Option<T>>` with some conveniences for the common case of
+/// something that exists with some probability. Dereferencing it observes it, giving an
+/// `Option<T>`.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerOption;
+/// let drop = SchroedingerOption::with_probability(0.3, "rare gem");
+/// assert!((drop.is_some_probability() - 0.3).abs() < 1e-15);
+/// let size = drop.map(|gem| gem.len());
+/// if let Some(n) = *size {
+///     assert_eq!(n, 8);
+/// }
+/// ```
+pub struct SchroedingerOption<T> {
+    inner: SchroedingerBox<Option<T>>,
+}
+
+impl<T> SchroedingerOption<T> {
+    /// Wraps a superposition of options.
+    pub fn new(b: SchroedingerBox<Option<T>>) -> SchroedingerOption<T> {
+        SchroedingerOption {
+            inner: b,
+        }
+    }
+
+    /// Creates an option that holds `value` with probability `p`, and nothing otherwise.
+    ///
+    /// # Panic
+    ///
+    /// Panics unless `0.0 <= p && p <= 1.0`.
+    pub fn with_probability(p: f64, value: T) -> SchroedingerOption<T> {
+        assert!((0.0..=1.0).contains(&p), "probability must be between 0 and 1");
+        SchroedingerOption::new(SchroedingerBox::from_f64_weights(vec![(p, Some(value)),
+                                                                       (1.0 - p, None)]))
+    }
+
+    /// Returns the probability that observing the option gives a value, without observing it.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the option can’t be observed.
+    pub fn is_some_probability(&self) -> f64 {
+        self.inner.probabilities().into_iter().filter(|&(_, o)| o.is_some()).map(|(p, _)| p).sum()
+    }
+
+    /// Returns whether the option has been decided, without observing it.
+    pub fn is_collapsed(&self) -> bool {
+        self.inner.is_collapsed()
+    }
+
+    /// Applies `f` to the value in every state that has one, without observing the option.
+    pub fn map<U, F>(self, mut f: F) -> SchroedingerOption<U> where F: FnMut(T) -> U {
+        SchroedingerOption::new(self.inner.map(|o| o.map(&mut f)))
+    }
+
+    /// Keeps the value only in the states where `f` returns `true` for it, without observing
+    /// the option.
+    pub fn filter<F>(self, mut f: F) -> SchroedingerOption<T> where F: FnMut(&T) -> bool {
+        SchroedingerOption::new(self.inner.map(|o| o.filter(&mut f)))
+    }
+
+    /// Moves the value out, observing the option if needed, or returns `default` if there isn’t
+    /// one.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the option can’t be observed.
+    pub fn unwrap_or(self, default: T) -> T {
+        self.inner.into_inner().unwrap_or(default)
+    }
+
+    /// Like `unwrap_or`, but computes the default with `f`.
+    pub fn unwrap_or_else<F>(self, f: F) -> T where F: FnOnce() -> T {
+        self.inner.into_inner().unwrap_or_else(f)
+    }
+
+    /// Consumes the option, returning the box inside.
+    pub fn into_inner(self) -> SchroedingerBox<Option<T>> {
+        self.inner
+    }
+}
+
+impl<T> From<SchroedingerBox<Option<T>>> for SchroedingerOption<T> {
+    fn from(b: SchroedingerBox<Option<T>>) -> SchroedingerOption<T> {
+        SchroedingerOption::new(b)
+    }
+}
+
+impl<T> Deref for SchroedingerOption<T> {
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Option<T> {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::SchroedingerOption;
+
+    #[test]
+    fn maybe_there() {
+        let certain = SchroedingerOption::with_probability(1.0, 5);
+        assert_eq!(certain.is_some_probability(), 1.0);
+        assert_eq!(certain.map(|x| x * 2).unwrap_or(0), 10);
+        let never = SchroedingerOption::with_probability(0.0, 5);
+        assert_eq!(*never, None);
+        let b = SchroedingerBox::from_probabilities(vec![(1, Some(1)), (1, Some(2)), (2, None)]);
+        let odd = SchroedingerOption::from(b).filter(|&x| x % 2 == 1);
+        assert_eq!(odd.is_some_probability(), 0.25);
+        assert!(!odd.is_collapsed());
+        let observed = *odd;
+        assert!(odd.is_collapsed());
+        assert_eq!(odd.unwrap_or_else(|| 0), observed.unwrap_or(0));
+    }
+}
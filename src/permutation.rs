@@ -0,0 +1,128 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::RefCell;
+use rand::Rng;
+use rng::local_rng;
+
+/// Some permutation of a set of items, undetermined until observed.
+///
+/// Each position collapses separately, the first time it is looked at: observing one position
+/// only rules out that item for the others, which stay superposed over everything that’s left.
+/// This is exactly as fair as shuffling everything up front, but a lot more fun.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerPermutation;
+/// let deck = SchroedingerPermutation::new((1..53).collect::<Vec<u8>>());
+/// let top = *deck.get(0).unwrap();
+/// // The rest of the deck is still undetermined...
+/// assert!(!deck.is_observed(1));
+/// // ...but it certainly doesn’t contain the card we’ve just drawn.
+/// assert!(deck.iter().skip(1).all(|&card| card != top));
+/// ```
+pub struct SchroedingerPermutation<T> {
+    items: Vec<T>,
+    state: RefCell<State>,
+}
+
+struct State {
+    // The item placed in each position, if it has been observed yet.
+    slots: Vec<Option<usize>>,
+    // The items that haven’t been placed yet.
+    pool: Vec<usize>,
+}
+
+impl<T> SchroedingerPermutation<T> {
+    /// Creates a new superposition of all permutations of `items`, each equally likely.
+    pub fn new(items: Vec<T>) -> SchroedingerPermutation<T> {
+        let n = items.len();
+        SchroedingerPermutation {
+            items,
+            state: RefCell::new(State {
+                slots: vec![None; n],
+                pool: (0..n).collect(),
+            }),
+        }
+    }
+
+    /// Returns the number of items being permuted.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns `true` if there are no items to permute.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns whether the item in position `i` has already been determined.
+    pub fn is_observed(&self, i: usize) -> bool {
+        self.state.borrow().slots.get(i).is_some_and(|s| s.is_some())
+    }
+
+    /// Observes the item in position `i`, collapsing that position (and only that position) if
+    /// needed. Returns `None` if `i` is out of bounds.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        let mut state = self.state.borrow_mut();
+        let State { ref mut slots, ref mut pool } = *state;
+        let slot = slots.get_mut(i)?;
+        let idx = match *slot {
+            Some(idx) => idx,
+            None => {
+                // This is one step of a Fisher–Yates shuffle, performed only when it’s needed.
+                let idx = pool.swap_remove(local_rng().gen_range(0..pool.len()));
+                *slot = Some(idx);
+                idx
+            },
+        };
+        Some(&self.items[idx])
+    }
+
+    /// Returns an iterator observing each position in order.
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
+        (0..self.len()).map(move |i| self.get(i).unwrap())
+    }
+
+    /// Collapses every position, returning the items in their permuted order.
+    pub fn into_vec(self) -> Vec<T> {
+        for i in 0..self.len() {
+            self.get(i);
+        }
+        let slots = self.state.into_inner().slots;
+        let mut items: Vec<_> = self.items.into_iter().map(Some).collect();
+        slots.into_iter().map(|s| items[s.unwrap()].take().unwrap()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SchroedingerPermutation;
+
+    #[test]
+    fn is_a_permutation() {
+        let perm = SchroedingerPermutation::new((0..20).collect());
+        assert_eq!(perm.get(20), None);
+        let last = *perm.get(19).unwrap();
+        assert!(perm.is_observed(19));
+        assert!(!perm.is_observed(0));
+        let mut all = perm.into_vec();
+        assert_eq!(all[19], last);
+        all.sort();
+        assert_eq!(all, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn observations_are_stable() {
+        let perm = SchroedingerPermutation::new(vec!['a', 'b', 'c']);
+        let first: Vec<_> = perm.iter().cloned().collect();
+        let second: Vec<_> = perm.iter().cloned().collect();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,527 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Qubits with complex amplitudes, enabled by the `num-complex` feature.
+//!
+//! A `SchroedingerBox` only knows the odds of each state. A qubit also has a phase for each of
+//! its two states, so gates can make the states interfere with each other before it is
+//! measured. A `QuantumRegister` holds several qubits at once, with an amplitude for every
+//! combination of their values, so it can also hold states that no set of separate qubits can.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use schroedinger_box::quantum::Qubit;
+//! let mut q = Qubit::zero();
+//! q.hadamard();
+//! // Now it is equally likely to be measured as 0 or 1...
+//! assert!((q.probability_of_one() - 0.5).abs() < 1e-12);
+//! q.hadamard();
+//! // ...but a second Hadamard gate makes the two paths to 1 cancel out.
+//! assert!(!q.measure());
+//! ```
+
+use std::array;
+use std::f64::consts::FRAC_1_SQRT_2;
+use num_complex::Complex64;
+use SchroedingerBox;
+
+/// A matrix acting on the amplitudes of a single qubit.
+type Gate = [[Complex64; 2]; 2];
+
+const ZERO: Complex64 = Complex64::new(0.0, 0.0);
+const ONE: Complex64 = Complex64::new(1.0, 0.0);
+const I: Complex64 = Complex64::new(0.0, 1.0);
+const HALF: Complex64 = Complex64::new(FRAC_1_SQRT_2, 0.0);
+
+const HADAMARD: Gate = [[HALF, HALF], [HALF, Complex64::new(-FRAC_1_SQRT_2, 0.0)]];
+const PAULI_X: Gate = [[ZERO, ONE], [ONE, ZERO]];
+const PAULI_Y: Gate = [[ZERO, Complex64::new(0.0, -1.0)], [I, ZERO]];
+const PAULI_Z: Gate = [[ONE, ZERO], [ZERO, Complex64::new(-1.0, 0.0)]];
+
+/// The phase shift gate, which turns the phase of 1 by `theta` radians.
+fn phase(theta: f64) -> Gate {
+    [[ONE, ZERO], [ZERO, Complex64::from_polar(1.0, theta)]]
+}
+
+/// The length of a vector of amplitudes, scaled by the largest of them first so that summing
+/// their squares can’t overflow. This is zero if they are all zero, and not finite if any of them
+/// isn’t.
+fn norm(amplitudes: &[Complex64]) -> f64 {
+    let largest = amplitudes.iter().map(|a| a.norm()).fold(0.0, f64::max);
+    if largest == 0.0 || !largest.is_finite() {
+        return largest
+    }
+    largest * amplitudes.iter().map(|a| (a / largest).norm_sqr()).sum::<f64>().sqrt()
+}
+
+/// A single qubit: a superposition of 0 and 1, each with a complex amplitude.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Qubit {
+    amplitudes: [Complex64; 2],
+}
+
+impl Qubit {
+    /// Creates a qubit that is definitely 0.
+    pub fn zero() -> Qubit {
+        Qubit {
+            amplitudes: [ONE, ZERO],
+        }
+    }
+
+    /// Creates a qubit that is definitely 1.
+    pub fn one() -> Qubit {
+        Qubit {
+            amplitudes: [ZERO, ONE],
+        }
+    }
+
+    /// Creates a qubit with the given amplitudes for 0 and 1, scaled so that the probabilities
+    /// add up to one.
+    ///
+    /// # Panic
+    ///
+    /// Panics if both amplitudes are zero, or either isn’t finite.
+    pub fn new(zero: Complex64, one: Complex64) -> Qubit {
+        let norm = norm(&[zero, one]);
+        assert!(norm > 0.0 && norm.is_finite(), "a qubit needs a finite, non-zero amplitude");
+        Qubit {
+            amplitudes: [zero / norm, one / norm],
+        }
+    }
+
+    /// Returns the amplitudes of 0 and 1.
+    pub fn amplitudes(&self) -> [Complex64; 2] {
+        self.amplitudes
+    }
+
+    /// Returns the probability that measuring the qubit gives 1, without measuring it.
+    pub fn probability_of_one(&self) -> f64 {
+        self.amplitudes[1].norm_sqr()
+    }
+
+    /// Applies the Hadamard gate, which turns 0 and 1 into even superpositions of them.
+    pub fn hadamard(&mut self) {
+        self.apply(&HADAMARD);
+    }
+
+    /// Applies the Pauli X gate, which swaps 0 and 1.
+    pub fn pauli_x(&mut self) {
+        self.apply(&PAULI_X);
+    }
+
+    /// Applies the Pauli Y gate, which swaps 0 and 1 and turns their phases.
+    pub fn pauli_y(&mut self) {
+        self.apply(&PAULI_Y);
+    }
+
+    /// Applies the Pauli Z gate, which flips the sign of 1.
+    pub fn pauli_z(&mut self) {
+        self.apply(&PAULI_Z);
+    }
+
+    /// Applies the phase shift gate, which turns the phase of 1 by `theta` radians.
+    pub fn phase(&mut self, theta: f64) {
+        self.apply(&phase(theta));
+    }
+
+    /// Measures the qubit, returning `true` for 1, and leaves it in the state it was measured
+    /// in.
+    pub fn measure(&mut self) -> bool {
+        let [zero, one] = self.amplitudes;
+        let outcome = SchroedingerBox::from_f64_weights(vec![(zero.norm_sqr(), false),
+                                                             (one.norm_sqr(), true)]);
+        let one = outcome.into_inner();
+        *self = if one { Qubit::one() } else { Qubit::zero() };
+        one
+    }
+
+    fn apply(&mut self, gate: &Gate) {
+        let [a, b] = self.amplitudes;
+        self.amplitudes = [gate[0][0] * a + gate[0][1] * b, gate[1][0] * a + gate[1][1] * b];
+    }
+}
+
+/// A register of `N` qubits, which are measured together.
+///
+/// The register has an amplitude for each of the `2^N` combinations of values of its qubits,
+/// indexed so that bit `i` of the index is the value of qubit `i`. Measuring one qubit only
+/// collapses the combinations that disagree with what was measured, and leaves the rest of the
+/// qubits in superposition.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::quantum::QuantumRegister;
+/// let mut reg = QuantumRegister::<3>::new();
+/// reg.pauli_x(1);
+/// reg.hadamard(2);
+/// let [a, b, _] = reg.measure();
+/// assert!(!a && b);
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct QuantumRegister<const N: usize> {
+    amplitudes: Vec<Complex64>,
+}
+
+impl<const N: usize> QuantumRegister<N> {
+    /// Creates a register whose qubits are all definitely 0.
+    pub fn new() -> QuantumRegister<N> {
+        let mut amplitudes = vec![ZERO; 1 << N];
+        amplitudes[0] = ONE;
+        QuantumRegister {
+            amplitudes,
+        }
+    }
+
+    /// Creates a register from separate qubits, qubit `i` of the register starting as
+    /// `qubits[i]`.
+    pub fn from_qubits(qubits: [Qubit; N]) -> QuantumRegister<N> {
+        let amplitudes = (0..1usize << N).map(|index| {
+            qubits.iter().enumerate().map(|(i, q)| q.amplitudes[index >> i & 1]).product()
+        }).collect();
+        QuantumRegister {
+            amplitudes,
+        }
+    }
+
+    /// Creates a register with the given amplitudes, scaled so that the probabilities add up to
+    /// one.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `amplitudes.len() != 2^N`, or if the amplitudes are all zero or any isn’t
+    /// finite.
+    pub fn from_amplitudes(amplitudes: Vec<Complex64>) -> QuantumRegister<N> {
+        assert_eq!(amplitudes.len(), 1 << N, "a register of {} qubits needs {} amplitudes",
+                   N, 1usize << N);
+        let norm = norm(&amplitudes);
+        assert!(norm > 0.0 && norm.is_finite(), "a register needs a finite, non-zero amplitude");
+        QuantumRegister {
+            amplitudes: amplitudes.into_iter().map(|a| a / norm).collect(),
+        }
+    }
+
+    /// Returns the amplitude of each combination of values of the qubits.
+    pub fn amplitudes(&self) -> &[Complex64] {
+        &self.amplitudes
+    }
+
+    /// Returns the probability that measuring qubit `i` gives 1, without measuring it.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `i >= N`.
+    pub fn probability_of_one(&self, i: usize) -> f64 {
+        assert!(i < N, "qubit {} is out of range for a register of {}", i, N);
+        self.amplitudes.iter().enumerate()
+            .filter(|&(index, _)| index >> i & 1 == 1)
+            .map(|(_, a)| a.norm_sqr())
+            .sum()
+    }
+
+    /// Applies the Hadamard gate to qubit `i`.
+    pub fn hadamard(&mut self, i: usize) {
+        self.apply(i, &HADAMARD);
+    }
+
+    /// Applies the Pauli X gate to qubit `i`.
+    pub fn pauli_x(&mut self, i: usize) {
+        self.apply(i, &PAULI_X);
+    }
+
+    /// Applies the Pauli Y gate to qubit `i`.
+    pub fn pauli_y(&mut self, i: usize) {
+        self.apply(i, &PAULI_Y);
+    }
+
+    /// Applies the Pauli Z gate to qubit `i`.
+    pub fn pauli_z(&mut self, i: usize) {
+        self.apply(i, &PAULI_Z);
+    }
+
+    /// Applies the phase shift gate to qubit `i`, turning the phase of 1 by `theta` radians.
+    pub fn phase(&mut self, i: usize, theta: f64) {
+        self.apply(i, &phase(theta));
+    }
+
+    /// Applies a unitary matrix to the amplitudes, `m[j][i]` being how much of the amplitude of
+    /// combination `i` goes to combination `j`.
+    ///
+    /// `m` must be unitary to within rounding: every entry of `m·m†` must be within `1e-9` of the
+    /// identity matrix’s. Afterwards, the amplitudes are scaled so that the probabilities add up
+    /// to one again, so that rounding errors don’t build up over many steps.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `m` isn’t a `2^N × 2^N` matrix, or isn’t unitary.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate num_complex;
+    /// # extern crate schroedinger_box;
+    /// # use num_complex::Complex64;
+    /// # use schroedinger_box::quantum::QuantumRegister;
+    /// # fn main() {
+    /// let (zero, one) = (Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0));
+    /// let mut reg = QuantumRegister::<1>::new();
+    /// reg.apply_unitary(&[vec![zero, one], vec![one, zero]]);
+    /// assert_eq!(reg.measure(), [true]);
+    /// # }
+    /// ```
+    pub fn apply_unitary(&mut self, m: &[Vec<Complex64>]) {
+        let n = self.amplitudes.len();
+        assert!(m.len() == n && m.iter().all(|row| row.len() == n),
+                "a register of {} qubits needs a {} × {} matrix", N, n, n);
+        for (i, a) in m.iter().enumerate() {
+            for (j, b) in m.iter().enumerate() {
+                let dot: Complex64 = a.iter().zip(b).map(|(x, y)| x * y.conj()).sum();
+                let identity = if i == j { ONE } else { ZERO };
+                assert!((dot - identity).norm() <= 1e-9, "the matrix isn’t unitary");
+            }
+        }
+        let amplitudes = m.iter().map(|row| {
+            row.iter().zip(&self.amplitudes).map(|(u, a)| u * a).sum()
+        }).collect();
+        *self = QuantumRegister::from_amplitudes(amplitudes);
+    }
+
+    /// Measures qubit `i`, returning `true` for 1.
+    ///
+    /// Only the combinations that agree with the outcome are left, so any qubits entangled with
+    /// this one are partly (or wholly) collapsed too.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `i >= N`.
+    pub fn measure_qubit(&mut self, i: usize) -> bool {
+        let p = self.probability_of_one(i);
+        let zero = (1.0 - p).max(0.0);
+        let one = SchroedingerBox::from_f64_weights(vec![(zero, false), (p, true)]).into_inner();
+        let norm = if one { p } else { zero }.sqrt();
+        for (index, a) in self.amplitudes.iter_mut().enumerate() {
+            *a = if (index >> i & 1 == 1) == one { *a / norm } else { ZERO };
+        }
+        one
+    }
+
+    /// Measures every qubit, returning the value of each, and leaves the register in the state
+    /// it was measured in.
+    pub fn measure(&mut self) -> [bool; N] {
+        let weights = self.amplitudes.iter().enumerate().map(|(index, a)| (a.norm_sqr(), index));
+        let index = SchroedingerBox::from_f64_weights(weights.collect()).into_inner();
+        for a in &mut self.amplitudes {
+            *a = ZERO;
+        }
+        self.amplitudes[index] = ONE;
+        array::from_fn(|i| index >> i & 1 == 1)
+    }
+
+    /// Applies the controlled NOT gate, which flips qubit `target` if qubit `control` is 1.
+    ///
+    /// On a `control` in superposition, this entangles the two qubits.
+    ///
+    /// # Panic
+    ///
+    /// Panics if either qubit is out of range, or they are the same qubit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::quantum::QuantumRegister;
+    /// let mut reg = QuantumRegister::<2>::new();
+    /// reg.hadamard(0);
+    /// reg.cnot(0, 1);
+    /// // A Bell pair: the qubits could be either value, but are always equal.
+    /// let [a, b] = reg.measure();
+    /// assert_eq!(a, b);
+    /// ```
+    pub fn cnot(&mut self, control: usize, target: usize) {
+        self.apply_controlled(&[control], target, &PAULI_X);
+    }
+
+    /// Applies the controlled Z gate, which flips the sign of the combinations where qubits `a`
+    /// and `b` are both 1.
+    ///
+    /// # Panic
+    ///
+    /// Panics if either qubit is out of range, or they are the same qubit.
+    pub fn cz(&mut self, a: usize, b: usize) {
+        self.apply_controlled(&[a], b, &PAULI_Z);
+    }
+
+    /// Applies the Toffoli gate, which flips qubit `target` if qubits `c1` and `c2` are both 1.
+    ///
+    /// # Panic
+    ///
+    /// Panics if any qubit is out of range, or any two are the same qubit.
+    pub fn toffoli(&mut self, c1: usize, c2: usize, target: usize) {
+        self.apply_controlled(&[c1, c2], target, &PAULI_X);
+    }
+
+    /// Applies `gate` to qubit `i`.
+    fn apply(&mut self, i: usize, gate: &Gate) {
+        self.apply_controlled(&[], i, gate);
+    }
+
+    /// Applies `gate` to qubit `target` in the combinations where every qubit in `controls` is 1,
+    /// pairing up each such combination where `target` is 0 with the one where it is 1.
+    fn apply_controlled(&mut self, controls: &[usize], target: usize, gate: &Gate) {
+        let mut mask = 0;
+        for &i in controls.iter().chain(Some(&target)) {
+            assert!(i < N, "qubit {} is out of range for a register of {}", i, N);
+            assert!(mask & 1 << i == 0, "qubit {} is used twice in one gate", i);
+            mask |= 1 << i;
+        }
+        let controls = mask & !(1 << target);
+        for index in 0..self.amplitudes.len() {
+            if index & mask != controls {
+                continue
+            }
+            let (a, b) = (self.amplitudes[index], self.amplitudes[index | 1 << target]);
+            self.amplitudes[index] = gate[0][0] * a + gate[0][1] * b;
+            self.amplitudes[index | 1 << target] = gate[1][0] * a + gate[1][1] * b;
+        }
+    }
+}
+
+impl<const N: usize> Default for QuantumRegister<N> {
+    fn default() -> QuantumRegister<N> {
+        QuantumRegister::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use num_complex::Complex64;
+    use super::{QuantumRegister, Qubit};
+
+    fn close(a: Qubit, b: [Complex64; 2]) -> bool {
+        a.amplitudes().iter().zip(&b).all(|(x, y)| (x - y).norm() < 1e-12)
+    }
+
+    #[test]
+    fn gates() {
+        let (zero, one, i) = (Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0), Complex64::i());
+        let mut q = Qubit::zero();
+        q.pauli_x();
+        assert_eq!(q, Qubit::one());
+        q.pauli_z();
+        assert!(close(q, [zero, -one]));
+        q.pauli_y();
+        assert!(close(q, [i, zero]));
+        let mut q = Qubit::one();
+        q.phase(PI / 2.0);
+        assert!(close(q, [zero, i]));
+        q.hadamard();
+        q.hadamard();
+        assert!(close(q, [zero, i]));
+        assert!(q.measure());
+        assert_eq!(q, Qubit::one());
+    }
+
+    #[test]
+    fn normalises() {
+        let q = Qubit::new(Complex64::new(3.0, 0.0), Complex64::new(0.0, 4.0));
+        assert!((q.probability_of_one() - 0.64).abs() < 1e-12);
+        let mut plus = Qubit::zero();
+        plus.hadamard();
+        let ones = (0..200).filter(|_| { let mut q = plus; q.measure() }).count();
+        assert!(0 < ones && ones < 200);
+    }
+
+    #[test]
+    fn registers() {
+        let (zero, half) = (Complex64::new(0.0, 0.0), Complex64::new(0.5, 0.0));
+        let mut plus = Qubit::zero();
+        plus.hadamard();
+        let mut reg = QuantumRegister::from_qubits([plus, plus]);
+        assert!(reg.amplitudes().iter().all(|a| (a - half).norm() < 1e-12));
+        reg.hadamard(1);
+        assert!((reg.probability_of_one(0) - 0.5).abs() < 1e-12);
+        assert!(reg.probability_of_one(1).abs() < 1e-12);
+        let a = reg.measure_qubit(0);
+        assert!((reg.probability_of_one(0) - if a { 1.0 } else { 0.0 }).abs() < 1e-12);
+        assert_eq!(reg.measure(), [a, false]);
+        let correlated = QuantumRegister::<2>::from_amplitudes(vec![half, zero, zero, half]);
+        for _ in 0..20 {
+            let mut reg = correlated.clone();
+            let a = reg.measure_qubit(1);
+            assert_eq!(reg.measure(), [a, a]);
+        }
+    }
+
+    #[test]
+    fn controlled_gates() {
+        let mut reg = QuantumRegister::<3>::new();
+        reg.cnot(0, 1);
+        reg.toffoli(0, 1, 2);
+        assert_eq!(reg.measure(), [false, false, false]);
+        reg.pauli_x(0);
+        reg.cnot(0, 1);
+        reg.toffoli(1, 0, 2);
+        assert_eq!(reg.measure(), [true, true, true]);
+        reg.cz(2, 0);
+        assert!((reg.amplitudes()[7] + Complex64::new(1.0, 0.0)).norm() < 1e-12);
+        for _ in 0..20 {
+            let mut bell = QuantumRegister::<2>::new();
+            bell.hadamard(0);
+            bell.cnot(0, 1);
+            let b = bell.measure_qubit(1);
+            assert_eq!(bell.measure(), [b, b]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn controlled_gate_on_itself() {
+        QuantumRegister::<2>::new().cnot(1, 1);
+    }
+
+    #[test]
+    fn unitaries() {
+        let (zero, one, i) = (Complex64::new(0.0, 0.0), Complex64::new(1.0, 0.0), Complex64::i());
+        let mut reg = QuantumRegister::<2>::new();
+        reg.hadamard(0);
+        let mut expected = reg.clone();
+        expected.cnot(0, 1);
+        expected.phase(1, PI / 2.0);
+        let mut m = vec![vec![zero; 4]; 4];
+        m[0][0] = one;
+        m[3][1] = i;
+        m[2][2] = one;
+        m[1][3] = one;
+        reg.apply_unitary(&m);
+        let (a, b) = (reg.amplitudes(), expected.amplitudes());
+        assert!(a.iter().zip(b).all(|(a, b)| (a - b).norm() < 1e-12));
+    }
+
+    #[test]
+    #[should_panic]
+    fn unitary_of_the_wrong_size() {
+        QuantumRegister::<2>::new().apply_unitary(&[vec![Complex64::new(1.0, 0.0)]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the matrix isn’t unitary")]
+    fn non_unitary() {
+        let (zero, two) = (Complex64::new(0.0, 0.0), Complex64::new(2.0, 0.0));
+        QuantumRegister::<1>::new().apply_unitary(&[vec![two, zero], vec![zero, two]]);
+    }
+
+    #[test]
+    fn huge_amplitudes() {
+        let huge = Complex64::new(1e200, 0.0);
+        let q = Qubit::new(huge, huge);
+        assert!((q.probability_of_one() - 0.5).abs() < 1e-12);
+        let reg = QuantumRegister::<1>::from_amplitudes(vec![huge, -huge]);
+        assert!((reg.probability_of_one(0) - 0.5).abs() < 1e-12);
+    }
+}
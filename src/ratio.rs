@@ -0,0 +1,104 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Exact rational weights, enabled by the `num-rational` feature.
+
+use num_rational::Ratio;
+use {gcd, total_weight, SchroedingerBox, SuperpositionError};
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Creates a new `SchroedingerBox` from a set of states, each with a rational probability.
+    ///
+    /// As with `from_probabilities`, the probabilities are relative to each other and needn’t add
+    /// up to one. They are converted to integer weights exactly, by bringing them all over a
+    /// common denominator.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `states.len() == 0`, or if the common denominator doesn’t fit in a `u64`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate num_rational;
+    /// # extern crate schroedinger_box;
+    /// # use num_rational::Ratio;
+    /// # use schroedinger_box::SchroedingerBox;
+    /// # fn main() {
+    /// let third = Ratio::new(1, 3);
+    /// let b = SchroedingerBox::from_ratios(vec![(third, 'a'), (third, 'b'), (third, 'c')]);
+    /// assert_eq!(b.ratios(), Ok(vec![third; 3]));
+    /// # }
+    /// ```
+    pub fn from_ratios(states: Vec<(Ratio<u64>, Cat)>) -> SchroedingerBox<Cat> {
+        SchroedingerBox::try_from_ratios(states).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `from_ratios`, but returns an error instead of panicking, under the same conditions as
+    /// `try_from_probabilities` or if the common denominator doesn’t fit in a `u64`.
+    pub fn try_from_ratios(states: Vec<(Ratio<u64>, Cat)>)
+            -> Result<SchroedingerBox<Cat>, SuperpositionError> {
+        let mut denom = 1u64;
+        for (r, _) in &states {
+            denom = (denom / gcd(denom, *r.denom())).checked_mul(*r.denom())
+                .ok_or(SuperpositionError::WeightOverflow)?;
+        }
+        let mut weighted = Vec::with_capacity(states.len());
+        for (r, c) in states {
+            let w = r.numer().checked_mul(denom / r.denom())
+                .ok_or(SuperpositionError::WeightOverflow)?;
+            weighted.push((w, c));
+        }
+        let common = weighted.iter().fold(0, |g, &(w, _)| gcd(g, w)).max(1);
+        for s in &mut weighted {
+            s.0 /= common;
+        }
+        SchroedingerBox::try_from_probabilities(weighted)
+    }
+
+    /// Returns the exact probability of each state that is still possible, without observing the
+    /// box.
+    ///
+    /// The probabilities are in the same order as the states were given, and add up to one. Once
+    /// the box has been observed, the only probability is that of the observed state.
+    pub fn ratios(&self) -> Result<Vec<Ratio<u64>>, SuperpositionError> {
+        let states = self.odds();
+        if states.len() == 1 {
+            return Ok(vec![Ratio::from_integer(1)])
+        }
+        let total = total_weight(states)?;
+        Ok(states.iter().map(|&(w, _)| Ratio::new(w, total)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Ratio;
+    use {SchroedingerBox, SuperpositionError};
+
+    #[test]
+    fn exact_weights() {
+        let b = SchroedingerBox::from_ratios(
+            vec![(Ratio::new(1, 2), 1), (Ratio::new(1, 3), 2), (Ratio::new(1, 6), 3)]);
+        assert_eq!(b.states(), &[(3, 1), (2, 2), (1, 3)][..]);
+        assert_eq!(b.ratios().unwrap(), vec![Ratio::new(1, 2), Ratio::new(1, 3), Ratio::new(1, 6)]);
+        let _ = *b;
+        assert_eq!(b.ratios().unwrap(), vec![Ratio::from_integer(1)]);
+    }
+
+    #[test]
+    fn invalid_ratios() {
+        let huge = Ratio::new(1, u64::MAX);
+        assert_eq!(SchroedingerBox::try_from_ratios(vec![(huge, 1), (Ratio::new(1, 2), 2)]).err(),
+                   Some(SuperpositionError::WeightOverflow));
+        assert_eq!(SchroedingerBox::<u8>::try_from_ratios(vec![]).err(),
+                   Some(SuperpositionError::Empty));
+        assert_eq!(SchroedingerBox::try_from_ratios(vec![(Ratio::from_integer(0), 1); 2]).err(),
+                   Some(SuperpositionError::ZeroTotalWeight));
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::ops::Deref;
+use SchroedingerBox;
+
+/// An operation that may or may not have failed, undetermined until observed.
+///
+/// This is a `SchroedingerBox<Result<T, E>>` with some conveniences for injecting failures with
+/// some probability. Dereferencing it observes it, giving a `Result<T, E>`, and `collapse` moves
+/// the result out, ready for `?`.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerResult;
+/// fn fetch() -> SchroedingerResult<&'static str, &'static str> {
+///     SchroedingerResult::failing_with_probability(0.1, "200 OK", "timed out")
+/// }
+///
+/// fn load() -> Result<usize, &'static str> {
+///     let response = fetch().map(|body| body.len()).collapse()?;
+///     Ok(response)
+/// }
+///
+/// let loaded = load();
+/// assert!(loaded == Ok(6) || loaded == Err("timed out"));
+/// ```
+pub struct SchroedingerResult<T, E> {
+    inner: SchroedingerBox<Result<T, E>>,
+}
+
+impl<T, E> SchroedingerResult<T, E> {
+    /// Wraps a superposition of results.
+    pub fn new(b: SchroedingerBox<Result<T, E>>) -> SchroedingerResult<T, E> {
+        SchroedingerResult {
+            inner: b,
+        }
+    }
+
+    /// Creates a result that is `Err(error)` with probability `p`, and `Ok(value)` otherwise.
+    ///
+    /// # Panic
+    ///
+    /// Panics unless `0.0 <= p && p <= 1.0`.
+    pub fn failing_with_probability(p: f64, value: T, error: E) -> SchroedingerResult<T, E> {
+        assert!((0.0..=1.0).contains(&p), "probability must be between 0 and 1");
+        SchroedingerResult::new(SchroedingerBox::from_f64_weights(vec![(1.0 - p, Ok(value)),
+                                                                       (p, Err(error))]))
+    }
+
+    /// Returns the probability that observing the result gives `Ok`, without observing it.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the result can’t be observed.
+    pub fn is_ok_probability(&self) -> f64 {
+        self.inner.probabilities().into_iter().filter(|&(_, r)| r.is_ok()).map(|(p, _)| p).sum()
+    }
+
+    /// Returns whether the result has been decided, without observing it.
+    pub fn is_collapsed(&self) -> bool {
+        self.inner.is_collapsed()
+    }
+
+    /// Moves the result out, observing it if needed.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the result can’t be observed.
+    pub fn collapse(self) -> Result<T, E> {
+        self.inner.into_inner()
+    }
+
+    /// Applies `f` to the value in every state that succeeded, without observing the result.
+    pub fn map<U, F>(self, mut f: F) -> SchroedingerResult<U, E> where F: FnMut(T) -> U {
+        SchroedingerResult::new(self.inner.map(|r| r.map(&mut f)))
+    }
+
+    /// Applies `f` to the error in every state that failed, without observing the result.
+    pub fn map_err<G, F>(self, mut f: F) -> SchroedingerResult<T, G> where F: FnMut(E) -> G {
+        SchroedingerResult::new(self.inner.map(|r| r.map_err(&mut f)))
+    }
+
+    /// Replaces every state that succeeded with `f` of its value, which may fail in turn, without
+    /// observing the result.
+    pub fn and_then<U, F>(self, mut f: F) -> SchroedingerResult<U, E>
+            where F: FnMut(T) -> Result<U, E> {
+        SchroedingerResult::new(self.inner.map(|r| r.and_then(&mut f)))
+    }
+
+    /// Moves the value out, observing the result if needed, or returns `default` if it failed.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the result can’t be observed.
+    pub fn unwrap_or(self, default: T) -> T {
+        self.collapse().unwrap_or(default)
+    }
+
+    /// Consumes the result, returning the box inside.
+    pub fn into_inner(self) -> SchroedingerBox<Result<T, E>> {
+        self.inner
+    }
+}
+
+impl<T, E> From<SchroedingerBox<Result<T, E>>> for SchroedingerResult<T, E> {
+    fn from(b: SchroedingerBox<Result<T, E>>) -> SchroedingerResult<T, E> {
+        SchroedingerResult::new(b)
+    }
+}
+
+impl<T, E> Deref for SchroedingerResult<T, E> {
+    type Target = Result<T, E>;
+
+    fn deref(&self) -> &Result<T, E> {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::SchroedingerResult;
+
+    #[test]
+    fn maybe_failing() {
+        let fine: SchroedingerResult<u8, ()> =
+            SchroedingerResult::failing_with_probability(0.0, 1, ());
+        assert_eq!(fine.is_ok_probability(), 1.0);
+        assert_eq!(fine.map(|x| x + 1).collapse(), Ok(2));
+        let broken = SchroedingerResult::failing_with_probability(1.0, 1, "oops");
+        assert_eq!(broken.map_err(str::len).unwrap_or(0), 0);
+        let b = SchroedingerBox::from_probabilities(vec![(1, Ok(1)), (1, Ok(2)), (2, Err('e'))]);
+        let even = SchroedingerResult::from(b)
+            .and_then(|x| if x % 2 == 0 { Ok(x) } else { Err('o') });
+        assert_eq!(even.is_ok_probability(), 0.25);
+        assert!(!even.is_collapsed());
+        let observed = *even;
+        assert_eq!(even.collapse(), observed);
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::RefCell;
+use rand::rngs::ThreadRng;
+use rand::{thread_rng, Error, RngCore};
+
+thread_local!(static OVERRIDE: RefCell<Option<Box<dyn RngCore>>> = const { RefCell::new(None) });
+
+/// Runs `f` with every observation on the current thread that isn’t given an RNG explicitly
+/// drawing from `rng` instead of the thread-local RNG.
+///
+/// This reaches boxes that are created and opened deep inside other code, so integration tests
+/// can be made deterministic by passing a seeded RNG. Overrides can be nested; the innermost one
+/// wins, and the previous one is restored when `f` returns or panics.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rand;
+/// # extern crate schroedinger_box;
+/// # use rand::SeedableRng;
+/// # use rand::rngs::StdRng;
+/// # use schroedinger_box::{with_rng_override, SchroedingerBox};
+/// # fn main() {
+/// fn roll_dice() -> Vec<u32> {
+///     (0..10).map(|_| *SchroedingerBox::new((1..7).collect())).collect()
+/// }
+/// let first = with_rng_override(StdRng::seed_from_u64(7), roll_dice);
+/// let again = with_rng_override(StdRng::seed_from_u64(7), roll_dice);
+/// assert_eq!(first, again);
+/// # }
+/// ```
+pub fn with_rng_override<R, F, T>(rng: R, f: F) -> T
+        where R: RngCore + 'static, F: FnOnce() -> T {
+    struct Restore(Option<Box<dyn RngCore>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            OVERRIDE.with(|o| *o.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(OVERRIDE.with(|o| o.borrow_mut().replace(Box::new(rng))));
+    f()
+}
+
+/// The RNG used for everything that isn’t given one explicitly: the override, if there is one,
+/// and the thread-local RNG otherwise.
+///
+/// The override is only borrowed for the duration of each draw, so user code run in between
+/// draws can observe boxes too.
+pub struct LocalRng {
+    fallback: ThreadRng,
+}
+
+/// Returns the RNG used for everything that isn’t given one explicitly.
+pub fn local_rng() -> LocalRng {
+    LocalRng {
+        fallback: thread_rng(),
+    }
+}
+
+impl LocalRng {
+    fn with<T, F>(&mut self, f: F) -> T where F: FnOnce(&mut dyn RngCore) -> T {
+        OVERRIDE.with(|o| match *o.borrow_mut() {
+            Some(ref mut rng) => f(&mut **rng),
+            None => f(&mut self.fallback),
+        })
+    }
+}
+
+impl RngCore for LocalRng {
+    fn next_u32(&mut self) -> u32 {
+        self.with(|r| r.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.with(|r| r.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.with(|r| r.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.with(|r| r.try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::mock::StepRng;
+    use rand::RngCore;
+    use SchroedingerBox;
+    use super::{local_rng, with_rng_override};
+
+    #[test]
+    fn nested_overrides() {
+        let outer = with_rng_override(StepRng::new(1, 0), || {
+            let inner = with_rng_override(StepRng::new(2, 0), || local_rng().next_u64());
+            (inner, local_rng().next_u64())
+        });
+        assert_eq!(outer, (2, 1));
+        // Both draws being zero means the first state is always chosen.
+        let zeros = with_rng_override(StepRng::new(0, 0), || {
+            (0..10).map(|_| *SchroedingerBox::new(vec![1, 2, 3])).collect::<Vec<_>>()
+        });
+        assert_eq!(zeros, vec![1; 10]);
+    }
+
+    #[test]
+    fn restored_after_panic() {
+        use std::panic::catch_unwind;
+        let _ = catch_unwind(|| with_rng_override(StepRng::new(0, 0), || panic!("oops")));
+        let draws: Vec<u64> = (0..3).map(|_| local_rng().next_u64()).collect();
+        assert!(draws.iter().any(|&d| d != 0));
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Hierarchical seeds for lazy, reproducible procedural generation.
+
+use std::cell::OnceCell;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rng::local_rng;
+use SchroedingerBox;
+
+/// A node in a tree of seeds.
+///
+/// The root seed is itself in superposition until something needs it. Every other node derives
+/// its seed from its parent’s and a key, so a given key path always leads to the same seed once
+/// the root has been decided, no matter which nodes were looked at first (or at all).
+///
+/// Nodes are cheap to create and share the root, so deciding the root through any node decides
+/// it for the whole tree.
+///
+/// Seeds only depend on the root seed and the hashes of the keys. Keys are hashed with a fixed
+/// algorithm, but note that some types (`usize`, for example) hash differently on different
+/// platforms, and that the RNGs handed out by `rng` are only reproducible as long as `rand`’s
+/// `StdRng` stays the same.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerBox;
+/// # use schroedinger_box::seed::SeedTree;
+/// let world = SeedTree::new();
+/// let chunk = world.child(&"forest").child(&(3u32, 4u32));
+/// let tree = SchroedingerBox::new(vec!["oak", "birch", "pine"]);
+/// let species = *chunk.child(&"tree 0").open(&tree);
+///
+/// // Regenerating the same chunk from the same root gives the same result.
+/// let again = SchroedingerBox::new(vec!["oak", "birch", "pine"]);
+/// let regenerated = SeedTree::from_seed(world.seed());
+/// let chunk = regenerated.child(&"forest").child(&(3u32, 4u32));
+/// assert_eq!(*chunk.child(&"tree 0").open(&again), species);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SeedTree {
+    root: Rc<OnceCell<u64>>,
+    path: Vec<u64>,
+}
+
+impl SeedTree {
+    /// Creates a new tree whose root seed will be decided randomly when it is first needed.
+    pub fn new() -> SeedTree {
+        SeedTree {
+            root: Rc::new(OnceCell::new()),
+            path: vec![],
+        }
+    }
+
+    /// Creates a new tree with the given root seed.
+    pub fn from_seed(seed: u64) -> SeedTree {
+        SeedTree {
+            root: Rc::new(OnceCell::from(seed)),
+            path: vec![],
+        }
+    }
+
+    /// Returns whether the root seed has been decided yet.
+    pub fn is_decided(&self) -> bool {
+        self.root.get().is_some()
+    }
+
+    /// Returns the child node with the given key, without deciding anything.
+    pub fn child<K>(&self, key: &K) -> SeedTree where K: Hash + ?Sized {
+        let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+        key.hash(&mut hasher);
+        let mut path = self.path.clone();
+        path.push(hasher.finish());
+        SeedTree {
+            root: self.root.clone(),
+            path,
+        }
+    }
+
+    /// Returns this node’s seed, deciding the root seed if needed.
+    pub fn seed(&self) -> u64 {
+        let root = *self.root.get_or_init(|| local_rng().gen());
+        self.path.iter().fold(root, |seed, &key| mix(mix(seed) ^ key))
+    }
+
+    /// Returns an RNG seeded with this node’s seed.
+    pub fn rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.seed())
+    }
+
+    /// Opens a box using this node’s seed, so that it collapses the same way every time.
+    ///
+    /// The box is opened with a fresh `rng`, so opening several boxes with the same node
+    /// correlates their outcomes; give each box its own child instead.
+    pub fn open<'a, Cat>(&self, b: &'a SchroedingerBox<Cat>) -> &'a Cat {
+        b.collapse_with(&mut self.rng())
+    }
+}
+
+impl Default for SeedTree {
+    fn default() -> SeedTree {
+        SeedTree::new()
+    }
+}
+
+/// The SplitMix64 finaliser.
+pub(crate) const fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The FNV-1a hash, which unlike `DefaultHasher` is guaranteed never to change.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::SeedTree;
+
+    #[test]
+    fn lazy_and_shared_root() {
+        let root = SeedTree::new();
+        let child = root.child("a").child(&1u8);
+        assert!(!root.is_decided());
+        let seed = child.seed();
+        assert!(root.is_decided());
+        assert_eq!(root.child("a").child(&1u8).seed(), seed);
+        assert!(root.child("a").child(&2u8).seed() != seed);
+        assert!(root.child("b").child(&1u8).seed() != seed);
+    }
+
+    #[test]
+    fn reproducible() {
+        let a = SeedTree::from_seed(42).child("region").child(&7u64);
+        let b = SeedTree::from_seed(42).child("region").child(&7u64);
+        assert_eq!(a.seed(), b.seed());
+        let states: Vec<u32> = (0..1000).collect();
+        let (x, y) = (SchroedingerBox::new(states.clone()), SchroedingerBox::new(states));
+        assert_eq!(a.open(&x), b.open(&y));
+        // Seeds must not change between releases, or saved worlds would be regenerated
+        // differently.
+        assert_eq!(SeedTree::from_seed(0).child("x").seed(), 0x9039_780d_fe85_86db);
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Serialization with Serde, enabled by the `serde` feature.
+//!
+//! A box is serialized as a sequence of `(weight, state)` pairs, without observing it: every state
+//! if it is still in superposition, or just the observed one if it isn’t. So a box in
+//! superposition comes back with all its states and weights, and an observed box comes back
+//! observed.
+
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::ser::{Serialize, Serializer};
+use {SchroedingerBox, SuperpositionError};
+
+impl<Cat> Serialize for SchroedingerBox<Cat> where Cat: Serialize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_seq(self.states())
+    }
+}
+
+impl<'de, Cat> Deserialize<'de> for SchroedingerBox<Cat> where Cat: Deserialize<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<SchroedingerBox<Cat>, D::Error>
+            where D: Deserializer<'de> {
+        let states = Vec::<(u64, Cat)>::deserialize(deserializer)?;
+        if states.is_empty() {
+            return Err(D::Error::custom(SuperpositionError::Empty))
+        }
+        Ok(SchroedingerBox::from_probabilities(states))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bincode::{deserialize, serialize};
+    use SchroedingerBox;
+
+    #[test]
+    fn round_trips() {
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (3, 'c')]);
+        let bytes = serialize(&b).unwrap();
+        assert_eq!(bytes, serialize(&vec![(1u64, 'a'), (0, 'b'), (3, 'c')]).unwrap());
+        assert!(!b.is_collapsed());
+        let back: SchroedingerBox<char> = deserialize(&bytes).unwrap();
+        assert_eq!(back.states(), b.states());
+        assert!(!back.is_collapsed());
+        let observed = *b;
+        let back: SchroedingerBox<char> = deserialize(&serialize(&b).unwrap()).unwrap();
+        assert!(back.is_collapsed());
+        assert_eq!(*back, observed);
+        let empty = serialize(&Vec::<(u64, char)>::new()).unwrap();
+        assert!(deserialize::<SchroedingerBox<char>>(&empty).is_err());
+    }
+}
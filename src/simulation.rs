@@ -0,0 +1,121 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use rng::{local_rng, LocalRng};
+use SchroedingerBox;
+
+/// Runs a model `n` times, collecting the outcomes.
+///
+/// Each run gets a `Trial`, through which the model can observe any boxes it likes. Boxes are
+/// never collapsed by a trial: every run sees a fresh observation of the same superposition.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{monte_carlo, SchroedingerBox};
+/// let d6 = SchroedingerBox::new((1..7).collect::<Vec<u32>>());
+/// let two_dice = monte_carlo(|t| t.observe(&d6) + t.observe(&d6), 10_000);
+/// let (low, high) = two_dice.confidence_interval(4.0);
+/// assert!(low < 7.0 && 7.0 < high);
+/// assert_eq!(two_dice.histogram().len(), 11);
+/// ```
+pub fn monte_carlo<T, F>(model: F, n: usize) -> Outcomes<T> where F: Fn(&mut Trial) -> T {
+    let mut trial = Trial {
+        rng: local_rng(),
+    };
+    Outcomes {
+        samples: (0..n).map(|_| model(&mut trial)).collect(),
+    }
+}
+
+/// A single run of a Monte Carlo simulation.
+pub struct Trial {
+    rng: LocalRng,
+}
+
+impl Trial {
+    /// Observes a box for this run only, leaving the box itself uncollapsed.
+    ///
+    /// A box that has already collapsed always gives its observed state.
+    pub fn observe<Cat>(&mut self, b: &SchroedingerBox<Cat>) -> Cat where Cat: Clone {
+        b.sample_ref(&mut self.rng).clone()
+    }
+}
+
+/// The outcomes of a Monte Carlo simulation.
+#[derive(Clone, Debug)]
+pub struct Outcomes<T> {
+    samples: Vec<T>,
+}
+
+impl<T> Outcomes<T> {
+    /// Returns the outcome of every run, in order.
+    pub fn samples(&self) -> &[T] {
+        &self.samples
+    }
+
+    /// Counts how many times each outcome occurred.
+    pub fn histogram(&self) -> HashMap<T, u64> where T: Clone + Eq + Hash {
+        let mut counts = HashMap::new();
+        for s in &self.samples {
+            *counts.entry(s.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns the mean of the outcomes, or NaN if there were no runs.
+    pub fn mean(&self) -> f64 where T: Copy + Into<f64> {
+        self.samples.iter().map(|&s| s.into()).sum::<f64>() / self.samples.len() as f64
+    }
+
+    /// Returns the sample variance of the outcomes, or NaN if there were fewer than two runs.
+    pub fn variance(&self) -> f64 where T: Copy + Into<f64> {
+        let mean = self.mean();
+        let squares = self.samples.iter().map(|&s| (s.into() - mean).powi(2)).sum::<f64>();
+        squares / (self.samples.len() as f64 - 1.0)
+    }
+
+    /// Returns a confidence interval for the true mean, `z` standard errors either side of the
+    /// sample mean (so `z = 1.96` gives a 95% interval).
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) where T: Copy + Into<f64> {
+        let mean = self.mean();
+        let error = z * (self.variance() / self.samples.len() as f64).sqrt();
+        (mean - error, mean + error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::monte_carlo;
+
+    #[test]
+    fn does_not_collapse() {
+        let coin = SchroedingerBox::new(vec![0u8, 1]);
+        let flips = monte_carlo(|t| t.observe(&coin), 1000);
+        let hist = flips.histogram();
+        assert_eq!(hist.len(), 2);
+        assert_eq!(hist[&0] + hist[&1], 1000);
+        let (low, high) = flips.confidence_interval(4.0);
+        assert!(low < 0.5 && 0.5 < high);
+    }
+
+    #[test]
+    fn statistics() {
+        let fixed = SchroedingerBox::new(vec![2.0f64]);
+        let runs = monte_carlo(|t| t.observe(&fixed) * 3.0, 10);
+        assert_eq!(runs.samples(), &[6.0; 10][..]);
+        assert_eq!(runs.mean(), 6.0);
+        assert_eq!(runs.variance(), 0.0);
+        assert_eq!(runs.confidence_interval(1.96), (6.0, 6.0));
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 5)]);
+        assert_eq!(monte_carlo(|t| t.observe(&zero), 3).samples(), &[5; 3][..]);
+    }
+}
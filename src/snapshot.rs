@@ -0,0 +1,122 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::{Cell, OnceCell};
+use std::fmt;
+use std::time::Instant;
+use SchroedingerBox;
+
+/// Everything about a box at some point in time, as taken by `SchroedingerBox::snapshot`.
+///
+/// A snapshot is opaque: it remembers the states, their weights and whether the box had been
+/// observed (and how), but it doesn’t let anyone look at them.
+pub struct Snapshot<Cat> {
+    states: Vec<(u64, Cat)>,
+    chosen: Option<usize>,
+    collapsed_at: Option<Instant>,
+    panic: Option<::Panic>,
+    frozen: bool,
+}
+
+impl<Cat> fmt::Debug for Snapshot<Cat> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Snapshot").finish_non_exhaustive()
+    }
+}
+
+impl<Cat> SchroedingerBox<Cat> where Cat: Clone {
+    /// Takes a snapshot of the box, without observing it, so that it can be rolled back with
+    /// `restore` later.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let mut b = SchroedingerBox::new(vec![1, 2, 3]);
+    /// let before = b.snapshot();
+    /// b.retain(|&x| x > 2).unwrap();
+    /// assert_eq!(*b, 3);
+    /// b.restore(before);
+    /// // The box is back in its superposition, as if it had never been observed.
+    /// assert_eq!(b.peek(), None);
+    /// ```
+    pub fn snapshot(&self) -> Snapshot<Cat> {
+        let panic = self._panic.take();
+        self._panic.set(panic.clone());
+        Snapshot {
+            states: self._states.clone(),
+            chosen: self._chosen.get().cloned(),
+            collapsed_at: self._collapsed_at.get().cloned(),
+            panic,
+            frozen: self._frozen.get(),
+        }
+    }
+
+    /// Puts the box back the way it was when `snapshot` was taken, including whether (and when)
+    /// it had been observed and whether its weights were frozen.
+    ///
+    /// A snapshot can be restored into any box of the same type, not just the one it was taken
+    /// from.
+    pub fn restore(&mut self, snapshot: Snapshot<Cat>) {
+        let (chosen, collapsed_at) = (OnceCell::new(), OnceCell::new());
+        if let Some(i) = snapshot.chosen {
+            let _ = chosen.set(i);
+        }
+        if let Some(t) = snapshot.collapsed_at {
+            let _ = collapsed_at.set(t);
+        }
+        self._states = snapshot.states;
+        self._chosen = chosen;
+        self._collapsed_at = collapsed_at;
+        self._panic = Cell::new(snapshot.panic);
+        self._frozen = Cell::new(snapshot.frozen);
+        #[cfg(feature = "test-util")]
+        {
+            self._trimmed_from = None;
+        }
+        self.track();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Observable, SchroedingerBox};
+
+    #[test]
+    fn rolls_back() {
+        let mut b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (2, 'b'), (3, 'c')]);
+        let before = b.snapshot();
+        b.retain(|&c| c != 'a').unwrap();
+        assert_eq!(b.probability_of(&'c'), 0.6);
+        let observed = *b;
+        let after = b.snapshot();
+        b.restore(before);
+        assert!(!b.is_frozen());
+        assert_eq!(b.collapsed_at(), None);
+        assert_eq!(b.probability_of(&'a'), 1.0 / 6.0);
+        b.restore(after);
+        assert_eq!(*b, observed);
+        assert_eq!(b.peek(), Some(&observed));
+        assert!(b.is_frozen());
+    }
+
+    #[test]
+    fn keeps_pending_panics() {
+        use {set_panic_mode, PanicMode};
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut b = SchroedingerBox::maybe_panic(1, 0u8, 1, "boom");
+        let before = b.snapshot();
+        assert_eq!(b.peek(), None);
+        b.restore(before);
+        let old = set_panic_mode(PanicMode::Always);
+        let result = catch_unwind(AssertUnwindSafe(|| *b));
+        set_panic_mode(old);
+        assert!(result.is_err());
+    }
+}
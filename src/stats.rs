@@ -0,0 +1,123 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use {entropy, SchroedingerBox};
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Returns the expected value of `f` over the box’s distribution, without observing it.
+    ///
+    /// Once the box has been observed, this is just `f` of the observed state.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let damage = SchroedingerBox::from_probabilities(vec![(3, "graze"), (1, "crit")]);
+    /// let mean = damage.expected_by(|&hit| if hit == "crit" { 20.0 } else { 4.0 });
+    /// assert_eq!(mean, 8.0);
+    /// ```
+    pub fn expected_by<F>(&self, f: F) -> f64 where F: Fn(&Cat) -> f64 {
+        self.probabilities().into_iter().map(|(p, c)| p * f(c)).sum()
+    }
+
+    /// Returns the variance of `f` over the box’s distribution, without observing it.
+    ///
+    /// Once the box has been observed, the variance is zero.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    pub fn variance_by<F>(&self, f: F) -> f64 where F: Fn(&Cat) -> f64 {
+        let probabilities = self.probabilities();
+        let values: Vec<_> = probabilities.iter().map(|&(_, c)| f(c)).collect();
+        let mean = probabilities.iter().zip(&values).map(|(&(p, _), x)| p * x).sum::<f64>();
+        probabilities.iter().zip(&values).map(|(&(p, _), x)| p * (x - mean) * (x - mean)).sum()
+    }
+
+    /// Returns the Shannon entropy of the box’s odds, in nats, without observing it.
+    ///
+    /// This is zero for a box that has been observed (or has only one state), and NaN for a box
+    /// that can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let coin = SchroedingerBox::new(vec!["heads", "tails"]);
+    /// assert_eq!(coin.entropy(), 2f64.ln());
+    /// let _ = *coin;
+    /// assert_eq!(coin.entropy(), 0.0);
+    /// ```
+    pub fn entropy(&self) -> f64 {
+        match self.odds() {
+            [_] => 0.0,
+            states => entropy(states),
+        }
+    }
+
+    /// Returns the expected value of the box’s states, without observing it.
+    ///
+    /// This is `expected_by` for states that are numbers.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let die = SchroedingerBox::new((1..7).collect::<Vec<u32>>());
+    /// assert_eq!(die.expected_value(), 3.5);
+    /// assert!((die.variance() - 35.0 / 12.0).abs() < 1e-12);
+    /// ```
+    pub fn expected_value(&self) -> f64 where Cat: Clone + Into<f64> {
+        self.expected_by(|c| c.clone().into())
+    }
+
+    /// Returns the variance of the box’s states, without observing it.
+    ///
+    /// This is `variance_by` for states that are numbers.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    pub fn variance(&self) -> f64 where Cat: Clone + Into<f64> {
+        self.variance_by(|c| c.clone().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+
+    #[test]
+    fn moments() {
+        let b = SchroedingerBox::from_probabilities(vec![(1, 2u8), (0, 100), (1, 4)]);
+        assert_eq!(b.expected_value(), 3.0);
+        assert_eq!(b.variance(), 1.0);
+        assert_eq!(b.expected_by(|&x| f64::from(x) * 10.0), 30.0);
+        assert!(!b.is_collapsed());
+        let observed = f64::from(*b);
+        assert_eq!(b.expected_value(), observed);
+        assert_eq!(b.variance(), 0.0);
+    }
+
+    #[test]
+    fn test_entropy() {
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (1, 'c'), (2, 'd')]);
+        assert!((b.entropy() - 1.5 * 2f64.ln()).abs() < 1e-12);
+        assert!(b.is_frozen() && !b.is_collapsed());
+        assert_eq!(SchroedingerBox::new(vec!['a']).entropy(), 0.0);
+        assert!(SchroedingerBox::from_probabilities(vec![(0, 'a'), (0, 'b')]).entropy().is_nan());
+    }
+}
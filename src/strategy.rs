@@ -0,0 +1,85 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::ops::Deref;
+use SchroedingerBox;
+
+/// A superposition of implementations of a trait, which settles on one of them the first time
+/// it is used.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerStrategy;
+/// trait Sorter { fn name(&self) -> &'static str; }
+/// struct Quick;
+/// struct Merge;
+/// impl Sorter for Quick { fn name(&self) -> &'static str { "quicksort" } }
+/// impl Sorter for Merge { fn name(&self) -> &'static str { "mergesort" } }
+///
+/// let sorter: SchroedingerStrategy<dyn Sorter> =
+///     SchroedingerStrategy::new(vec![(9, Box::new(Quick)), (1, Box::new(Merge))]);
+/// // The first call decides which implementation we get...
+/// let name = sorter.name();
+/// // ...and every call after that sticks with it.
+/// assert_eq!(sorter.as_ref().name(), name);
+/// ```
+pub struct SchroedingerStrategy<T: ?Sized> {
+    inner: SchroedingerBox<Box<T>>,
+}
+
+impl<T: ?Sized> SchroedingerStrategy<T> {
+    /// Creates a new strategy from a set of weighted implementations.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `impls.len() == 0`.
+    pub fn new(impls: Vec<(u64, Box<T>)>) -> SchroedingerStrategy<T> {
+        SchroedingerStrategy {
+            inner: SchroedingerBox::from_probabilities(impls),
+        }
+    }
+
+    /// Moves the chosen implementation out, choosing one if needed.
+    pub fn into_inner(self) -> Box<T> {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for SchroedingerStrategy<T> {
+    /// Obtains a reference to the chosen implementation, choosing one if needed.
+    fn as_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: ?Sized> Deref for SchroedingerStrategy<T> {
+    type Target = T;
+
+    /// Obtains a reference to the chosen implementation, choosing one if needed.
+    fn deref(&self) -> &T {
+        self.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Display;
+    use super::SchroedingerStrategy;
+
+    #[test]
+    fn sticks_with_one() {
+        let s: SchroedingerStrategy<dyn Display> =
+            SchroedingerStrategy::new(vec![(1, Box::new(1)), (1, Box::new("two"))]);
+        let first = s.to_string();
+        for _ in 0..10 {
+            assert_eq!(s.as_ref().to_string(), first);
+        }
+        assert_eq!(s.into_inner().to_string(), first);
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::OnceCell;
+use rand::Rng;
+use rand::seq::index;
+use rng::local_rng;
+
+/// Some subset of a set of items, undetermined until observed.
+///
+/// The whole subset collapses at once, the first time any part of it is looked at.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerSubset;
+/// let fired = SchroedingerSubset::with_size(vec!["alarm", "sprinkler", "lights"], 2);
+/// assert_eq!(fired.iter().count(), 2);
+/// assert_eq!(fired.mask().iter().filter(|&&b| b).count(), 2);
+/// ```
+pub struct SchroedingerSubset<T> {
+    items: Vec<T>,
+    mode: Mode,
+    mask: OnceCell<Vec<bool>>,
+}
+
+enum Mode {
+    Independent(f64),
+    Size(usize),
+}
+
+impl<T> SchroedingerSubset<T> {
+    /// Creates a new subset where each item is included independently with probability `p`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `p` is not between 0 and 1.
+    pub fn with_probability(items: Vec<T>, p: f64) -> SchroedingerSubset<T> {
+        assert!((0.0..=1.0).contains(&p));
+        SchroedingerSubset {
+            items,
+            mode: Mode::Independent(p),
+            mask: OnceCell::new(),
+        }
+    }
+
+    /// Creates a new subset of exactly `k` items, each such subset being equally likely.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `k > items.len()`.
+    pub fn with_size(items: Vec<T>, k: usize) -> SchroedingerSubset<T> {
+        assert!(k <= items.len());
+        SchroedingerSubset {
+            items,
+            mode: Mode::Size(k),
+            mask: OnceCell::new(),
+        }
+    }
+
+    /// Returns whether the subset has been determined yet.
+    pub fn is_collapsed(&self) -> bool {
+        self.mask.get().is_some()
+    }
+
+    /// Observes the subset, returning whether each item is in it.
+    pub fn mask(&self) -> &[bool] {
+        self.mask.get_or_init(|| {
+            let mut rng = local_rng();
+            let n = self.items.len();
+            match self.mode {
+                Mode::Independent(p) => (0..n).map(|_| rng.gen_bool(p)).collect(),
+                Mode::Size(k) => {
+                    let mut mask = vec![false; n];
+                    for i in index::sample(&mut rng, n, k) {
+                        mask[i] = true;
+                    }
+                    mask
+                },
+            }
+        })
+    }
+
+    /// Observes the subset, returning whether the item at index `i` is in it.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn contains(&self, i: usize) -> bool {
+        self.mask()[i]
+    }
+
+    /// Observes the subset, returning an iterator over the items in it.
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
+        self.items.iter().zip(self.mask()).filter(|&(_, &b)| b).map(|(t, _)| t)
+    }
+
+    /// Observes the subset, returning the items in it.
+    pub fn into_vec(self) -> Vec<T> {
+        self.mask();
+        let mask = self.mask.into_inner().unwrap();
+        self.items.into_iter().zip(mask).filter(|&(_, b)| b).map(|(t, _)| t).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SchroedingerSubset;
+
+    #[test]
+    fn probability_extremes() {
+        let none = SchroedingerSubset::with_probability(vec![1, 2, 3], 0.0);
+        assert!(!none.is_collapsed());
+        assert_eq!(none.into_vec(), vec![]);
+        let all = SchroedingerSubset::with_probability(vec![1, 2, 3], 1.0);
+        assert_eq!(all.into_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn fixed_size() {
+        let sub = SchroedingerSubset::with_size((0..10).collect(), 4);
+        let mask = sub.mask().to_vec();
+        assert!(sub.is_collapsed());
+        assert_eq!(mask, sub.mask());
+        let chosen = sub.into_vec();
+        assert_eq!(chosen.len(), 4);
+        assert!(chosen.iter().all(|&i| mask[i]));
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::ops::Deref;
+#[cfg(feature = "async")]
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+#[cfg(feature = "async")]
+use std::task::Waker;
+use std::sync::atomic::Ordering::{AcqRel, Acquire};
+use rng::local_rng;
+use {try_choose, SchroedingerBox, SuperpositionError};
+
+/// A `SchroedingerBox` that can be shared between threads.
+///
+/// However many threads observe the box at once, exactly one outcome is chosen, and every thread
+/// sees it. Observing never blocks: the threads racing to collapse the box each choose a state,
+/// the first to finish installs its choice with a single atomic compare-and-swap, and the rest
+/// use that one instead. Afterwards, observing costs one atomic load.
+///
+/// Unlike `SchroedingerBox`, the states can’t be changed once the box has been made.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// # use schroedinger_box::SyncSchroedingerBox;
+/// let cat = Arc::new(SyncSchroedingerBox::new(vec!["alive", "dead"]));
+/// let seen: Vec<&str> = (0..4).map(|_| {
+///     let cat = cat.clone();
+///     thread::spawn(move || **cat)
+/// }).map(|t| t.join().unwrap()).collect();
+/// assert!(seen.iter().all(|&s| s == **cat));
+/// ```
+pub struct SyncSchroedingerBox<Cat> {
+    states: Vec<(u64, Cat)>,
+    // The index of the observed state, or `UNDECIDED`.
+    chosen: AtomicUsize,
+    // The tasks waiting for the box to be observed.
+    #[cfg(feature = "async")]
+    pub(crate) wakers: Mutex<Vec<Waker>>,
+}
+
+/// No `Vec` can be long enough for this to be the index of a state.
+const UNDECIDED: usize = usize::MAX;
+
+impl<Cat> SyncSchroedingerBox<Cat> {
+    /// Creates a new box from a set of states, as `SchroedingerBox::new` does.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `states.len() == 0`.
+    pub fn new(states: Vec<Cat>) -> SyncSchroedingerBox<Cat> {
+        SyncSchroedingerBox::from_probabilities(states.into_iter().map(|x| (1, x)).collect())
+    }
+
+    /// Creates a new box from a set of weighted states, as `SchroedingerBox::from_probabilities`
+    /// does.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `states.len() == 0`.
+    pub fn from_probabilities(states: Vec<(u64, Cat)>) -> SyncSchroedingerBox<Cat> {
+        assert!(!states.is_empty(), "{}", SuperpositionError::Empty);
+        SyncSchroedingerBox {
+            states,
+            chosen: AtomicUsize::new(UNDECIDED),
+            #[cfg(feature = "async")]
+            wakers: Mutex::new(vec![]),
+        }
+    }
+
+    /// Like dereferencing the box, but returns an error instead of panicking if the box can’t be
+    /// observed.
+    pub fn try_get(&self) -> Result<&Cat, SuperpositionError> {
+        if self.states.len() == 1 {
+            return Ok(&self.states[0].1)
+        }
+        let i = match self.chosen.load(Acquire) {
+            UNDECIDED => {
+                let i = try_choose(&self.states, &mut local_rng())?;
+                match self.chosen.compare_exchange(UNDECIDED, i, AcqRel, Acquire) {
+                    Ok(_) => {
+                        #[cfg(feature = "async")]
+                        ::future::wake_all(self);
+                        i
+                    },
+                    Err(winner) => winner,
+                }
+            },
+            i => i,
+        };
+        Ok(&self.states[i].1)
+    }
+
+    /// Returns whether the box has collapsed, without observing it.
+    pub fn is_collapsed(&self) -> bool {
+        self.states.len() == 1 || self.chosen.load(Acquire) != UNDECIDED
+    }
+
+    /// Moves the value out, consuming the box and observing it if needed.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    pub fn into_inner(mut self) -> Cat {
+        if let Err(e) = self.try_get() {
+            panic!("{}", e);
+        }
+        let i = match *self.chosen.get_mut() {
+            UNDECIDED => 0,
+            i => i,
+        };
+        self.states.swap_remove(i).1
+    }
+}
+
+impl<Cat> From<SchroedingerBox<Cat>> for SyncSchroedingerBox<Cat> {
+    /// Makes a box shareable, keeping its superposition (or its observed state).
+    fn from(b: SchroedingerBox<Cat>) -> SyncSchroedingerBox<Cat> {
+        SyncSchroedingerBox::from_probabilities(b.into_states())
+    }
+}
+
+impl<Cat> Deref for SyncSchroedingerBox<Cat> {
+    type Target = Cat;
+
+    fn deref(&self) -> &Cat {
+        self.try_get().unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+/// Formats the box without observing it, as `SchroedingerBox` does.
+impl<Cat> fmt::Debug for SyncSchroedingerBox<Cat>
+        where Cat: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.chosen.load(Acquire) {
+            UNDECIDED if self.states.len() != 1 => ::debug_superposed(f, &self.states),
+            UNDECIDED => f.debug_tuple("Collapsed").field(&self.states[0].1).finish(),
+            i => f.debug_tuple("Collapsed").field(&self.states[i].1).finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use {SchroedingerBox, SuperpositionError};
+    use super::SyncSchroedingerBox;
+
+    #[test]
+    fn one_outcome_for_all_threads() {
+        for _ in 0..10 {
+            let b = Arc::new(SyncSchroedingerBox::new((0..1000).collect::<Vec<u32>>()));
+            let barrier = Arc::new(Barrier::new(8));
+            let seen: Vec<u32> = (0..8).map(|_| {
+                let (b, barrier) = (b.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    **b
+                })
+            }).collect::<Vec<_>>().into_iter().map(|t| t.join().unwrap()).collect();
+            assert!(b.is_collapsed());
+            assert!(seen.iter().all(|&s| s == **b));
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        let observed = SchroedingerBox::new(vec![1, 2, 3]);
+        let state = *observed;
+        let shared = SyncSchroedingerBox::from(observed);
+        assert!(shared.is_collapsed());
+        assert_eq!(shared.into_inner(), state);
+        let zero = SyncSchroedingerBox::from_probabilities(vec![(0, 1), (0, 2)]);
+        assert_eq!(zero.try_get().err(), Some(SuperpositionError::ZeroTotalWeight));
+        assert!(!zero.is_collapsed());
+        assert_eq!(format!("{:?}", zero), "Superposed {1: 0, 2: 0}");
+        assert_eq!(format!("{:?}", SyncSchroedingerBox::new(vec![3])), "Collapsed(3)");
+    }
+}
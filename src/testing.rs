@@ -0,0 +1,289 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Statistical assertions about `SchroedingerBox`es.
+//!
+//! Checking a random outcome against a single expected value is either flaky or useless. The
+//! helpers here sample a box many times and test the frequencies properly instead, so a test
+//! fails by chance only as often as you allow it to.
+
+use std::fmt;
+use std::hash::Hash;
+use alias::AliasTable;
+use rng::local_rng;
+use {total_weight, SchroedingerBox};
+
+/// The result of a statistical test.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TestOutcome {
+    /// The test statistic.
+    pub statistic: f64,
+    /// The number of degrees of freedom the statistic was compared with.
+    pub degrees_of_freedom: usize,
+    /// The probability of a statistic at least this extreme if the box behaves as declared.
+    pub p_value: f64,
+    /// Whether the box passed, i.e. whether `p_value` is at least the significance level.
+    pub passed: bool,
+}
+
+/// Draws `samples` states from a box, without observing it, and runs Pearson’s chi-square test
+/// of the observed frequencies against the box’s weights at significance level `alpha`.
+///
+/// A correctly behaving box fails with probability `alpha`, so pick something small. States with
+/// a weight of zero must never be drawn; if one is, the test fails outright. A box with only one
+/// state, whatever its weight, or that has already been observed, has nothing to test, and always
+/// passes.
+///
+/// # Panic
+///
+/// Panics if the box can’t be observed.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::SchroedingerBox;
+/// # use schroedinger_box::testing::chi_square;
+/// let loaded = SchroedingerBox::from_probabilities(vec![(1, 1), (1, 2), (1, 3), (5, 4)]);
+/// assert!(chi_square(&loaded, 10_000, 1e-6).passed);
+/// ```
+pub fn chi_square<Cat>(b: &SchroedingerBox<Cat>, samples: usize, alpha: f64) -> TestOutcome {
+    let states = b.states();
+    if states.len() == 1 {
+        // A box with one state always gives it, whatever its weight.
+        return TestOutcome {
+            statistic: 0.0,
+            degrees_of_freedom: 0,
+            p_value: 1.0,
+            passed: true,
+        }
+    }
+    if let Err(e) = total_weight(states) {
+        panic!("{}", e);
+    }
+    let weights: Vec<u64> = states.iter().map(|&(w, _)| w).collect();
+    let mut observed = vec![0; states.len()];
+    let table = AliasTable::new(weights.iter().cloned());
+    let mut rng = local_rng();
+    for _ in 0..samples {
+        observed[table.sample(&mut rng)] += 1;
+    }
+    outcome(&observed, &weights, alpha)
+}
+
+/// Asserts that `expr` is a box whose states turn up with the expected frequencies.
+///
+/// The box is sampled `samples` times without being observed, and each expected frequency must
+/// be matched to within `tolerance`. States not listed are expected never to turn up. Keys must be
+/// single tokens (such as literals), so wrap anything longer in parentheses. On failure, the
+/// message includes everything that was observed.
+///
+/// This is a thin wrapper around `testing::assert_distribution`.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate schroedinger_box;
+/// # use schroedinger_box::SchroedingerBox;
+/// # fn main() {
+/// let coin = SchroedingerBox::from_probabilities(vec![(1, "heads"), (3, "tails")]);
+/// assert_distribution!(coin, samples = 10_000, expected = {"heads": 0.25, "tails": 0.75},
+///                      tolerance = 0.03);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_distribution {
+    ($b:expr, samples = $n:expr, expected = { $($k:tt : $p:expr),* $(,)* },
+     tolerance = $t:expr $(,)*) => {
+        $crate::testing::assert_distribution(&$b, $n, &[$(($k, $p)),*], $t)
+    };
+}
+
+/// Asserts that a box’s states turn up with the expected frequencies, as `assert_distribution!`
+/// does.
+///
+/// # Panic
+///
+/// Panics if any frequency is off by more than `tolerance`, or if the box can’t be observed.
+pub fn assert_distribution<Cat>(b: &SchroedingerBox<Cat>, samples: usize, expected: &[(Cat, f64)],
+                                tolerance: f64) where Cat: Clone + Eq + Hash + fmt::Debug {
+    let tally = b.histogram(samples, &mut local_rng());
+    let frequency = |c: &Cat| tally.get(c).map_or(0, |&n| n) as f64 / samples as f64;
+    let mut failures = vec![];
+    for &(ref c, p) in expected.iter() {
+        if (frequency(c) - p).abs() > tolerance {
+            failures.push(format!("{:?} turned up {:.2}% of the time, not {:.2}% ± {:.2}%", c,
+                                  frequency(c) * 100.0, p * 100.0, tolerance * 100.0));
+        }
+    }
+    for c in tally.keys() {
+        if frequency(c) > tolerance && !expected.iter().any(|(e, _)| e == c) {
+            failures.push(format!("{:?} turned up {:.2}% of the time, but wasn’t expected", c,
+                                  frequency(c) * 100.0));
+        }
+    }
+    if !failures.is_empty() {
+        let mut observed: Vec<_> = tally.iter().collect();
+        observed.sort_by(|a, b| b.1.cmp(a.1));
+        panic!("distribution assertion failed after {} samples:\n  {}\nobserved: {:?}", samples,
+               failures.join("\n  "), observed);
+    }
+}
+
+/// Runs the chi-square test on a tally of observations.
+fn outcome(observed: &[u64], weights: &[u64], alpha: f64) -> TestOutcome {
+    let n = observed.iter().sum::<u64>() as f64;
+    let total = weights.iter().sum::<u64>() as f64;
+    let mut statistic = 0.0;
+    let mut categories = 0;
+    for (&o, &w) in observed.iter().zip(weights) {
+        if w == 0 {
+            if o > 0 {
+                statistic = f64::INFINITY;
+            }
+            continue
+        }
+        let expected = n * w as f64 / total;
+        statistic += (o as f64 - expected).powi(2) / expected;
+        categories += 1;
+    }
+    let degrees_of_freedom = categories.max(1) - 1;
+    let p_value = if statistic.is_infinite() {
+        0.0
+    } else if degrees_of_freedom == 0 {
+        1.0
+    } else {
+        gamma_q(degrees_of_freedom as f64 / 2.0, statistic / 2.0)
+    };
+    TestOutcome {
+        statistic,
+        degrees_of_freedom,
+        p_value,
+        passed: p_value >= alpha,
+    }
+}
+
+/// The regularised upper incomplete gamma function, Q(a, x).
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const EPS: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+    if x <= 0.0 {
+        return 1.0
+    }
+    let prefactor = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        // The series for P(a, x) converges quickly here.
+        let (mut term, mut sum, mut ap) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..1000 {
+            ap += 1.0;
+            term *= x / ap;
+            sum += term;
+            if term.abs() < sum.abs() * EPS {
+                break
+            }
+        }
+        1.0 - sum * prefactor
+    } else {
+        // Otherwise use the continued fraction for Q(a, x), evaluated with Lentz’s method.
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPS {
+                break
+            }
+        }
+        prefactor * h
+    }
+}
+
+/// The natural logarithm of the gamma function, using the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..].iter().enumerate()
+        .fold(COEFFICIENTS[0], |s, (i, &c)| s + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::{chi_square, gamma_q, outcome};
+
+    #[test]
+    fn p_values() {
+        // With two degrees of freedom, the p-value is exactly `exp(-x / 2)`.
+        for &x in &[0.5, 2.0, 10.0] {
+            assert!((gamma_q(1.0, x / 2.0) - (-x / 2.0f64).exp()).abs() < 1e-12);
+        }
+        // The textbook 5% critical value for one degree of freedom.
+        assert!((gamma_q(0.5, 3.841_459 / 2.0) - 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn detects_bias() {
+        let fair = outcome(&[510, 490], &[1, 1], 0.01);
+        assert!(fair.passed);
+        assert_eq!(fair.degrees_of_freedom, 1);
+        assert!(!outcome(&[600, 400], &[1, 1], 0.01).passed);
+        let impossible = outcome(&[1, 999], &[0, 1], 0.01);
+        assert!(!impossible.passed);
+        assert_eq!(impossible.p_value, 0.0);
+    }
+
+    #[test]
+    fn distributions() {
+        use std::panic::catch_unwind;
+
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (3, 'c')]);
+        assert_distribution!(b, samples = 10_000, expected = {'a': 0.25, 'c': 0.75},
+                             tolerance = 0.05);
+        let wrong = catch_unwind(|| {
+            let b = SchroedingerBox::new(vec![1, 2]);
+            assert_distribution!(b, samples = 1000, expected = {1: 1.0}, tolerance = 0.01);
+        });
+        let message = wrong.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("1 turned up") && message.contains("2 turned up"));
+    }
+
+    #[test]
+    fn boxes() {
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (3, 'c')]);
+        assert!(chi_square(&b, 10_000, 1e-6).passed);
+        let _ = *b;
+        assert_eq!(chi_square(&b, 100, 0.5).p_value, 1.0);
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 'z')]);
+        assert!(chi_square(&zero, 100, 0.5).passed);
+        assert_distribution!(zero, samples = 100, expected = {'z': 1.0}, tolerance = 0.0);
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::iter::FromIterator;
+use std::ops::{Index, IndexMut};
+use {collapse_all, EntangledGroup, SchroedingerBox, SuperpositionError};
+
+/// A vector of values that are each in their own superposition.
+///
+/// Indexing observes only the element that is looked at; the others stay undecided.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{SchroedingerBox, SchroedingerVec};
+/// let mut row: SchroedingerVec<char> = (0..8).map(|_| SchroedingerBox::new(vec!['#', '.']))
+///     .collect();
+/// let first = row[0];
+/// assert!(row.is_collapsed(0) && !row.is_collapsed(1));
+/// assert!((row.entropy() - 7.0 * 2f64.ln()).abs() < 1e-12);
+/// let tiles: String = row.collapse_all().into_iter().map(|&mut c| c).collect();
+/// assert!(tiles.starts_with(first));
+/// ```
+pub struct SchroedingerVec<T> {
+    boxes: Vec<SchroedingerBox<T>>,
+}
+
+impl<T> SchroedingerVec<T> {
+    /// Creates an empty vector.
+    pub fn new() -> SchroedingerVec<T> {
+        SchroedingerVec {
+            boxes: vec![],
+        }
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.boxes.len()
+    }
+
+    /// Returns whether there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    /// Adds an element to the end.
+    pub fn push(&mut self, b: SchroedingerBox<T>) {
+        self.boxes.push(b);
+    }
+
+    /// Observes the element at index `i`, or returns `None` if it is out of range.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the element can’t be observed.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.boxes.get(i).map(|b| &**b)
+    }
+
+    /// Like `get`, but gives a mutable reference.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.boxes.get_mut(i).map(|b| &mut **b)
+    }
+
+    /// Returns whether the element at index `i` has been decided, without observing it.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `i` is out of range.
+    pub fn is_collapsed(&self, i: usize) -> bool {
+        self.boxes[i].is_collapsed()
+    }
+
+    /// Returns the elements as boxes, without observing them.
+    pub fn boxes(&self) -> &[SchroedingerBox<T>] {
+        &self.boxes
+    }
+
+    /// Returns the elements as boxes mutably, without observing them.
+    pub fn boxes_mut(&mut self) -> &mut [SchroedingerBox<T>] {
+        &mut self.boxes
+    }
+
+    /// Observes every element, as `collapse_all` does, and returns references to them in order.
+    ///
+    /// # Panic
+    ///
+    /// Panics if any element can’t be observed.
+    pub fn collapse_all(&mut self) -> Vec<&mut T> {
+        collapse_all(&mut self.boxes)
+    }
+
+    /// Applies `f` to every state of every element, without observing any of them.
+    pub fn map_elements<U, F>(self, mut f: F) -> SchroedingerVec<U> where F: FnMut(T) -> U {
+        self.boxes.into_iter().map(|b| b.map(&mut f)).collect()
+    }
+
+    /// Returns the joint entropy of the elements, in nats, without observing them.
+    ///
+    /// The elements are independent, so this is the sum of their entropies. Like
+    /// `SchroedingerBox::entropy`, it freezes the weights of every element.
+    pub fn entropy(&self) -> f64 {
+        self.boxes.iter().map(SchroedingerBox::entropy).sum()
+    }
+
+    /// Consumes the vector, returning its elements as boxes.
+    pub fn into_vec(self) -> Vec<SchroedingerBox<T>> {
+        self.boxes
+    }
+
+    /// Entangles the elements, so that observing any of them decides all of them, as
+    /// `EntangledGroup::with_outcomes` does.
+    ///
+    /// Each outcome lists an index into every element’s states, and its weight is the product of
+    /// those states’ weights. Fails if no outcome can be chosen or the weights overflow.
+    ///
+    /// # Panic
+    ///
+    /// Panics if an outcome doesn’t have an index for every element, or an index is out of range.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{SchroedingerBox, SchroedingerVec};
+    /// let doors: SchroedingerVec<_> = (0..3).map(|_| SchroedingerBox::new(vec!["goat", "car"]))
+    ///     .collect();
+    /// // There is only one car.
+    /// let doors = doors.entangle(&[vec![1, 0, 0], vec![0, 1, 0], vec![0, 0, 1]]).unwrap();
+    /// let cars = doors.members().iter().filter(|&d| **d == "car").count();
+    /// assert_eq!(cars, 1);
+    /// ```
+    pub fn entangle(self, outcomes: &[Vec<usize>])
+            -> Result<EntangledGroup<T>, SuperpositionError> {
+        EntangledGroup::with_outcomes(self.boxes, outcomes)
+    }
+}
+
+impl<T> Default for SchroedingerVec<T> {
+    fn default() -> SchroedingerVec<T> {
+        SchroedingerVec::new()
+    }
+}
+
+impl<T> From<Vec<SchroedingerBox<T>>> for SchroedingerVec<T> {
+    fn from(boxes: Vec<SchroedingerBox<T>>) -> SchroedingerVec<T> {
+        SchroedingerVec {
+            boxes,
+        }
+    }
+}
+
+impl<T> FromIterator<SchroedingerBox<T>> for SchroedingerVec<T> {
+    fn from_iter<I>(boxes: I) -> SchroedingerVec<T> where I: IntoIterator<Item=SchroedingerBox<T>> {
+        SchroedingerVec::from(boxes.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T> Index<usize> for SchroedingerVec<T> {
+    type Output = T;
+
+    /// Observes the element at index `i`.
+    fn index(&self, i: usize) -> &T {
+        &self.boxes[i]
+    }
+}
+
+impl<T> IndexMut<usize> for SchroedingerVec<T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        &mut self.boxes[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use SchroedingerBox;
+    use super::SchroedingerVec;
+
+    #[test]
+    fn elements_collapse_separately() {
+        let mut v = SchroedingerVec::new();
+        assert!(v.is_empty());
+        v.push(SchroedingerBox::new(vec![1, 2]));
+        v.push(SchroedingerBox::from_probabilities(vec![(0, 3), (1, 4)]));
+        v[1] += 10;
+        assert_eq!(v.get(1), Some(&14));
+        assert!(!v.is_collapsed(0));
+        assert_eq!(v.get(2), None);
+        let v = v.map_elements(|x| x * 2);
+        assert!(!v.is_collapsed(0));
+        assert_eq!(v[1], 28);
+        assert_eq!(v.entropy(), 2f64.ln());
+        assert_eq!(v.into_vec().len(), 2);
+    }
+
+    #[test]
+    fn entangled_elements() {
+        let v: SchroedingerVec<_> = vec![SchroedingerBox::new(vec![0, 1]),
+                                         SchroedingerBox::new(vec![0, 1])].into();
+        let group = v.entangle(&[vec![0, 1], vec![1, 0]]).unwrap();
+        assert!(!group.is_collapsed());
+        assert_eq!(*group.members()[0] + *group.members()[1], 1);
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use {Observable, SchroedingerBox};
+
+/// A read-only view of a box that can’t observe it, as returned by `SchroedingerBox::frozen`.
+///
+/// A function taking a `FrozenView` instead of a `&SchroedingerBox` says in its signature that it
+/// only inspects the odds: nothing it does can collapse the box.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::{FrozenView, Observable, SchroedingerBox};
+/// fn report(cat: FrozenView<bool>) -> String {
+///     format!("{:.0}% alive", cat.probability_of(&true) * 100.0)
+/// }
+///
+/// let cat = SchroedingerBox::from_probabilities(vec![(3, true), (1, false)]);
+/// assert_eq!(report(cat.frozen()), "75% alive");
+/// assert_eq!(cat.peek(), None);
+/// ```
+pub struct FrozenView<'a, Cat: 'a> {
+    b: &'a SchroedingerBox<Cat>,
+}
+
+impl<'a, Cat> Clone for FrozenView<'a, Cat> {
+    fn clone(&self) -> FrozenView<'a, Cat> {
+        *self
+    }
+}
+
+impl<'a, Cat> Copy for FrozenView<'a, Cat> {}
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Returns a view of the box that can look at the odds but can’t observe the box.
+    ///
+    /// The view reveals the odds, so this freezes the weights.
+    pub fn frozen(&self) -> FrozenView<'_, Cat> {
+        self.odds();
+        FrozenView {
+            b: self,
+        }
+    }
+}
+
+impl<'a, Cat> FrozenView<'a, Cat> {
+    /// Returns the states that the box could still collapse into, with their weights.
+    ///
+    /// If the box has been observed, this is just the observed state.
+    pub fn states(&self) -> &'a [(u64, Cat)] {
+        self.b.states()
+    }
+
+    /// Returns the weights of the states, in the same order as `states`.
+    pub fn weights(&self) -> Vec<u64> {
+        self.states().iter().map(|&(w, _)| w).collect()
+    }
+
+    /// Returns the Shannon entropy of the odds, in nats.
+    ///
+    /// This is zero for a box that has been observed (or has only one state), and NaN for a box
+    /// that can’t be observed.
+    pub fn entropy(&self) -> f64 {
+        self.b.entropy()
+    }
+
+    /// Returns the probability that observing the box gives a value equal to `value`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    pub fn probability_of(&self, value: &Cat) -> f64 where Cat: PartialEq {
+        self.b.probability_of(value)
+    }
+
+    /// Obtains a reference to the value if the box has already been observed, as
+    /// `Observable::peek` does.
+    pub fn peek(&self) -> Option<&'a Cat> {
+        self.b.peek()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Observable, SchroedingerBox};
+
+    #[test]
+    fn looks_without_observing() {
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (1, 'b'), (2, 'c')]);
+        let view = b.frozen();
+        assert!(b.is_frozen());
+        assert_eq!(view.weights(), vec![1, 1, 2]);
+        assert_eq!(view.states()[2], (2, 'c'));
+        assert!((view.entropy() - 1.5 * 2f64.ln()).abs() < 1e-12);
+        assert_eq!(view.probability_of(&'c'), 0.5);
+        assert_eq!(view.peek(), None);
+        assert_eq!(b.peek(), None);
+        let observed = *b;
+        assert_eq!(view.states(), &[(if observed == 'c' { 2 } else { 1 }, observed)][..]);
+        assert_eq!(view.entropy(), 0.0);
+        assert_eq!(view.peek(), Some(&observed));
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::f64::consts::PI;
+use rand::Rng;
+use rng::local_rng;
+use {float, try_choose, SchroedingerBox, SuperpositionError};
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Probes the box gently, returning a noisy reading of `f` without collapsing the box.
+    ///
+    /// The reading is `f` of a state drawn from the box’s distribution, plus normally
+    /// distributed noise with a standard deviation of `1 / strength`, so on average it is the
+    /// expected value of `f`. The weights are then updated by how well each state agrees with the
+    /// reading: a weak measurement barely changes them, while a strong one leaves almost all of the
+    /// weight on the states whose values are close to the reading.
+    ///
+    /// This fails without touching the box if the weights are frozen, if the box can’t be
+    /// observed, or if `f` gives NaN.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `strength` isn’t positive and finite.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let mut b = SchroedingerBox::new(vec![0.0, 10.0]);
+    /// let reading = b.weak_measure(|&x| x, 0.01).unwrap();
+    /// assert!(reading.is_finite());
+    /// assert_eq!(b.peek(), None);
+    /// ```
+    pub fn weak_measure<F>(&mut self, f: F, strength: f64) -> Result<f64, SuperpositionError>
+            where F: Fn(&Cat) -> f64 {
+        assert!(strength > 0.0 && strength.is_finite(),
+                "the strength of a weak measurement must be positive and finite");
+        self.check_thawed()?;
+        let mut rng = local_rng();
+        let states = self.states();
+        let values: Vec<_> = states.iter().map(|(_, c)| f(c)).collect();
+        let i = if states.len() == 1 { 0 } else { try_choose(states, &mut rng)? };
+        let reading = values[i] + gaussian(&mut rng) / strength;
+        if reading.is_nan() {
+            return Err(SuperpositionError::InvalidWeight)
+        }
+        if states.len() == 1 {
+            return Ok(reading)
+        }
+        // How likely the reading is for each state, relative to the likeliest state that is still
+        // possible, so that they can’t all underflow to zero.
+        let likelihood: Vec<_> = values.iter()
+            .map(|x| -0.5 * ((x - reading) * strength).powi(2))
+            .collect();
+        let max = states.iter().zip(&likelihood)
+            .filter(|&(&(w, _), _)| w > 0)
+            .fold(f64::NEG_INFINITY, |max, (_, &l)| max.max(l));
+        let weights: Vec<_> = states.iter().zip(&likelihood)
+            .map(|(&(w, _), &l)| w as f64 * (l - max).exp())
+            .collect();
+        let weights = float::scale(&weights)?;
+        for (state, w) in self.states_mut().iter_mut().zip(weights) {
+            state.0 = w;
+        }
+        Ok(reading)
+    }
+}
+
+/// Draws a number from the standard normal distribution, by the Box–Muller transform.
+fn gaussian<R>(rng: &mut R) -> f64 where R: Rng {
+    let u = 1.0 - rng.gen::<f64>();
+    (-2.0 * u.ln()).sqrt() * (2.0 * PI * rng.gen::<f64>()).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use {Observable, SchroedingerBox, SuperpositionError};
+
+    #[test]
+    fn disturbs_the_weights() {
+        let mut b = SchroedingerBox::new(vec![0.0, 10.0]);
+        let reading = b.weak_measure(|&x| x, 1e-3).unwrap();
+        assert!(b.states().iter().all(|&(w, _)| w > 0));
+        assert_eq!(b.peek(), None);
+        let close = if reading < 5.0 { 0.0 } else { 10.0 };
+        assert!(b.probability_of(&close) >= 0.5);
+
+        let mut b = SchroedingerBox::new(vec![0.0, 10.0]);
+        let reading = b.weak_measure(|&x| x, 100.0).unwrap();
+        let close = if reading < 5.0 { 0.0 } else { 10.0 };
+        assert!((reading - close).abs() < 1.0);
+        assert_eq!(b.probability_of(&close), 1.0);
+        assert!(!b.is_collapsed());
+    }
+
+    #[test]
+    fn leaves_the_box_alone_on_failure() {
+        let mut b = SchroedingerBox::new(vec![1.0, 2.0]);
+        assert_eq!(b.weak_measure(|_| f64::NAN, 1.0), Err(SuperpositionError::InvalidWeight));
+        assert_eq!(b.states(), &[(1, 1.0), (1, 2.0)][..]);
+        b.probability_of(&1.0);
+        assert_eq!(b.weak_measure(|&x| x, 1.0).map(|_| ()), Err(SuperpositionError::WeightsFrozen));
+    }
+}
@@ -0,0 +1,801 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::mem::take;
+use {float, gcd, total_weight, total_weight_of, SchroedingerBox, SuperpositionError};
+
+/// Controls what happens when arithmetic on weights overflows a `u64`.
+///
+/// This applies to each weight computed by `amplify`, `update`, `mixture` and `merge_scaled`.
+/// Whatever the policy, the weights of a box must still add up to something that fits in a
+/// `u64`: under `Checked` and `Wrapping` a total that doesn’t is an error, while under
+/// `Saturating` every weight is scaled down until it fits.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum WeightArithmetic {
+    /// Overflow is an error, and the box is left as it was. This is the default.
+    Checked,
+    /// Overflowing weights are clamped to `u64::MAX`.
+    Saturating,
+    /// Overflowing weights wrap around.
+    Wrapping,
+}
+
+thread_local!(static ARITHMETIC: Cell<WeightArithmetic> =
+    const { Cell::new(WeightArithmetic::Checked) });
+
+/// Sets the `WeightArithmetic` for the current thread, returning the previous policy.
+pub fn set_weight_arithmetic(policy: WeightArithmetic) -> WeightArithmetic {
+    ARITHMETIC.with(|a| a.replace(policy))
+}
+
+impl WeightArithmetic {
+    fn current() -> WeightArithmetic {
+        ARITHMETIC.with(|a| a.get())
+    }
+
+    fn add(self, a: u64, b: u64) -> Result<u64, SuperpositionError> {
+        match self {
+            WeightArithmetic::Checked => a.checked_add(b).ok_or(SuperpositionError::WeightOverflow),
+            WeightArithmetic::Saturating => Ok(a.saturating_add(b)),
+            WeightArithmetic::Wrapping => Ok(a.wrapping_add(b)),
+        }
+    }
+
+    fn mul(self, a: u64, b: u64) -> Result<u64, SuperpositionError> {
+        match self {
+            WeightArithmetic::Checked => a.checked_mul(b).ok_or(SuperpositionError::WeightOverflow),
+            WeightArithmetic::Saturating => Ok(a.saturating_mul(b)),
+            WeightArithmetic::Wrapping => Ok(a.wrapping_mul(b)),
+        }
+    }
+
+    /// Checks that `weights` can be the weights of a box, scaling them down first if the policy
+    /// is `Saturating` and they add up to too much.
+    fn finish(self, weights: &mut [u64]) -> Result<(), SuperpositionError> {
+        if self == WeightArithmetic::Saturating {
+            let total = weights.iter().map(|&w| u128::from(w)).sum::<u128>();
+            let bits = 128 - total.leading_zeros();
+            if bits > 63 {
+                // Leave plenty of room for keeping small weights non-zero.
+                for w in weights.iter_mut() {
+                    *w = (*w >> (bits - 63)).max((*w).min(1));
+                }
+            }
+        }
+        if weights.len() != 1 {
+            total_weight_of(weights.iter().cloned())?;
+        }
+        Ok(())
+    }
+}
+
+impl<Cat> SchroedingerBox<Cat> {
+    /// Multiplies the weight of every state for which `f` returns `true` by `factor`, without
+    /// observing the box.
+    ///
+    /// Overflow is handled according to the current `WeightArithmetic`. If the result isn’t a
+    /// box that can be observed, or if the weights are frozen, the box is left as it was and the
+    /// error says why.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{SchroedingerBox, SuperpositionError};
+    /// let mut d6 = SchroedingerBox::new((1..7).collect::<Vec<u32>>());
+    /// d6.amplify(5, |&x| x == 6).unwrap();
+    /// // Six is now as likely as everything else put together.
+    /// assert_eq!(d6.amplify(u64::MAX, |&x| x == 6), Err(SuperpositionError::WeightOverflow));
+    /// ```
+    pub fn amplify<F>(&mut self, factor: u64, mut f: F) -> Result<(), SuperpositionError>
+            where F: FnMut(&Cat) -> bool {
+        self.update(|c| if f(c) { factor } else { 1 })
+    }
+
+    /// Multiplies the weight of every state by the likelihood `f` gives it, without observing the
+    /// box.
+    ///
+    /// This is a Bayesian update: the new probability of each state is proportional to its old
+    /// probability times its likelihood. Overflow is handled according to the current
+    /// `WeightArithmetic`. If the result isn’t a box that can be observed (say, if every
+    /// likelihood is zero), or if the weights are frozen, the box is left as it was and the error
+    /// says why.
+    pub fn update<F>(&mut self, mut f: F) -> Result<(), SuperpositionError>
+            where F: FnMut(&Cat) -> u64 {
+        self.check_thawed()?;
+        let policy = WeightArithmetic::current();
+        let states = self.states_mut();
+        let mut weights = states.iter().map(|&(w, ref c)| policy.mul(w, f(c)))
+            .collect::<Result<Vec<_>, _>>()?;
+        policy.finish(&mut weights)?;
+        for (s, w) in states.iter_mut().zip(weights) {
+            s.0 = w;
+        }
+        Ok(())
+    }
+
+    /// Like `update`, but with floating-point likelihoods, which may be fractions.
+    ///
+    /// The new weights are converted back to integers as `from_f64_weights` does, so they keep
+    /// their proportions to about 62 bits. If any likelihood is negative or NaN, if the result
+    /// isn’t a box that can be observed, or if the weights are frozen, the box is left as it was
+    /// and the error says why.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let mut hiding = SchroedingerBox::new(vec!["kitchen", "garden", "attic"]);
+    /// // The dog barks twice as often when someone is in the garden.
+    /// hiding.condition_on(|&room| if room == "garden" { 0.6 } else { 0.3 }).unwrap();
+    /// assert!((hiding.probability_of(&"garden") - 0.5).abs() < 1e-15);
+    /// ```
+    pub fn condition_on<F>(&mut self, mut likelihood: F) -> Result<(), SuperpositionError>
+            where F: FnMut(&Cat) -> f64 {
+        self.check_thawed()?;
+        let states = self.states_mut();
+        let weights: Vec<f64> = states.iter().map(|&(w, ref c)| w as f64 * likelihood(c)).collect();
+        let weights = float::scale(&weights)?;
+        if weights.len() != 1 {
+            total_weight_of(weights.iter().cloned())?;
+        }
+        for (s, w) in states.iter_mut().zip(weights) {
+            s.0 = w;
+        }
+        Ok(())
+    }
+
+    /// Evolves the odds by a stochastic matrix, without observing the box.
+    ///
+    /// `m[j][i]` is the chance of a box in state `i` moving to state `j`, so each new weight is
+    /// `Σ m[j][i] × w[i]`, with the states in the order `probabilities` gives them. The new
+    /// weights are converted back to integers as `condition_on` does. If any new weight is
+    /// negative or NaN, if the result isn’t a box that can be observed, if the box has already
+    /// been observed or if the weights are frozen, the box is left as it was and the error says
+    /// why.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `m` isn’t a square matrix with a row and a column for each state.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let mut weather = SchroedingerBox::new(vec!["sunny", "rainy"]);
+    /// // Sunny days stay sunny nine times in ten; rainy days clear up half the time.
+    /// weather.apply_stochastic(&[vec![0.9, 0.5], vec![0.1, 0.5]]).unwrap();
+    /// assert!((weather.probability_of(&"sunny") - 0.7).abs() < 1e-15);
+    /// ```
+    pub fn apply_stochastic(&mut self, m: &[Vec<f64>]) -> Result<(), SuperpositionError> {
+        if self.collapsed_at().is_some() {
+            return Err(SuperpositionError::AlreadyObserved)
+        }
+        self.check_thawed()?;
+        let states = self.states_mut();
+        let n = states.len();
+        assert!(m.len() == n && m.iter().all(|row| row.len() == n),
+                "a superposition of {} states needs a {} × {} matrix", n, n, n);
+        let weights: Vec<f64> = m.iter().map(|row| {
+            row.iter().zip(states.iter()).map(|(&p, &(w, _))| p * w as f64).sum()
+        }).collect();
+        let weights = float::scale(&weights)?;
+        if weights.len() != 1 {
+            total_weight_of(weights.iter().cloned())?;
+        }
+        for (s, w) in states.iter_mut().zip(weights) {
+            s.0 = w;
+        }
+        Ok(())
+    }
+
+    /// Replaces the weight of every state with the one `f` gives it, without observing the box.
+    ///
+    /// `f` is given each state and its current weight. If the result isn’t a box that can be
+    /// observed, or if the weights are frozen, the box is left as it was and the error says why.
+    pub fn scale_weights<F>(&mut self, mut f: F) -> Result<(), SuperpositionError>
+            where F: FnMut(&Cat, u64) -> u64 {
+        self.check_thawed()?;
+        let states = self.states_mut();
+        let weights: Vec<u64> = states.iter().map(|&(w, ref c)| f(c, w)).collect();
+        if weights.len() != 1 {
+            total_weight_of(weights.iter().cloned())?;
+        }
+        for (s, w) in states.iter_mut().zip(weights) {
+            s.0 = w;
+        }
+        Ok(())
+    }
+
+    /// Divides every weight by their greatest common divisor, without observing the box.
+    ///
+    /// The odds stay exactly the same, so this works even if the weights are frozen. Boxes that
+    /// are updated over and over should be normalised now and then, to keep the weights from
+    /// overflowing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let mut b = SchroedingerBox::new(vec!['a', 'b']);
+    /// // Evidence for `a` and evidence for `b`, over and over: without `normalize`, the weights
+    /// // would overflow long before the end.
+    /// for i in 0..100 {
+    ///     b.update(|&c| if (c == 'a') == (i % 2 == 0) { 1000 } else { 1 }).unwrap();
+    ///     b.normalize();
+    /// }
+    /// assert_eq!(b.probability_of(&'a'), 0.5);
+    /// ```
+    pub fn normalize(&mut self) {
+        let states = self.states_mut();
+        let common = states.iter().fold(0, |g, &(w, _)| gcd(g, w));
+        if common > 1 {
+            for s in states.iter_mut() {
+                s.0 /= common;
+            }
+        }
+    }
+
+    /// Tidies up the states without observing the box: states with a weight of zero are dropped,
+    /// equal states are merged into one with their weights added up, and the weights are divided
+    /// by their greatest common divisor, as `normalize` does.
+    ///
+    /// If the weights then add up to more than `max_total`, they are scaled down to fit, rounding
+    /// each one down but never to zero, and taking whatever rounding leaves over off the largest.
+    /// The total is then at most `max_total`, unless there are more states than that, in which
+    /// case every state is left with a weight of one. This changes the odds slightly, but leaves
+    /// room to combine the box with others (with `zip` or `and_then`, say) without overflowing.
+    /// It also mends a box whose weights add up to more than a `u64` can hold. If the weights are
+    /// frozen, the box is left as it was and an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let mut b = SchroedingerBox::from_probabilities(
+    ///     vec![(u64::MAX / 2, 'a'), (0, 'b'), (u64::MAX / 2, 'c'), (u64::MAX / 2, 'a')]);
+    /// b.compact(1000).unwrap();
+    /// assert_eq!(b.probability_of(&'b'), 0.0);
+    /// assert!((b.probability_of(&'a') - 2.0 / 3.0).abs() < 1e-3);
+    /// assert_eq!(b.zip(SchroedingerBox::new(vec![1, 2])).probabilities().len(), 4);
+    /// ```
+    pub fn compact(&mut self, max_total: u64) -> Result<(), SuperpositionError>
+            where Cat: PartialEq {
+        self.check_thawed()?;
+        let states = self.states_mut();
+        let mut merged: Vec<(u128, Cat)> = Vec::with_capacity(states.len());
+        for (w, c) in take(states) {
+            match merged.iter_mut().find(|s| s.1 == c) {
+                Some(s) => s.0 += u128::from(w),
+                None => merged.push((u128::from(w), c)),
+            }
+        }
+        if merged.iter().any(|s| s.0 > 0) {
+            merged.retain(|s| s.0 > 0);
+        }
+        let mut total = merged.iter().map(|s| s.0).sum::<u128>();
+        if total > u128::from(max_total) {
+            // Make sure that scaling can’t overflow, at the cost of some precision.
+            let shift = 64u32.saturating_sub(total.leading_zeros());
+            total >>= shift;
+            for s in &mut merged {
+                s.0 = ((s.0 >> shift) * u128::from(max_total) / total).max(1);
+            }
+            // Rounding small weights up to one can go over, so take the excess off the largest.
+            let mut excess = merged.iter().map(|s| s.0).sum::<u128>()
+                .saturating_sub(u128::from(max_total));
+            while excess > 0 {
+                let largest = merged.iter_mut().max_by_key(|s| s.0).map(|s| &mut s.0);
+                match largest {
+                    Some(w) if *w > 1 => {
+                        let cut = excess.min(*w - 1);
+                        *w -= cut;
+                        excess -= cut;
+                    },
+                    _ => break,
+                }
+            }
+        }
+        *states = merged.into_iter().map(|(w, c)| (w as u64, c)).collect();
+        let common = states.iter().fold(0, |g, &(w, _)| gcd(g, w));
+        if common > 1 {
+            for s in states.iter_mut() {
+                s.0 /= common;
+            }
+        }
+        Ok(())
+    }
+
+    /// Combines two boxes into one that behaves like `a` with probability proportional to
+    /// `weight_a`, and like `b` with probability proportional to `weight_b`.
+    ///
+    /// Overflow is handled according to the current `WeightArithmetic`.
+    pub fn mixture(a: SchroedingerBox<Cat>, weight_a: u64, b: SchroedingerBox<Cat>, weight_b: u64)
+            -> Result<SchroedingerBox<Cat>, SuperpositionError> {
+        let policy = WeightArithmetic::current();
+        let (mut a, mut b) = (a, b);
+        let (a, b) = (a.states_mut(), b.states_mut());
+        // A box with one state always gives it, whatever its weight.
+        for states in [&mut *a, &mut *b] {
+            if let [(ref mut w, _)] = **states {
+                *w = 1;
+            }
+        }
+        let total_a = total_weight_of(a.iter().map(|s| s.0))?;
+        let total_b = total_weight_of(b.iter().map(|s| s.0))?;
+        // Bring both boxes over the same total weight before weighting them.
+        let scale_a = policy.mul(weight_a, total_b)?;
+        let scale_b = policy.mul(weight_b, total_a)?;
+        let mut weights = Vec::with_capacity(a.len() + b.len());
+        for &(w, _) in a.iter() {
+            weights.push(policy.mul(w, scale_a)?);
+        }
+        for &(w, _) in b.iter() {
+            weights.push(policy.mul(w, scale_b)?);
+        }
+        policy.finish(&mut weights)?;
+        let states = a.drain(..).chain(b.drain(..)).zip(weights).map(|((_, c), w)| (w, c));
+        SchroedingerBox::try_from_probabilities(states.collect())
+    }
+
+    /// Combines the states of two boxes into one box, adding up the weights of equal states.
+    ///
+    /// Unlike `mixture`, the weights are used as they are, so a box with larger weights has more
+    /// say in the result. States keep the order in which they first appear.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the weights overflow.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let a = SchroedingerBox::from_probabilities(vec![(1, "rain"), (1, "sun")]);
+    /// let b = SchroedingerBox::from_probabilities(vec![(2, "sun"), (1, "snow")]);
+    /// let merged = a.merge(b);
+    /// assert_eq!(merged.probability_of(&"sun"), 0.6);
+    /// ```
+    pub fn merge(self, other: SchroedingerBox<Cat>) -> SchroedingerBox<Cat> where Cat: Eq + Hash {
+        self.merge_scaled(1, other, 1).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `merge`, but multiplies the weights of each box by a factor first.
+    ///
+    /// Overflow is handled according to the current `WeightArithmetic`.
+    pub fn merge_scaled(self, scale: u64, other: SchroedingerBox<Cat>, other_scale: u64)
+            -> Result<SchroedingerBox<Cat>, SuperpositionError> where Cat: Eq + Hash {
+        let policy = WeightArithmetic::current();
+        let (mut a, mut b) = (self, other);
+        let mut states = Vec::with_capacity(a.states_mut().len() + b.states_mut().len());
+        for (w, c) in a.states_mut().drain(..) {
+            states.push((policy.mul(w, scale)?, c));
+        }
+        for (w, c) in b.states_mut().drain(..) {
+            states.push((policy.mul(w, other_scale)?, c));
+        }
+        // Find where each state first appears, then add every weight to its first appearance.
+        let first: Vec<usize> = {
+            let mut seen = HashMap::new();
+            states.iter().enumerate().map(|(i, (_, c))| *seen.entry(c).or_insert(i)).collect()
+        };
+        let mut weights = vec![0; states.len()];
+        for (&f, &(w, _)) in first.iter().zip(&states) {
+            weights[f] = policy.add(weights[f], w)?;
+        }
+        let mut merged = vec![];
+        for (i, ((_, c), f)) in states.into_iter().zip(first).enumerate() {
+            if i == f {
+                merged.push((weights[i], c));
+            }
+        }
+        let mut weights: Vec<u64> = merged.iter().map(|s| s.0).collect();
+        policy.finish(&mut weights)?;
+        for (s, w) in merged.iter_mut().zip(weights) {
+            s.0 = w;
+        }
+        SchroedingerBox::try_from_probabilities(merged)
+    }
+
+    /// Replaces every state with the box of states that `f` gives for it, without observing
+    /// anything, so that each stage of a random outcome can depend on the last.
+    ///
+    /// The probability of each resulting state is the probability of the state it came from
+    /// times its probability in the box `f` gave; states that could never be chosen are left
+    /// out. Overflow is handled according to the current `WeightArithmetic`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the weights overflow, or if this box or any box `f` gives can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let tier = SchroedingerBox::from_probabilities(vec![(9, "common"), (1, "rare")]);
+    /// let item = tier.and_then(|t| match t {
+    ///     "common" => SchroedingerBox::new(vec!["stick", "stone", "string"]),
+    ///     _ => SchroedingerBox::new(vec!["sword"]),
+    /// });
+    /// assert_eq!(item.probability_of(&"stone"), 0.3);
+    /// assert_eq!(item.probability_of(&"sword"), 0.1);
+    /// ```
+    pub fn and_then<U, F>(self, f: F) -> SchroedingerBox<U>
+            where F: FnMut(Cat) -> SchroedingerBox<U> {
+        self.try_and_then(f).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Combines two independent boxes into a box of pairs, without observing either of them.
+    ///
+    /// The probability of each pair is the product of the probabilities of its states.
+    ///
+    /// # Panic
+    ///
+    /// Panics under the same conditions as `and_then`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::{Observable, SchroedingerBox};
+    /// let coin = SchroedingerBox::new(vec!["heads", "tails"]);
+    /// let d4 = SchroedingerBox::new(vec![1, 2, 3, 4]);
+    /// let both = coin.zip(d4);
+    /// assert_eq!(both.probability_of(&("tails", 4)), 0.125);
+    /// ```
+    pub fn zip<B>(self, mut other: SchroedingerBox<B>) -> SchroedingerBox<(Cat, B)>
+            where Cat: Clone, B: Clone {
+        let others = take(other.states_mut());
+        self.and_then(|a| SchroedingerBox::from_probabilities(
+            others.iter().map(|(w, b)| (*w, (a.clone(), b.clone()))).collect()))
+    }
+
+    fn try_and_then<U, F>(mut self, mut f: F) -> Result<SchroedingerBox<U>, SuperpositionError>
+            where F: FnMut(Cat) -> SchroedingerBox<U> {
+        let policy = WeightArithmetic::current();
+        let outer = take(self.states_mut());
+        let certain = outer.len() == 1;
+        if !certain {
+            total_weight(&outer)?;
+        }
+        let mut stages = vec![];
+        for (w, c) in outer.into_iter().filter(|s| certain || s.0 > 0) {
+            let mut inner = take(f(c).states_mut());
+            let total = if inner.len() == 1 {
+                // A state that can’t be anything else might as well have a weight of one.
+                inner[0].0 = 1;
+                1
+            } else {
+                total_weight(&inner)?
+            };
+            stages.push((if certain { 1 } else { w }, total, inner));
+        }
+        // Bring every stage over the same total weight, as `mixture` does.
+        let mut common = 1;
+        for &(_, total, _) in &stages {
+            common = policy.mul(common / gcd(common, total), total)?;
+        }
+        let mut states = vec![];
+        for (w, total, inner) in stages {
+            let scale = policy.mul(w, common / total)?;
+            for (v, c) in inner.into_iter().filter(|s| s.0 > 0) {
+                states.push((policy.mul(v, scale)?, c));
+            }
+        }
+        let mut weights: Vec<u64> = states.iter().map(|s| s.0).collect();
+        policy.finish(&mut weights)?;
+        let common = weights.iter().fold(0, |g, &w| gcd(g, w)).max(1);
+        for (s, w) in states.iter_mut().zip(weights) {
+            s.0 = w / common;
+        }
+        SchroedingerBox::try_from_probabilities(states)
+    }
+
+    /// Returns a fresh box of the states that are possible in this box but not in `other`,
+    /// keeping their relative weights, without observing either box.
+    ///
+    /// A state is possible if it is still left and has a non-zero weight, or is the only state
+    /// left. Returns `None` if no state is left.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let here = SchroedingerBox::new(vec![1, 2, 3, 4]);
+    /// let ruled_out = SchroedingerBox::new(vec![2, 4, 6]);
+    /// let left = here.difference(&ruled_out).unwrap();
+    /// assert!(*left == 1 || *left == 3);
+    /// assert!(here.difference(&here).is_none());
+    /// ```
+    pub fn difference(&self, other: &SchroedingerBox<Cat>) -> Option<SchroedingerBox<Cat>>
+            where Cat: Clone + Eq + Hash {
+        let ruled_out: HashSet<&Cat> = possible(other.states()).map(|(_, c)| c).collect();
+        let left: Vec<(u64, Cat)> = possible(self.states())
+            .filter(|&(_, c)| !ruled_out.contains(c))
+            .cloned().collect();
+        if left.is_empty() {
+            None
+        } else {
+            Some(SchroedingerBox::from_probabilities(left))
+        }
+    }
+
+    /// Compares the probabilities of every state that is possible in either box, without
+    /// observing either of them.
+    ///
+    /// Each state comes with its probability in this box divided by its probability in `other`:
+    /// infinity for states that are only possible here, and zero for states that are only
+    /// possible there. Equal states are counted together. This reveals the odds of both boxes,
+    /// which freezes their weights.
+    ///
+    /// # Panic
+    ///
+    /// Panics if either box can’t be observed.
+    pub fn likelihood_ratio<'a>(&'a self, other: &'a SchroedingerBox<Cat>) -> Vec<(&'a Cat, f64)>
+            where Cat: Eq + Hash {
+        let (here, there) = (probabilities(self.odds()), probabilities(other.odds()));
+        let mut order: Vec<&Cat> = here.iter().chain(&there).map(|&(c, _)| c).collect();
+        let mut seen = HashSet::new();
+        order.retain(|c| seen.insert(*c));
+        let (here, there): (HashMap<_, _>, HashMap<_, _>) =
+            (here.into_iter().collect(), there.into_iter().collect());
+        order.into_iter().map(|c| {
+            let p = here.get(c).map_or(0.0, |&p| p);
+            let q = there.get(c).map_or(0.0, |&q| q);
+            (c, if q == 0.0 { f64::INFINITY } else { p / q })
+        }).collect()
+    }
+}
+
+impl SchroedingerBox<f64> {
+    /// Returns whether two boxes of numbers have approximately the same distribution, without
+    /// observing either of them.
+    ///
+    /// Values within `value_tol` of each other are treated as the same. For every possible value
+    /// in either box, the probability of landing within `value_tol` of it must be the same in the
+    /// two boxes to within `prob_tol`. This reveals the odds of both boxes, which freezes their
+    /// weights.
+    ///
+    /// # Panic
+    ///
+    /// Panics if either box can’t be observed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use schroedinger_box::SchroedingerBox;
+    /// let a = SchroedingerBox::new(vec![0.1 + 0.2, 1.0]);
+    /// let b = SchroedingerBox::from_probabilities(vec![(500, 0.3), (499, 1.0), (1, 100.0)]);
+    /// assert!(a.approx_eq(&b, 1e-9, 0.01));
+    /// assert!(!a.approx_eq(&b, 1e-9, 0.0001));
+    /// ```
+    pub fn approx_eq(&self, other: &SchroedingerBox<f64>, value_tol: f64, prob_tol: f64) -> bool {
+        let (a, b) = (approx_probabilities(self.odds()), approx_probabilities(other.odds()));
+        let near = |dist: &[(f64, f64)], v: f64| {
+            dist.iter().filter(|&&(x, _)| (x - v).abs() <= value_tol).map(|&(_, p)| p).sum::<f64>()
+        };
+        a.iter().chain(&b).all(|&(v, _)| (near(&a, v) - near(&b, v)).abs() <= prob_tol)
+    }
+}
+
+/// Returns the probability of every possible number.
+fn approx_probabilities(states: &[(u64, f64)]) -> Vec<(f64, f64)> {
+    if let [(_, x)] = *states {
+        return vec![(x, 1.0)]
+    }
+    let total = total_weight(states).unwrap_or_else(|e| panic!("{}", e)) as f64;
+    states.iter().filter(|s| s.0 > 0).map(|&(w, x)| (x, w as f64 / total)).collect()
+}
+
+/// Returns the probability of every possible state, adding up equal states, in the order in which
+/// they first appear.
+fn probabilities<Cat>(states: &[(u64, Cat)]) -> Vec<(&Cat, f64)> where Cat: Eq + Hash {
+    if let [(_, ref c)] = *states {
+        return vec![(c, 1.0)]
+    }
+    let total = total_weight(states).unwrap_or_else(|e| panic!("{}", e)) as f64;
+    let mut index = HashMap::new();
+    let mut probabilities: Vec<(&Cat, f64)> = vec![];
+    for &(w, ref c) in states.iter().filter(|s| s.0 > 0) {
+        let i = *index.entry(c).or_insert_with(|| {
+            probabilities.push((c, 0.0));
+            probabilities.len() - 1
+        });
+        probabilities[i].1 += w as f64 / total;
+    }
+    probabilities
+}
+
+/// Returns the states that a box could collapse into: those with a non-zero weight, or the only
+/// state, whatever its weight.
+fn possible<Cat>(states: &[(u64, Cat)]) -> impl Iterator<Item=&(u64, Cat)> {
+    let only = states.len() == 1;
+    states.iter().filter(move |&&(w, _)| only || w > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use {SchroedingerBox, SuperpositionError};
+    use super::{set_weight_arithmetic, WeightArithmetic};
+
+    #[test]
+    fn checked_by_default() {
+        let mut b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (2, 'b')]);
+        b.amplify(3, |&c| c == 'a').unwrap();
+        assert_eq!(b.states(), &[(3, 'a'), (2, 'b')][..]);
+        assert_eq!(b.update(|_| 0), Err(SuperpositionError::ZeroTotalWeight));
+        assert_eq!(b.update(|_| u64::MAX), Err(SuperpositionError::WeightOverflow));
+        assert_eq!(b.states(), &[(3, 'a'), (2, 'b')][..]);
+    }
+
+    #[test]
+    fn saturating_and_wrapping() {
+        let old = set_weight_arithmetic(WeightArithmetic::Saturating);
+        let mut b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (2, 'b'), (4, 'c')]);
+        b.update(|&c| if c == 'a' { 1 } else { u64::MAX }).unwrap();
+        let weights: Vec<u64> = b.states().iter().map(|s| s.0).collect();
+        assert_eq!(weights[0], 1);
+        assert_eq!(weights[1], weights[2]);
+        assert!(weights[1] >= 1 << 61);
+
+        set_weight_arithmetic(WeightArithmetic::Wrapping);
+        let mut b = SchroedingerBox::from_probabilities(vec![(2, 'a'), (1, 'b')]);
+        b.amplify(1 << 63, |&c| c == 'a').unwrap();
+        assert_eq!(b.states(), &[(0, 'a'), (1, 'b')][..]);
+        set_weight_arithmetic(old);
+    }
+
+    #[test]
+    fn scaling_and_normalising() {
+        let mut b = SchroedingerBox::from_probabilities(vec![(2, 'a'), (4, 'b'), (0, 'c')]);
+        b.scale_weights(|&c, w| if c == 'c' { 6 } else { w * 3 }).unwrap();
+        assert_eq!(b.states(), &[(6, 'a'), (12, 'b'), (6, 'c')][..]);
+        b.normalize();
+        assert_eq!(b.states(), &[(1, 'a'), (2, 'b'), (1, 'c')][..]);
+        assert_eq!(b.scale_weights(|_, _| 0), Err(SuperpositionError::ZeroTotalWeight));
+    }
+
+    #[test]
+    fn compacting() {
+        let mut b = SchroedingerBox::from_probabilities(
+            vec![(2, 'a'), (0, 'b'), (4, 'c'), (2, 'a'), (0, 'b')]);
+        b.compact(u64::MAX).unwrap();
+        assert_eq!(b.states(), &[(1, 'a'), (1, 'c')][..]);
+        let mut b = SchroedingerBox::from_probabilities(vec![(u64::MAX, 1), (u64::MAX, 1), (1, 2)]);
+        b.compact(100).unwrap();
+        assert_eq!(b.states(), &[(99, 1), (1, 2)][..]);
+        let mut crowded = SchroedingerBox::from_probabilities(vec![(5, 'x'), (3, 'y'), (1, 'z')]);
+        crowded.compact(2).unwrap();
+        assert_eq!(crowded.states(), &[(1, 'x'), (1, 'y'), (1, 'z')][..]);
+        let mut zero = SchroedingerBox::from_probabilities(vec![(0, 'a'), (0, 'a'), (0, 'b')]);
+        zero.compact(100).unwrap();
+        assert_eq!(zero.states(), &[(0, 'a'), (0, 'b')][..]);
+        let _ = b.probabilities();
+        assert_eq!(b.compact(1), Err(SuperpositionError::WeightsFrozen));
+    }
+
+    #[test]
+    fn merging() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 'x'), (2, 'y'), (3, 'x')]);
+        let b = SchroedingerBox::from_probabilities(vec![(5, 'z'), (1, 'y')]);
+        let merged = a.merge_scaled(2, b, 3).unwrap();
+        assert_eq!(merged.states(), &[(8, 'x'), (7, 'y'), (15, 'z')][..]);
+
+        let observed = SchroedingerBox::new(vec!['p', 'q']);
+        let val = *observed;
+        let merged = observed.merge(SchroedingerBox::new(vec![val]));
+        assert_eq!(merged.states(), &[(2, val)][..]);
+        let huge = SchroedingerBox::from_probabilities(vec![(u64::MAX, 'a'), (0, 'b')]);
+        let err = huge.merge_scaled(1, SchroedingerBox::new(vec!['a']), 1);
+        assert_eq!(err.err(), Some(SuperpositionError::WeightOverflow));
+    }
+
+    #[test]
+    fn differences() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 'x'), (0, 'y'), (2, 'z'), (3, 'w')]);
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'w'), (0, 'z')]);
+        assert_eq!(a.difference(&b).unwrap().states(), &[(1, 'x'), (2, 'z')][..]);
+        // Observing `b` doesn’t change what’s possible in it.
+        assert_eq!(*b, 'w');
+        assert!(b.difference(&a).is_none());
+        let x = SchroedingerBox::new(vec!['x']);
+        assert_eq!(b.difference(&x).unwrap().states(), &[(1, 'w')][..]);
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 'x')]);
+        assert_eq!(zero.difference(&b).unwrap().states(), &[(0, 'x')][..]);
+        assert_eq!(a.difference(&zero).unwrap().states(), &[(2, 'z'), (3, 'w')][..]);
+    }
+
+    #[test]
+    fn likelihood_ratios() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 'x'), (1, 'y'), (2, 'x')]);
+        let b = SchroedingerBox::from_probabilities(vec![(1, 'y'), (1, 'z'), (0, 'w')]);
+        let ratios = a.likelihood_ratio(&b);
+        assert_eq!(ratios, vec![(&'x', f64::INFINITY), (&'y', 0.5), (&'z', 0.0)]);
+        assert!(a.is_frozen() && b.is_frozen());
+    }
+
+    #[test]
+    fn conditioning() {
+        let mut b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (2, 'b'), (3, 'c')]);
+        b.condition_on(|&c| if c == 'a' { 1.0 } else { 0.25 }).unwrap();
+        assert_eq!(b.states(), &[(4, 'a'), (2, 'b'), (3, 'c')][..]);
+        assert_eq!(b.condition_on(|_| 0.0), Err(SuperpositionError::ZeroTotalWeight));
+        assert_eq!(b.condition_on(|_| -1.0), Err(SuperpositionError::InvalidWeight));
+        assert_eq!(b.states(), &[(4, 'a'), (2, 'b'), (3, 'c')][..]);
+    }
+
+    #[test]
+    fn stochastic_evolution() {
+        let mut b = SchroedingerBox::from_probabilities(vec![(1, 'a'), (3, 'b')]);
+        b.apply_stochastic(&[vec![0.0, 1.0], vec![1.0, 0.0]]).unwrap();
+        assert_eq!(b.states(), &[(3, 'a'), (1, 'b')][..]);
+        assert_eq!(b.apply_stochastic(&[vec![0.0, 0.0], vec![0.0, 0.0]]),
+                   Err(SuperpositionError::ZeroTotalWeight));
+        assert_eq!(b.apply_stochastic(&[vec![-1.0, 0.0], vec![0.0, 1.0]]),
+                   Err(SuperpositionError::InvalidWeight));
+        assert_eq!(b.states(), &[(3, 'a'), (1, 'b')][..]);
+        let _ = *b;
+        assert_eq!(b.apply_stochastic(&[vec![1.0]]), Err(SuperpositionError::AlreadyObserved));
+    }
+
+    #[test]
+    fn approximately_equal() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 1.0), (1, 1.05), (2, 2.0)]);
+        let b = SchroedingerBox::from_probabilities(vec![(1, 1.02), (1, 2.01)]);
+        assert!(a.approx_eq(&b, 0.1, 1e-9));
+        assert!(b.approx_eq(&a, 0.1, 1e-9));
+        assert!(!a.approx_eq(&b, 0.001, 0.1));
+        let c = SchroedingerBox::from_probabilities(vec![(0, 5.0), (1, 1.0)]);
+        assert!(c.approx_eq(&SchroedingerBox::new(vec![1.0]), 0.0, 0.0));
+    }
+
+    #[test]
+    fn mixtures() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 1), (3, 2)]);
+        let b = SchroedingerBox::new(vec![3]);
+        let mix = SchroedingerBox::mixture(a, 1, b, 1).unwrap();
+        assert_eq!(mix.states(), &[(1, 1), (3, 2), (4, 3)][..]);
+        let a = SchroedingerBox::new(vec![1]);
+        let b = SchroedingerBox::new(vec![2]);
+        assert_eq!(SchroedingerBox::mixture(a, 0, b, 0).err(),
+                   Some(SuperpositionError::ZeroTotalWeight));
+        let zero = SchroedingerBox::from_probabilities(vec![(0, 1)]);
+        let b = SchroedingerBox::from_probabilities(vec![(1, 2), (1, 3)]);
+        let mix = SchroedingerBox::mixture(zero, 1, b, 1).unwrap();
+        assert_eq!(mix.states(), &[(2, 1), (1, 2), (1, 3)][..]);
+    }
+
+    #[test]
+    fn chaining() {
+        let b = SchroedingerBox::from_probabilities(vec![(1, 1), (0, 2), (2, 3)]);
+        let chained = b.and_then(|n| SchroedingerBox::from_probabilities(
+            (0..n).map(|i| (i as u64 + 1, n * 10 + i)).collect()));
+        // 1 -> 10 with probability 1/3; 3 -> 30, 31, 32 with probability 2/3 * (1, 2, 3)/6.
+        assert_eq!(chained.states(), &[(3, 10), (1, 30), (2, 31), (3, 32)][..]);
+        let certain = SchroedingerBox::new(vec![5]);
+        let _ = *certain;
+        let inner = certain.and_then(|n| SchroedingerBox::from_probabilities(vec![(0, n)]));
+        assert_eq!(inner.states(), &[(1, 5)][..]);
+        let old = set_weight_arithmetic(WeightArithmetic::Checked);
+        let huge = SchroedingerBox::from_probabilities(vec![(1, u64::MAX - 1), (1, u64::MAX - 2)]);
+        let result = huge.try_and_then(|n| SchroedingerBox::from_probabilities(
+            vec![(n, 'a'), (1, 'b')]));
+        assert_eq!(result.err(), Some(SuperpositionError::WeightOverflow));
+        set_weight_arithmetic(old);
+    }
+
+    #[test]
+    fn zipping() {
+        let a = SchroedingerBox::from_probabilities(vec![(1, 'a'), (0, 'b'), (3, 'c')]);
+        let b = SchroedingerBox::from_probabilities(vec![(2, 1), (4, 2)]);
+        let zipped = a.zip(b);
+        assert_eq!(zipped.states(),
+                   &[(1, ('a', 1)), (2, ('a', 2)), (3, ('c', 1)), (6, ('c', 2))][..]);
+    }
+}
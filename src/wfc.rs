@@ -0,0 +1,619 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The wave function collapse procedural generation algorithm.
+//!
+//! A `Grid` is a rectangle of `SchroedingerBox` cells, each of which starts out superposed over
+//! every tile. The `Solver` repeatedly observes the cell with the lowest entropy and propagates
+//! the consequences to its neighbours, backtracking when it runs into a contradiction.
+//!
+//! The propagation step is also available on its own as `propagate`, for those who would rather
+//! pick which cells to observe themselves.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use schroedinger_box::wfc::{Direction, Grid, Solver};
+//! // Stripes: a tile may only sit next to an equal tile vertically and a different one
+//! // horizontally.
+//! let mut grid = Grid::new(4, 3, vec![(1, 'x'), (1, 'o')]);
+//! let solver = Solver::new(|a: &char, dir: Direction, b: &char| match dir {
+//!     Direction::North | Direction::South => a == b,
+//!     Direction::East | Direction::West => a != b,
+//! });
+//! solver.run(&mut grid).unwrap();
+//! let tiles = grid.into_tiles();
+//! assert!(tiles[0] != tiles[1]);
+//! ```
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::mem::take;
+use rand::Rng;
+use rng::local_rng;
+use {entropy, SchroedingerBox};
+
+/// One of the four directions in which a cell can have a neighbour.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    /// All four directions, clockwise from north.
+    pub const ALL: [Direction; 4] =
+        [Direction::North, Direction::East, Direction::South, Direction::West];
+
+    /// Returns the direction pointing the opposite way.
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
+/// An adjacency constraint between tiles.
+///
+/// Implemented for any `Fn(&T, Direction, &T) -> bool`.
+pub trait Constraint<T> {
+    /// Returns whether tile `b` may be placed next to tile `a` in direction `dir` (so `b` is
+    /// north of `a` if `dir` is `North`).
+    fn allows(&self, a: &T, dir: Direction, b: &T) -> bool;
+}
+
+impl<T, F> Constraint<T> for F where F: Fn(&T, Direction, &T) -> bool {
+    fn allows(&self, a: &T, dir: Direction, b: &T) -> bool {
+        self(a, dir, b)
+    }
+}
+
+/// The error returned when some cell has no possible tiles left.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Contradiction {
+    /// The column of the offending cell.
+    pub x: usize,
+    /// The row of the offending cell.
+    pub y: usize,
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no tile fits at ({}, {})", self.x, self.y)
+    }
+}
+
+impl Error for Contradiction {}
+
+/// A set of adjacency rules, built up one allowed pair of neighbours at a time or learnt from a
+/// sample.
+///
+/// Rules are symmetric: allowing `b` north of `a` also allows `a` south of `b`.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::wfc::{Direction, Grid, Rules, Solver};
+/// # use schroedinger_box::wfc::Direction::*;
+/// #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+/// enum Tile { Sea, Coast, Land }
+///
+/// let rules = Rules::new()
+///     .allow_everywhere(Tile::Sea, Tile::Sea)
+///     .allow_everywhere(Tile::Sea, Tile::Coast)
+///     .allow_everywhere(Tile::Coast, Tile::Coast)
+///     .allow(Tile::Coast, North, Tile::Land)
+///     .allow_everywhere(Tile::Land, Tile::Land);
+/// let mut grid = Grid::new(8, 8, vec![(2, Tile::Sea), (1, Tile::Coast), (2, Tile::Land)]);
+/// Solver::new(rules).run(&mut grid).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Rules<T> where T: Eq + Hash {
+    allowed: HashSet<(T, Direction, T)>,
+}
+
+impl<T> Rules<T> where T: Clone + Eq + Hash {
+    /// Creates a set of rules that allows nothing.
+    pub fn new() -> Rules<T> {
+        Rules {
+            allowed: HashSet::new(),
+        }
+    }
+
+    /// Learns the rules from a sample, given as a row-major slice of `width` columns: every pair
+    /// of neighbours in the sample is allowed.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `width == 0` or `sample.len()` is not a multiple of `width`.
+    pub fn from_sample(width: usize, sample: &[T]) -> Rules<T> {
+        assert!(width > 0 && sample.len().is_multiple_of(width));
+        let mut rules = Rules::new();
+        for (i, t) in sample.iter().enumerate() {
+            if (i + 1) % width != 0 {
+                rules = rules.allow(t.clone(), Direction::East, sample[i + 1].clone());
+            }
+            if i + width < sample.len() {
+                rules = rules.allow(t.clone(), Direction::South, sample[i + width].clone());
+            }
+        }
+        rules
+    }
+
+    /// Allows `b` to be placed next to `a` in direction `dir`.
+    pub fn allow(mut self, a: T, dir: Direction, b: T) -> Rules<T> {
+        self.allowed.insert((b.clone(), dir.opposite(), a.clone()));
+        self.allowed.insert((a, dir, b));
+        self
+    }
+
+    /// Allows `a` and `b` to be placed next to each other in any direction.
+    pub fn allow_everywhere(self, a: T, b: T) -> Rules<T> {
+        Direction::ALL.iter().fold(self, |r, &dir| r.allow(a.clone(), dir, b.clone()))
+    }
+}
+
+impl<T> Default for Rules<T> where T: Clone + Eq + Hash {
+    fn default() -> Rules<T> {
+        Rules::new()
+    }
+}
+
+impl<T> Constraint<T> for Rules<T> where T: Clone + Eq + Hash {
+    fn allows(&self, a: &T, dir: Direction, b: &T) -> bool {
+        self.allowed.contains(&(a.clone(), dir, b.clone()))
+    }
+}
+
+/// Counts how often each tile occurs in a sample, for use as the tile weights of a `Grid`.
+pub fn sample_weights<T>(sample: &[T]) -> Vec<(u64, T)> where T: Clone + PartialEq {
+    let mut weights: Vec<(u64, T)> = vec![];
+    for t in sample {
+        match weights.iter_mut().find(|(_, u)| u == t) {
+            Some(w) => w.0 += 1,
+            None => weights.push((1, t.clone())),
+        }
+    }
+    weights
+}
+
+/// A rectangular grid of superposed tiles.
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<SchroedingerBox<T>>,
+    // Cells whose neighbours haven’t been pruned since they last changed.
+    dirty: Vec<usize>,
+}
+
+impl<T> Grid<T> where T: Clone {
+    /// Creates a new grid where every cell is superposed over all of the given weighted tiles.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `tiles.len() == 0`.
+    pub fn new(width: usize, height: usize, tiles: Vec<(u64, T)>) -> Grid<T> {
+        assert!(!tiles.is_empty());
+        Grid {
+            width,
+            height,
+            cells: (0..width * height)
+                .map(|_| SchroedingerBox::from_probabilities(tiles.clone()))
+                .collect(),
+            dirty: (0..width * height).collect(),
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// Returns the number of columns in the grid.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows in the grid.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the cell in column `x` and row `y`.
+    ///
+    /// Dereferencing the cell observes it, just like any other box; the solver will respect the
+    /// outcome on its next run. `propagate` won’t notice, though, so use `observe` instead when
+    /// driving propagation by hand.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the coordinates are out of bounds.
+    pub fn cell(&self, x: usize, y: usize) -> &SchroedingerBox<T> {
+        &self.cells[self.index(x, y)]
+    }
+
+    /// Returns the tiles still possible in column `x` and row `y`, and their weights, without
+    /// observing the cell.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the coordinates are out of bounds.
+    pub fn candidates(&self, x: usize, y: usize) -> &[(u64, T)] {
+        self.cells[self.index(x, y)].states()
+    }
+
+    /// Returns the coordinates of every cell with more than one possible tile left.
+    pub fn undetermined(&self) -> Vec<(usize, usize)> {
+        self.cells.iter().enumerate()
+            .filter_map(|(i, c)| if c.states().len() > 1 { Some(self.coords(i)) } else { None })
+            .collect()
+    }
+
+    /// Observes the cell in column `x` and row `y`, marking it for propagation.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the coordinates are out of bounds.
+    pub fn observe(&mut self, x: usize, y: usize) -> &T {
+        let idx = self.index(x, y);
+        self.dirty.push(idx);
+        &self.cells[idx]
+    }
+
+    /// Rules out the tiles in column `x` and row `y` for which `f` returns `false`, marking the
+    /// cell for propagation if anything changed.
+    ///
+    /// If no tiles would be left, or only several tiles that all weigh nothing (so that none of
+    /// them could be observed), the cell is not modified and a contradiction is returned instead.
+    ///
+    /// Unlike `SchroedingerBox::retain`, this rules tiles out even if the cell’s weights are
+    /// frozen, and so does propagation: a tile that doesn’t fit was never really possible, and a
+    /// grid that couldn’t be pruned once someone had looked at the odds couldn’t be solved.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the coordinates are out of bounds.
+    pub fn retain<F>(&mut self, x: usize, y: usize, mut f: F) -> Result<(), Contradiction>
+            where F: FnMut(&T) -> bool {
+        let idx = self.index(x, y);
+        let states = self.cells[idx].states_mut();
+        let keep: Vec<bool> = states.iter().map(|(_, t)| f(t)).collect();
+        let mut kept = states.iter().zip(&keep).filter(|&(_, &k)| k).map(|(&(w, _), _)| w);
+        let unobservable = match (kept.next(), kept.next()) {
+            (None, _) => true,
+            (Some(w), Some(v)) => w == 0 && v == 0 && kept.all(|w| w == 0),
+            (Some(_), None) => false,
+        };
+        if unobservable {
+            return Err(Contradiction { x, y });
+        }
+        if keep.contains(&false) {
+            let mut keep = keep.into_iter();
+            states.retain(|_| keep.next() == Some(true));
+            self.dirty.push(idx);
+        }
+        Ok(())
+    }
+
+    /// Collapses every cell, returning the tiles in row-major order.
+    pub fn into_tiles(self) -> Vec<T> {
+        self.cells.into_iter().map(SchroedingerBox::into_inner).collect()
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(x < self.width && y < self.height);
+        y * self.width + x
+    }
+
+    fn coords(&self, idx: usize) -> (usize, usize) {
+        (idx % self.width, idx / self.width)
+    }
+
+    fn neighbour(&self, idx: usize, dir: Direction) -> Option<usize> {
+        let (x, y) = self.coords(idx);
+        match dir {
+            Direction::North if y > 0 => Some(idx - self.width),
+            Direction::East if x + 1 < self.width => Some(idx + 1),
+            Direction::South if y + 1 < self.height => Some(idx + self.width),
+            Direction::West if x > 0 => Some(idx - 1),
+            _ => None,
+        }
+    }
+
+    fn contradiction(&self, idx: usize) -> Contradiction {
+        let (x, y) = self.coords(idx);
+        Contradiction { x, y }
+    }
+
+    fn propagate<C>(&mut self, constraint: &C) -> Result<(), Contradiction>
+            where C: Constraint<T> {
+        let mut queue = take(&mut self.dirty);
+        while let Some(idx) = queue.pop() {
+            for &dir in &Direction::ALL {
+                let n = match self.neighbour(idx, dir) {
+                    Some(n) => n,
+                    None => continue,
+                };
+                let states = take(self.cells[n].states_mut());
+                let before = states.len();
+                let kept: Vec<_> = {
+                    let here = self.cells[idx].states_mut();
+                    states.into_iter()
+                        .filter(|(_, b)| here.iter().any(|(_, a)| constraint.allows(a, dir, b)))
+                        .collect()
+                };
+                if kept.is_empty() {
+                    return Err(self.contradiction(n));
+                }
+                let changed = kept.len() != before;
+                *self.cells[n].states_mut() = kept;
+                if changed {
+                    queue.push(n);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> Grid<T> where T: Clone {
+    fn snapshot(&mut self) -> Vec<Vec<(u64, T)>> {
+        self.cells.iter_mut().map(|c| c.states_mut().clone()).collect()
+    }
+
+    fn restore(&mut self, snapshot: Vec<Vec<(u64, T)>>) {
+        for (cell, states) in self.cells.iter_mut().zip(snapshot) {
+            *cell.states_mut() = states;
+        }
+        self.dirty.clear();
+    }
+}
+
+/// Prunes tiles that no longer fit from the neighbours of every cell that has been observed or
+/// had tiles ruled out (through `Grid::observe` and `Grid::retain`) since the last propagation,
+/// and so on transitively.
+///
+/// A fresh grid counts as entirely changed. If some cell runs out of tiles the contradiction is
+/// returned, and the grid is left in an inconsistent state. Like `Grid::retain`, this prunes
+/// cells whose weights are frozen too.
+///
+/// # Example
+///
+/// ```rust
+/// # use schroedinger_box::wfc::{propagate, Direction, Grid};
+/// let same = |a: &u8, _: Direction, b: &u8| a == b;
+/// let mut grid = Grid::new(3, 3, vec![(1, 1), (1, 2), (1, 3)]);
+/// // Always observe the top-right cell first, because we can.
+/// let tile = *grid.observe(2, 0);
+/// propagate(&mut grid, &same).unwrap();
+/// assert_eq!(grid.candidates(0, 2), &[(1, tile)]);
+/// ```
+pub fn propagate<T, C>(grid: &mut Grid<T>, constraint: &C) -> Result<(), Contradiction>
+        where C: Constraint<T> {
+    grid.propagate(constraint)
+}
+
+/// A wave function collapse solver.
+pub struct Solver<C> {
+    constraint: C,
+    backtrack_limit: usize,
+}
+
+impl<C> Solver<C> {
+    /// Creates a new solver enforcing the given adjacency constraint.
+    pub fn new(constraint: C) -> Solver<C> {
+        Solver {
+            constraint,
+            backtrack_limit: 1000,
+        }
+    }
+
+    /// Sets the number of times the solver may backtrack before giving up. The default is 1000.
+    pub fn backtrack_limit(mut self, limit: usize) -> Solver<C> {
+        self.backtrack_limit = limit;
+        self
+    }
+
+    /// Collapses every cell in the grid into a tile consistent with the constraint.
+    ///
+    /// Cells that have already been observed are kept as they are. When an observation leads to
+    /// a contradiction, the grid is rolled back and the offending tile is ruled out for that
+    /// cell. If the grid cannot be solved (or the backtrack limit is reached) the last
+    /// contradiction is returned and the grid is left partially collapsed.
+    pub fn run<T>(&self, grid: &mut Grid<T>) -> Result<(), Contradiction>
+            where C: Constraint<T>, T: Clone + PartialEq {
+        let mut rng = local_rng();
+        let mut backtracks = 0;
+        let mut history = vec![];
+        grid.dirty = (0..grid.cells.len()).collect();
+        grid.propagate(&self.constraint)?;
+        loop {
+            // Pick the most constrained cell, breaking ties randomly.
+            let next = grid.cells.iter_mut().enumerate()
+                .filter_map(|(i, c)| match *c.states_mut() {
+                    ref s if s.len() > 1 => Some((entropy(s) + rng.gen::<f64>() * 1e-6, i)),
+                    _ => None,
+                })
+                .fold(None, |best: Option<(f64, usize)>, cur| match best {
+                    Some(b) if b.0 <= cur.0 => Some(b),
+                    _ => Some(cur),
+                });
+            let idx = match next {
+                Some((_, idx)) => idx,
+                None => return Ok(()),
+            };
+
+            // A cell left with only tiles that weigh nothing can't be observed, which is as much
+            // a contradiction as having no tiles at all.
+            let snapshot = grid.snapshot();
+            let mut result = match grid.cells[idx].try_collapse_with(&mut rng) {
+                Ok(tile) => {
+                    let tile = tile.clone();
+                    history.push((snapshot, idx, tile));
+                    grid.dirty.push(idx);
+                    grid.propagate(&self.constraint)
+                },
+                Err(_) => Err(grid.contradiction(idx)),
+            };
+            while let Err(e) = result {
+                let (snapshot, idx, tile) = match history.pop() {
+                    Some(h) if backtracks < self.backtrack_limit => h,
+                    _ => return Err(e),
+                };
+                backtracks += 1;
+                grid.restore(snapshot);
+                let (x, y) = grid.coords(idx);
+                result = grid.retain(x, y, |t| *t != tile)
+                    .and_then(|_| grid.propagate(&self.constraint));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{propagate, sample_weights, Contradiction, Direction, Grid, Rules, Solver};
+    use super::Constraint;
+
+    #[test]
+    fn checkerboard() {
+        let mut grid = Grid::new(5, 5, vec![(1, 0u8), (3, 1)]);
+        Solver::new(|a: &u8, _: Direction, b: &u8| a != b).run(&mut grid).unwrap();
+        let tiles = grid.into_tiles();
+        for y in 0..5 {
+            for x in 0..5 {
+                assert_eq!(tiles[y * 5 + x], tiles[0] ^ ((x + y) % 2) as u8);
+            }
+        }
+    }
+
+    #[test]
+    fn respects_observed_cells() {
+        let mut grid = Grid::new(3, 1, vec![(1, 'a'), (1, 'b'), (1, 'c')]);
+        let first = **grid.cell(0, 0);
+        Solver::new(|a: &char, _: Direction, b: &char| a == b).run(&mut grid).unwrap();
+        assert_eq!(grid.into_tiles(), vec![first; 3]);
+    }
+
+    #[test]
+    fn prunes_before_observing() {
+        // 'a' is allowed next to nothing, so it should never even be considered.
+        let mut grid = Grid::new(4, 4, vec![(1000, 'a'), (1, 'b')]);
+        Solver::new(|a: &char, _: Direction, b: &char| *a == 'b' && *b == 'b')
+            .run(&mut grid).unwrap();
+        assert!(grid.into_tiles().iter().all(|&t| t == 'b'));
+    }
+
+    #[test]
+    fn unsolvable() {
+        let mut grid = Grid::new(2, 1, vec![(1, ())]);
+        let res = Solver::new(|_: &(), _: Direction, _: &()| false).run(&mut grid);
+        assert!(res == Err(Contradiction { x: 1, y: 0 }) || res == Err(Contradiction { x: 0, y: 0 }));
+    }
+
+    #[test]
+    fn weightless_tiles() {
+        let no_c = |a: &char, _: Direction, b: &char| *a != 'c' && *b != 'c';
+        let mut grid = Grid::new(2, 1, vec![(0, 'a'), (0, 'b'), (1, 'c')]);
+        assert!(Solver::new(no_c).run(&mut grid).is_err());
+
+        let mut grid = Grid::new(2, 1, vec![(0, 'a'), (0, 'b'), (1, 'c')]);
+        assert_eq!(grid.retain(0, 0, |&t| t != 'c'), Err(Contradiction { x: 0, y: 0 }));
+        grid.retain(0, 0, |&t| t == 'a').unwrap();
+        assert_eq!(grid.candidates(0, 0), &[(0, 'a')]);
+    }
+
+    #[test]
+    fn retain_asks_once() {
+        use Observable;
+
+        let mut grid = Grid::new(1, 1, vec![(1, 'a'), (1, 'b'), (1, 'c'), (1, 'd')]);
+        assert_eq!(grid.cell(0, 0).probability_of(&'a'), 0.25);
+        // Keeps every other tile, which only works if each one is asked about exactly once.
+        let mut asked = 0;
+        grid.retain(0, 0, |_| { asked += 1; asked % 2 == 0 }).unwrap();
+        assert_eq!(grid.candidates(0, 0), &[(1, 'b'), (1, 'd')]);
+        assert_eq!(asked, 4);
+    }
+
+    #[test]
+    fn manual_propagation() {
+        let next = |a: &u8, dir: Direction, b: &u8| match dir {
+            Direction::East => *b == a + 1,
+            Direction::West => *b + 1 == *a,
+            _ => a == b,
+        };
+        let mut grid = Grid::new(3, 2, (0..5).map(|t| (1, t)).collect());
+        propagate(&mut grid, &next).unwrap();
+        assert_eq!(grid.candidates(0, 0).len(), 3);
+        assert_eq!(grid.undetermined().len(), 6);
+
+        grid.retain(1, 1, |&t| t == 3).unwrap();
+        assert_eq!(grid.retain(1, 1, |&t| t == 0), Err(Contradiction { x: 1, y: 1 }));
+        propagate(&mut grid, &next).unwrap();
+        assert!(grid.undetermined().is_empty());
+        assert_eq!(grid.into_tiles(), vec![2, 3, 4, 2, 3, 4]);
+
+        let mut grid = Grid::new(2, 1, vec![(1, 0), (1, 1)]);
+        grid.retain(0, 0, |&t| t == 1).unwrap();
+        assert_eq!(propagate(&mut grid, &next), Err(Contradiction { x: 1, y: 0 }));
+    }
+
+    #[test]
+    fn rules_are_symmetric() {
+        let rules = Rules::new().allow('a', Direction::North, 'b');
+        assert!(rules.allows(&'a', Direction::North, &'b'));
+        assert!(rules.allows(&'b', Direction::South, &'a'));
+        assert!(!rules.allows(&'a', Direction::South, &'b'));
+        assert!(!rules.allows(&'b', Direction::North, &'a'));
+    }
+
+    #[test]
+    fn rules_from_sample() {
+        let sample = "ab\
+                      cd".chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+        let rules = Rules::from_sample(2, &sample);
+        assert!(rules.allows(&'a', Direction::East, &'b'));
+        assert!(rules.allows(&'d', Direction::North, &'b'));
+        assert!(!rules.allows(&'b', Direction::East, &'c'));
+        assert!(!rules.allows(&'a', Direction::East, &'a'));
+        assert_eq!(sample_weights(&['x', 'y', 'x']), vec![(2, 'x'), (1, 'y')]);
+
+        // A sample that tiles must admit a solution.
+        let mut grid = Grid::new(4, 4, sample_weights(&"aabb".chars().collect::<Vec<_>>()));
+        Solver::new(Rules::from_sample(4, &"aabbaabb".chars().collect::<Vec<_>>()))
+            .run(&mut grid).unwrap();
+    }
+
+    #[test]
+    fn exhausts_backtracking() {
+        // Tiles are (row, colour). On a 2x2 grid, the top two colours must be equal and every
+        // other pair must differ. Every tile has support from its neighbours, so only search can
+        // find out that this is impossible.
+        let rule = |a: &(u8, bool), dir: Direction, b: &(u8, bool)| match dir {
+            Direction::South => a.0 == 0 && b.0 == 1 && a.1 != b.1,
+            Direction::North => a.0 == 1 && b.0 == 0 && a.1 != b.1,
+            _ => a.0 == b.0 && (a.1 == b.1) == (a.0 == 0),
+        };
+        let tiles = vec![(1, (0, true)), (1, (0, false)), (1, (1, true)), (1, (1, false))];
+        let mut grid = Grid::new(2, 2, tiles.clone());
+        assert!(Solver::new(rule).run(&mut grid).is_err());
+
+        // Making the bottom row agree as well makes it solvable.
+        let rule = |a: &(u8, bool), dir: Direction, b: &(u8, bool)| match dir {
+            Direction::East | Direction::West => a.0 == b.0 && a.1 == b.1,
+            _ => rule(a, dir, b),
+        };
+        let mut grid = Grid::new(2, 2, tiles);
+        Solver::new(rule).run(&mut grid).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::Cell;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+use rng::local_rng;
+use {SchroedingerBox, SuperpositionError};
+
+/// A box that is held in place by being watched: observations that come in quick succession only
+/// give a provisional state, and the box doesn’t really collapse until they stop.
+///
+/// The first observation picks a state, as usual, but doesn’t commit to it. Every observation
+/// within `window` of the previous one gives the same state and keeps it provisional; once a
+/// whole window passes without any, the provisional state becomes the real one, callbacks and
+/// all.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use schroedinger_box::{SchroedingerBox, ZenoBox};
+/// let watched = ZenoBox::new(SchroedingerBox::new(vec!["up", "down"]), Duration::from_secs(60));
+/// let first = *watched;
+/// assert_eq!(*watched, first);
+/// assert!(!watched.is_collapsed());
+/// // Once nothing can watch it any more, the box collapses for real.
+/// let b = watched.into_inner();
+/// assert!(b.is_collapsed());
+/// assert_eq!(*b, first);
+/// ```
+pub struct ZenoBox<Cat> {
+    inner: SchroedingerBox<Cat>,
+    window: Duration,
+    // The index of the provisional state, and when it was last observed.
+    provisional: Cell<Option<(usize, Instant)>>,
+}
+
+impl<Cat> ZenoBox<Cat> {
+    /// Wraps `b` so that observations within `window` of each other don’t collapse it.
+    pub fn new(b: SchroedingerBox<Cat>, window: Duration) -> ZenoBox<Cat> {
+        ZenoBox {
+            inner: b,
+            window,
+            provisional: Cell::new(None),
+        }
+    }
+
+    /// Observes the box, giving the provisional state if it is still being watched.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the box can’t be observed.
+    pub fn observe(&self) -> &Cat {
+        self.try_observe().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `observe`, but returns an error instead of panicking if the box can’t be observed.
+    pub fn try_observe(&self) -> Result<&Cat, SuperpositionError> {
+        self.try_observe_at(Instant::now())
+    }
+
+    /// Returns whether the box has really collapsed, without observing it.
+    ///
+    /// A provisional state is committed here if its window has passed.
+    pub fn is_collapsed(&self) -> bool {
+        self.is_collapsed_at(Instant::now())
+    }
+
+    /// Like `try_observe`, as if it were `now`.
+    fn try_observe_at(&self, now: Instant) -> Result<&Cat, SuperpositionError> {
+        if let Some(i) = self.watched(now) {
+            self.provisional.set(Some((i, now)));
+            return Ok(&self.inner._states[i].1)
+        }
+        if self.inner.is_collapsed() {
+            return self.inner.try_get()
+        }
+        // The box chooses the state as it would if it were observed, but doesn’t record it yet.
+        let i = self.inner.decide(&mut local_rng())?;
+        self.provisional.set(Some((i, now)));
+        Ok(&self.inner._states[i].1)
+    }
+
+    /// Like `is_collapsed`, as if it were `now`.
+    fn is_collapsed_at(&self, now: Instant) -> bool {
+        self.watched(now);
+        self.inner.is_collapsed()
+    }
+
+    /// Consumes the wrapper, returning the box inside.
+    ///
+    /// As nothing can watch the box any more, a provisional state is committed.
+    pub fn into_inner(self) -> SchroedingerBox<Cat> {
+        if let Some((i, _)) = self.provisional.take() {
+            self.commit(i);
+        }
+        self.inner
+    }
+
+    /// Returns the index of the provisional state, if it is still within its window, committing
+    /// it if it isn’t.
+    fn watched(&self, now: Instant) -> Option<usize> {
+        match self.provisional.get() {
+            Some((i, last)) if now < last + self.window => Some(i),
+            Some((i, _)) => {
+                self.provisional.set(None);
+                self.commit(i);
+                None
+            },
+            None => None,
+        }
+    }
+
+    /// Collapses the box into the state with index `i`.
+    fn commit(&self, i: usize) {
+        let _ = self.inner._chosen.set(i);
+        let _ = self.inner.try_collapse_with(&mut local_rng());
+    }
+}
+
+impl<Cat> Deref for ZenoBox<Cat> {
+    type Target = Cat;
+
+    /// Observes the box, as `observe` does.
+    fn deref(&self) -> &Cat {
+        self.observe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use std::sync::{Arc, Mutex};
+    use SchroedingerBox;
+    use super::ZenoBox;
+
+    #[test]
+    fn commits_once_observations_stop() {
+        let mut b = SchroedingerBox::new((0..100).collect::<Vec<u32>>());
+        let committed = Arc::new(Mutex::new(None));
+        let c = committed.clone();
+        b.on_collapse(move |&x| *c.lock().unwrap() = Some(x));
+        let window = Duration::from_millis(50);
+        let watched = ZenoBox::new(b, window);
+        let mut now = Instant::now();
+        let first = *watched.try_observe_at(now).unwrap();
+        for _ in 0..10 {
+            now += window / 2;
+            assert_eq!(*watched.try_observe_at(now).unwrap(), first);
+        }
+        assert!(!watched.is_collapsed_at(now + window / 2));
+        assert_eq!(*committed.lock().unwrap(), None);
+        now += window;
+        assert!(watched.is_collapsed_at(now));
+        assert_eq!(*committed.lock().unwrap(), Some(first));
+        assert_eq!(*watched.try_observe_at(now).unwrap(), first);
+        assert_eq!(watched.into_inner().observation_count(), 2);
+    }
+
+    #[test]
+    fn commits_when_unwrapped() {
+        let watched = ZenoBox::new(SchroedingerBox::new(vec!['a', 'b']), Duration::from_secs(60));
+        let first = *watched;
+        let b = watched.into_inner();
+        assert!(b.is_collapsed());
+        assert_eq!(*b, first);
+    }
+
+    #[test]
+    fn chooses_as_the_box_would() {
+        let seeded = *SchroedingerBox::with_seed((0..10).collect::<Vec<u32>>(), 42);
+        for _ in 0..10 {
+            let b = SchroedingerBox::with_seed((0..10).collect::<Vec<u32>>(), 42);
+            let watched = ZenoBox::new(b, Duration::from_secs(60));
+            assert_eq!(*watched, seeded);
+            assert_eq!(*watched.into_inner(), seeded);
+        }
+        let zero = ZenoBox::new(SchroedingerBox::from_probabilities(vec![(0, 'z')]),
+                                Duration::from_secs(60));
+        assert_eq!(zero.try_observe(), Ok(&'z'));
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright 2016 schroedinger_box developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Rigged observations are process-wide, so these get a test binary (and a test) of their own.
+#![cfg(feature = "test-util")]
+
+extern crate schroedinger_box;
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::thread;
+use std::time::Duration;
+use schroedinger_box::{ForcedOutcomes, SchroedingerBox, ZenoBox};
+
+#[test]
+fn forced_outcomes() {
+    let mut b = SchroedingerBox::new(vec![1, 2, 3]);
+    b.force_state(2);
+    assert_eq!(*b, 3);
+    b.force_state(2);
+    assert!(catch_unwind(AssertUnwindSafe(|| b.force_state(0))).is_err());
+    // Discarding the states that weren’t chosen doesn’t change which index was observed.
+    b.retain(|_| true).unwrap();
+    b.force_state(2);
+    assert!(catch_unwind(AssertUnwindSafe(|| b.force_state(0))).is_err());
+
+    let rigged = ForcedOutcomes::new(vec![1, 0, 2]);
+    assert_eq!(*SchroedingerBox::new(vec!['a', 'b']), 'b');
+    // Boxes with only one state don’t use anything up.
+    assert_eq!(*SchroedingerBox::new(vec!['z']), 'z');
+    assert_eq!(thread::spawn(|| *SchroedingerBox::new(vec!['a', 'b'])).join().unwrap(), 'a');
+    assert_eq!(rigged.remaining(), 1);
+    // A watched box takes its provisional state from the queue, and commits to that one.
+    let watched = ZenoBox::new(SchroedingerBox::new(vec!['x', 'y', 'w']), Duration::from_secs(60));
+    assert_eq!(*watched, 'w');
+    assert_eq!(*watched.into_inner(), 'w');
+    assert_eq!(rigged.remaining(), 0);
+    drop(rigged);
+    let free: Vec<u32> = (0..100).map(|_| *SchroedingerBox::new(vec![0, 1])).collect();
+    assert!(free.contains(&0) && free.contains(&1));
+}